@@ -0,0 +1,160 @@
+use clipanion_core::{fuzzy::{gen_random_command_line, gen_random_command_spec, gen_random_command_values, gen_random_keyword, gen_random_option_spec, gen_random_value}, CliBuilder, CommandSpec, Component, PositionalSpec, SelectionResult};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use rand::Rng;
+use rand_seeder::SipHasher;
+
+fn parse(cli_builder: &CliBuilder, args: &[&str]) -> usize {
+    let mut selector
+        = cli_builder.run(args).unwrap();
+
+    match selector.resolve_state(|_| Ok(())) {
+        Ok(SelectionResult::Command(_, state, _)) => state.values().len(),
+        _ => 0,
+    }
+}
+
+fn bench_random_command_lines(c: &mut Criterion, name: &str, determinize: bool) {
+    let mut rng = SipHasher::from("bench")
+        .into_rng();
+
+    let cases = (0..50).map(|_| {
+        let use_optional_positionals
+            = rng.random_bool(0.5);
+
+        let command_spec
+            = gen_random_command_spec(&mut rng, use_optional_positionals);
+
+        let command_values
+            = gen_random_command_values(&mut rng, &command_spec);
+
+        let command_line
+            = gen_random_command_line(&mut rng, &command_spec, &command_values);
+
+        (command_spec, command_line)
+    }).collect::<Vec<_>>();
+
+    let cli_builders = cases.iter().map(|(command_spec, _)| {
+        let mut cli_builder
+            = CliBuilder::new();
+
+        cli_builder.add_command(command_spec);
+        cli_builder.set_determinize(determinize);
+        cli_builder.precompile();
+        cli_builder
+    }).collect::<Vec<_>>();
+
+    c.bench_function(name, |b| {
+        b.iter(|| {
+            cases.iter().zip(&cli_builders).map(|((_, command_line), cli_builder)| {
+                let args
+                    = command_line.iter().map(|s| s.as_str()).collect::<Vec<_>>();
+
+                parse(cli_builder, &args)
+            }).sum::<usize>()
+        });
+    });
+}
+
+fn bench_long_rest_arguments(c: &mut Criterion, name: &str, determinize: bool) {
+    let mut rng = SipHasher::from("bench-rest")
+        .into_rng();
+
+    let mut components
+        = vec![Component::Positional(PositionalSpec::keyword("run"))];
+
+    for _ in 0..4 {
+        components.push(Component::Option(gen_random_option_spec(&mut rng, true)));
+    }
+
+    components.push(Component::Positional(PositionalSpec::rest()));
+
+    let command_spec = CommandSpec {
+        components,
+        ..Default::default()
+    };
+
+    let mut cli_builder
+        = CliBuilder::new();
+
+    cli_builder.add_command(&command_spec);
+    cli_builder.set_determinize(determinize);
+    cli_builder.precompile();
+
+    let mut group
+        = c.benchmark_group(name);
+
+    for len in [10, 100, 1000] {
+        let command_line
+            = std::iter::once("run".to_string())
+                .chain((0..len).map(|_| gen_random_value(&mut rng)))
+                .collect::<Vec<_>>();
+
+        let args
+            = command_line.iter().map(|s| s.as_str()).collect::<Vec<_>>();
+
+        group.throughput(Throughput::Elements(len as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(len), &args, |b, args| {
+            b.iter(|| parse(&cli_builder, args));
+        });
+    }
+
+    group.finish();
+}
+
+fn bench_namespaces(c: &mut Criterion) {
+    let mut rng = SipHasher::from("bench-namespaces")
+        .into_rng();
+
+    let command_specs = (0..50).flat_map(|namespace| {
+        (0..4).map(|_| CommandSpec {
+            primary_path: vec![format!("namespace-{}", namespace), gen_random_keyword(&mut rng)],
+            components: (0..4).map(|_| Component::Option(gen_random_option_spec(&mut rng, true))).collect(),
+            ..Default::default()
+        }).collect::<Vec<_>>()
+    }).collect::<Vec<_>>();
+
+    let mut cli_builder
+        = CliBuilder::new();
+
+    for command_spec in &command_specs {
+        cli_builder.add_command(command_spec);
+    }
+
+    let command_line
+        = command_specs[0].primary_path.clone();
+
+    let args
+        = command_line.iter().map(|s| s.as_str()).collect::<Vec<_>>();
+
+    let mut group
+        = c.benchmark_group("namespaces");
+
+    group.bench_function("full", |b| {
+        b.iter(|| {
+            let mut cli_builder
+                = cli_builder.clone();
+
+            cli_builder.precompile();
+            parse(&cli_builder, &args)
+        });
+    });
+
+    group.bench_function("dispatched", |b| {
+        b.iter(|| parse(&cli_builder, &args));
+    });
+
+    group.finish();
+}
+
+fn bench_nfa(c: &mut Criterion) {
+    bench_random_command_lines(c, "random_command_lines", false);
+    bench_long_rest_arguments(c, "long_rest_arguments", false);
+}
+
+fn bench_dfa(c: &mut Criterion) {
+    bench_random_command_lines(c, "random_command_lines_determinized", true);
+    bench_long_rest_arguments(c, "long_rest_arguments_determinized", true);
+}
+
+criterion_group!(benches, bench_nfa, bench_dfa, bench_namespaces);
+criterion_main!(benches);
@@ -1,15 +1,22 @@
-use std::{fmt::Display, iter::once, ops::Range};
+use std::{fmt::Display, iter::once, ops::Range, sync::{Arc, OnceLock}};
 
 use itertools::Itertools;
 
-use crate::{machine, runner::{self, DeriveState, RunnerState, ValidateTransition}, shared::{Arg, ArgKey, UserArg, ERROR_NODE_ID, INITIAL_NODE_ID, SUCCESS_NODE_ID}, CommandUsageResult, Error, Selector};
+use crate::{completion::{self, Completion}, dispatch::DispatchTrie, machine, runner::{self, DeriveState, RunnerState, ValidateTransition}, shared::{Arg, ArgKey, UserArg, CUSTOM_NODE_ID, ERROR_NODE_ID, INITIAL_NODE_ID, SUCCESS_NODE_ID}, suggestions::{self, Suggestion}, trace::TraceSink, trail::{Trail, ValueList}, CommandUsageOptions, CommandUsageResult, Error, Selector};
 
 #[cfg(test)]
-use crate::SelectionResult;
+use crate::{trace::{DropReason, TraceEvent}, SelectionResult};
 
 #[derive(Debug, Clone)]
 pub enum BuiltinCommand<'cmds, 'args> {
+    Complete(Vec<&'args str>, usize),
     Describe,
+
+    // The command line to explain, and whether to print the explanation as
+    // JSON rather than text
+    Explain(Vec<&'args str>, bool),
+
+    Manual(Vec<&'args str>),
     Tokenize(Vec<&'args str>),
     Version,
     Help(Vec<&'cmds CommandSpec>),
@@ -92,6 +99,30 @@ pub enum Token {
 pub struct TokenSet {
     pub command_id: usize,
     pub tokens: Vec<Token>,
+
+    // Where each argument was read from, for the ones that didn't come
+    // directly from the command line; indexed like the tokens' `arg_index`
+    pub sources: Vec<Option<ArgSource>>,
+}
+
+/**
+ * The location of an argument that was read from a file (for example a
+ * response file passed as `@path`) rather than from the command line.
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+#[cfg_attr(feature = "serde", derive(ts_rs::TS))]
+#[cfg_attr(feature = "serde", ts(export, export_to = "index.ts"))]
+pub struct ArgSource {
+    pub path: String,
+    pub line: usize,
+}
+
+impl std::fmt::Display for ArgSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.path, self.line)
+    }
 }
 
 #[derive(Clone, Debug, Default, PartialEq, Eq)]
@@ -100,20 +131,24 @@ pub struct State<'args> {
     pub node_id: usize,
     pub keyword_count: usize,
     pub path: Vec<&'args str>,
-    pub positional_values: Vec<(usize, Vec<UserArg<'args>>)>,
-    pub option_values: Vec<(usize, Vec<UserArg<'args>>)>,
+    pub positional_values: ValueList<'args>,
+    pub option_values: ValueList<'args>,
+
+    // The options filled by `Selector::apply_fallbacks` rather than by the
+    // command line; their values are given an index past the last argument
+    pub fallback_ids: Vec<usize>,
+
     pub post_double_dash: bool,
     pub is_help: bool,
 
     #[cfg(feature = "tokens")]
-    pub tokens: Vec<Token>,
+    pub tokens: Trail<Token>,
 }
 
 impl<'args> State<'args> {
     pub fn values(&self) -> Vec<(usize, Vec<&'args str>)> {
-        self.positional_values.clone()
-            .into_iter()
-            .chain(self.option_values.clone().into_iter())
+        self.positional_values.iter()
+            .chain(self.option_values.iter())
             .sorted_by_key(|(id, _)| *id)
             .map(|(id, values)| (id, values.iter().map(|s| s.value).collect()))
             .collect()
@@ -149,6 +184,50 @@ pub enum Check<'cmds> {
     IsOptionBinding(&'cmds str),
     IsNotOptionLike,
     IsBatch(Vec<char>),
+    IsBatchOption(Vec<char>, char),
+    IsBatchBinding(Vec<char>, char),
+    IsHyphenValue(Vec<&'cmds str>),
+    IsNotOptionLikeOrNumber(Vec<&'cmds str>),
+    IsUnknownOption(Vec<&'cmds str>),
+}
+
+/**
+ * Whether the argument would be parsed as one of the given options, either
+ * directly (`--foo`, `-f`), through a binding (`--foo=bar`), or as part of a
+ * batch (`-fv`, `-fbar`).
+ */
+fn is_known_option(names: &[&str], arg: &str) -> bool {
+    names.iter().any(|name| {
+        arg == *name
+            || (name.starts_with("--") && arg.strip_prefix(name).is_some_and(|rest| rest.starts_with('=')))
+            || (name.len() == 2 && !arg.starts_with("--") && arg.get(..2) == Some(name))
+    })
+}
+
+fn is_negative_number(arg: &str) -> bool {
+    arg.strip_prefix('-')
+        .filter(|number| number.starts_with(|c: char| c.is_ascii_digit() || c == '.'))
+        .is_some_and(|number| number.parse::<f64>().is_ok())
+}
+
+/**
+ * Split a batch of short options (`-xvf`) the way getopt does: the leading
+ * characters must be flags, and the first one that isn't must be the given
+ * option; return the byte offset right after it.
+ */
+fn split_batch(arg: &str, flags: &[char], option: char) -> Option<usize> {
+    if !arg.starts_with('-') || arg.starts_with("--") {
+        return None;
+    }
+
+    let (index, c) = arg.char_indices()
+        .skip(1)
+        .find(|(_, c)| !flags.contains(c))?;
+
+    match c == option {
+        true => Some(index + c.len_utf8()),
+        false => None,
+    }
 }
 
 impl<'cmds, 'args> ValidateTransition<'args, State<'args>> for Check<'cmds> {
@@ -173,6 +252,29 @@ impl<'cmds, 'args> ValidateTransition<'args, State<'args>> for Check<'cmds> {
             Check::IsBatch(batch) => {
                 !state.post_double_dash && arg.starts_with("-") && arg.len() > 2 && arg.chars().skip(1).all(|c| batch.contains(&c))
             },
+
+            // -xvf, with the value of -f in the next argument
+            Check::IsBatchOption(batch, option) => {
+                !state.post_double_dash && arg.len() > 2 && split_batch(arg, batch, *option) == Some(arg.len())
+            },
+
+            // -xvfarchive.tar, -farchive.tar, -f=archive.tar
+            Check::IsBatchBinding(batch, option) => {
+                !state.post_double_dash && split_batch(arg, batch, *option).is_some_and(|offset| offset < arg.len())
+            },
+
+            // Anything goes, except for the options that remain recognized
+            Check::IsHyphenValue(names) => {
+                state.post_double_dash || (arg != "--" && !is_known_option(names, arg))
+            },
+
+            Check::IsNotOptionLikeOrNumber(names) => {
+                state.post_double_dash || !arg.starts_with("-") || (is_negative_number(arg) && !is_known_option(names, arg))
+            },
+
+            Check::IsUnknownOption(names) => {
+                !state.post_double_dash && arg.starts_with("-") && arg != "--" && !is_known_option(names, arg)
+            },
         }
     }
 }
@@ -192,10 +294,37 @@ pub enum Reducer {
     PushValue(Attachment),
     BindValue(usize, usize),
     ResolveBatch(Vec<(char, usize)>),
+    ResolveBatchOption(Vec<(char, usize)>, usize),
+    ResolveBatchBinding(Vec<(char, usize)>, usize),
+    CollectOption(usize),
+}
+
+/**
+ * Push the flags at the start of the batch, stopping at the first character
+ * that isn't one of them; return the byte offset of that character.
+ */
+fn resolve_batch_flags<'args>(state: &mut State<'args>, batch: &[(char, usize)], user_arg: UserArg<'args>) -> usize {
+    for (batch_index, c) in user_arg.value.char_indices().skip(1) {
+        let Some((_, option_id)) = batch.iter().find(|(other_c, _)| c == *other_c) else {
+            return batch_index;
+        };
+
+        state.option_values.push((*option_id, vec![]));
+
+        #[cfg(feature = "tokens")] {
+            state.tokens.push(Token::Option {
+                arg_index: user_arg.index,
+                slice: if batch_index == 1 {0..2} else {batch_index..batch_index + c.len_utf8()},
+                component_id: *option_id,
+            });
+        }
+    }
+
+    user_arg.value.len()
 }
 
 impl<'args> DeriveState<'args, State<'args>> for Reducer {
-    fn derive(&self, state: &mut State<'args>, _target_id: usize, token: Arg<'args>) -> () {
+    fn derive(&self, state: &mut State<'args>, _target_id: usize, token: Arg<'args>) {
         match self {
             Reducer::EnableHelp => {
                 state.is_help = true;
@@ -272,41 +401,37 @@ impl<'args> DeriveState<'args, State<'args>> for Reducer {
             Reducer::PushValue(attachment) => {
                 match attachment {
                     Attachment::Option => {
-                        let Some((attachment_id, ref mut values)) = state.option_values.last_mut() else {
-                            panic!("No option value found");
-                        };
-
                         let Arg::User(user_arg) = token else {
                             panic!("Expected user argument");
                         };
 
-                        values.push(user_arg);
+                        let Some(attachment_id) = state.option_values.push_value(user_arg) else {
+                            panic!("No option value found");
+                        };
 
                         #[cfg(feature = "tokens")] {
                             state.tokens.push(Token::Value {
                                 arg_index: user_arg.index,
                                 slice: 0..user_arg.value.len(),
-                                component_id: *attachment_id,
+                                component_id: attachment_id,
                             });
                         }
                     },
 
                     Attachment::Positional => {
-                        let Some((attachment_id, ref mut values)) = state.positional_values.last_mut() else {
-                            panic!("No positional value found");
-                        };
-
                         let Arg::User(user_arg) = token else {
                             panic!("Expected user argument");
                         };
 
-                        values.push(user_arg);
+                        let Some(attachment_id) = state.positional_values.push_value(user_arg) else {
+                            panic!("No positional value found");
+                        };
 
                         #[cfg(feature = "tokens")] {
                             state.tokens.push(Token::Positional {
                                 arg_index: user_arg.index,
                                 slice: 0..user_arg.value.len(),
-                                component_id: *attachment_id,
+                                component_id: attachment_id,
                             });
                         }
                     },
@@ -349,24 +474,93 @@ impl<'args> DeriveState<'args, State<'args>> for Reducer {
                     panic!("Expected user argument");
                 };
 
-                for (batch_index, c) in user_arg.value.chars().enumerate().skip(1) {
-                    let Some((_, option_id)) = batch.iter().find(|(other_c, _)| c == *other_c) else {
-                        continue;
-                    };
+                resolve_batch_flags(state, batch, user_arg);
+            },
+
+            Reducer::ResolveBatchOption(batch, option_id) => {
+                let Arg::User(user_arg) = token else {
+                    panic!("Expected user argument");
+                };
+
+                let option_index
+                    = resolve_batch_flags(state, batch, user_arg);
+
+                state.option_values.push((*option_id, vec![]));
+
+                #[cfg(feature = "tokens")] {
+                    state.tokens.push(Token::Option {
+                        arg_index: user_arg.index,
+                        slice: if option_index == 1 {0..user_arg.value.len()} else {option_index..user_arg.value.len()},
+                        component_id: *option_id,
+                    });
+                }
+            },
+
+            Reducer::CollectOption(component_id) => {
+                let Arg::User(user_arg) = token else {
+                    panic!("Expected user argument");
+                };
+
+                state.option_values.push((*component_id, vec![user_arg]));
+
+                #[cfg(feature = "tokens")] {
+                    state.tokens.push(Token::Option {
+                        arg_index: user_arg.index,
+                        slice: 0..user_arg.value.len(),
+                        component_id: *component_id,
+                    });
+                }
+            },
+
+            Reducer::ResolveBatchBinding(batch, option_id) => {
+                let Arg::User(user_arg) = token else {
+                    panic!("Expected user argument");
+                };
+
+                let option_index
+                    = resolve_batch_flags(state, batch, user_arg);
+
+                let option_end = option_index + user_arg.value[option_index..].chars()
+                    .next().map_or(0, |c| c.len_utf8());
 
-                    state.option_values.push((*option_id, vec![]));
+                let value_start = match user_arg.value[option_end..].starts_with('=') {
+                    true => option_end + 1,
+                    false => option_end,
+                };
 
-                    #[cfg(feature = "tokens")] {
-                        state.tokens.push(Token::Option {
+                state.option_values.push((*option_id, vec![UserArg {
+                    value: &user_arg.value[value_start..],
+                    index: user_arg.index,
+                }]));
+
+                #[cfg(feature = "tokens")] {
+                    state.tokens.push(Token::Option {
+                        arg_index: user_arg.index,
+                        slice: if option_index == 1 {0..option_end} else {option_index..option_end},
+                        component_id: *option_id,
+                    });
+
+                    if value_start > option_end {
+                        state.tokens.push(Token::Assign {
                             arg_index: user_arg.index,
-                            slice: if batch_index == 0 {0..2} else {batch_index..batch_index + 1},
+                            slice: option_end..value_start,
                             component_id: *option_id,
                         });
                     }
+
+                    state.tokens.push(Token::Value {
+                        arg_index: user_arg.index,
+                        slice: value_start..user_arg.value.len(),
+                        component_id: *option_id,
+                    });
                 }
             },
         }
     }
+
+    fn describe(&self) -> Option<String> {
+        Some(format!("{:?}", self))
+    }
 }
 
 type Machine<'cmds>
@@ -385,16 +579,19 @@ pub enum PositionalSpec {
     Dynamic {
         name: String,
         documentation: Option<Documentation>,
+        choices: Vec<String>,
+        constraints: Vec<Constraint>,
 
         min_len: usize,
         extra_len: Option<usize>,
 
+        allow_hyphen_values: bool,
         is_prefix: bool,
         is_proxy: bool,
     },
 }
 
-fn format_range(f: &mut std::fmt::Formatter<'_>, name: &str, min_len: usize, extra_len: Option<usize>) -> std::fmt::Result {
+fn format_range(f: &mut impl std::fmt::Write, name: &str, min_len: usize, extra_len: Option<usize>) -> std::fmt::Result {
     if min_len > 0 {
         write!(f, "<{}>", name)?;
 
@@ -439,8 +636,11 @@ impl std::fmt::Display for PositionalSpec {
                 write!(f, "{}", expected)
             },
 
-            PositionalSpec::Dynamic {name, min_len, extra_len, ..} => {
-                format_range(f, name, *min_len, *extra_len)
+            PositionalSpec::Dynamic {name, choices, min_len, extra_len, ..} => {
+                match choices.is_empty() {
+                    true => format_range(f, name, *min_len, *extra_len),
+                    false => format_range(f, &choices.join("|"), *min_len, *extra_len),
+                }
             },
         }
     }
@@ -458,10 +658,13 @@ impl PositionalSpec {
             name: "".to_string(),
 
             documentation: None,
+            choices: vec![],
+            constraints: vec![],
 
             min_len: 0,
             extra_len: Some(1),
 
+            allow_hyphen_values: false,
             is_prefix: false,
             is_proxy: false,
         }
@@ -472,10 +675,13 @@ impl PositionalSpec {
             name: "".to_string(),
 
             documentation: None,
+            choices: vec![],
+            constraints: vec![],
 
             min_len: 1,
             extra_len: Some(0),
 
+            allow_hyphen_values: false,
             is_prefix: false,
             is_proxy: false,
         }
@@ -486,10 +692,13 @@ impl PositionalSpec {
             name: "".to_string(),
 
             documentation: None,
+            choices: vec![],
+            constraints: vec![],
 
             min_len: 0,
             extra_len: None,
 
+            allow_hyphen_values: false,
             is_prefix: false,
             is_proxy: false,
         }
@@ -500,10 +709,13 @@ impl PositionalSpec {
             name: "".to_string(),
 
             documentation: None,
+            choices: vec![],
+            constraints: vec![],
 
             min_len: 0,
             extra_len: None,
 
+            allow_hyphen_values: false,
             is_prefix: false,
             is_proxy: true,
         }
@@ -521,12 +733,17 @@ pub struct OptionSpec {
 
     pub documentation: Option<Documentation>,
     pub default_value: Option<String>,
+    pub env: Option<String>,
+    pub choices: Vec<String>,
+    pub constraints: Vec<Constraint>,
+    pub lifecycle: Lifecycle,
 
     pub min_len: usize,
     pub extra_len: Option<usize>,
 
     pub allow_binding: bool,
     pub allow_boolean: bool,
+    pub allow_hyphen_values: bool,
     pub is_hidden: bool,
     pub is_required: bool,
 }
@@ -558,12 +775,17 @@ impl OptionSpec {
 
             documentation: None,
             default_value: None,
+            env: None,
+            choices: vec![],
+            constraints: vec![],
 
             min_len: 0,
             extra_len: Some(0),
 
             allow_binding: false,
             allow_boolean: true,
+            allow_hyphen_values: false,
+            lifecycle: Lifecycle::default(),
             is_hidden: false,
             is_required: false,
         }
@@ -579,12 +801,17 @@ impl OptionSpec {
 
             documentation: None,
             default_value: None,
+            env: None,
+            choices: vec![],
+            constraints: vec![],
 
             min_len: 1,
             extra_len: Some(0),
 
             allow_binding: false,
             allow_boolean: false,
+            allow_hyphen_values: false,
+            lifecycle: Lifecycle::default(),
             is_hidden: false,
             is_required: true,
         }
@@ -594,29 +821,93 @@ impl OptionSpec {
         once(self.primary_name.as_str())
             .chain(self.aliases.iter().map(|alias| alias.as_str()))
     }
-}
 
-impl std::fmt::Display for OptionSpec {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        if self.is_required {
-            write!(f, "<{}", self.primary_name)?;
-        } else {
-            write!(f, "[{}", self.primary_name)?;
+    /**
+     * The names of the option followed by its value placeholders, without the
+     * brackets marking it as required or optional (for example
+     * `--output,-o <arg>`).
+     */
+    pub fn definition(&self) -> String {
+        let mut definition
+            = self.all_names().join(",");
+
+        if !self.is_flag() {
+            let value_name = match self.choices.is_empty() {
+                true => "arg".to_string(),
+                false => self.choices.join("|"),
+            };
+
+            definition.push(' ');
+
+            // Writing into a string can't fail
+            let _ = format_range(&mut definition, &value_name, self.min_len, self.extra_len);
         }
 
-        for alias in &self.aliases {
-            write!(f, ",{}", alias)?;
+        definition
+    }
+
+    /**
+     * Whether the option never takes a value; only those can appear anywhere
+     * in a batch of short options.
+     */
+    pub fn is_flag(&self) -> bool {
+        self.min_len == 0 && self.extra_len == Some(0)
+    }
+
+    /**
+     * The environment variable used as fallback when the option isn't set on
+     * the command line; either the one explicitly set on the option, or one
+     * derived from its long name when the program defines a prefix (for
+     * example `--dry-run` becomes `MYTOOL_DRY_RUN`).
+     */
+    pub fn env_var_name(&self, env_prefix: Option<&str>) -> Option<String> {
+        if self.env.is_some() {
+            return self.env.clone();
         }
 
-        if self.min_len > 0 || self.extra_len != Some(0) {
-            write!(f, " ")?;
-            format_range(f, "arg", self.min_len, self.extra_len)?;
+        if self.is_hidden {
+            return None;
         }
 
+        let env_prefix
+            = env_prefix?;
+
+        let long_name = self.all_names()
+            .find(|name| name.starts_with("--"))?;
+
+        let suffix = long_name[2..]
+            .to_uppercase()
+            .replace('-', "_");
+
+        Some(format!("{}_{}", env_prefix, suffix))
+    }
+}
+
+impl std::fmt::Display for OptionSpec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         if self.is_required {
-            write!(f, ">")
+            write!(f, "<{}>", self.definition())
         } else {
-            write!(f, "]")
+            write!(f, "[{}]", self.definition())
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+#[cfg_attr(feature = "serde", derive(ts_rs::TS))]
+#[cfg_attr(feature = "serde", ts(export_to = "index.ts"))]
+pub struct PassthroughSpec {
+    pub name: String,
+    pub documentation: Option<Documentation>,
+}
+
+impl PassthroughSpec {
+    pub fn new<T: Into<String>>(name: T) -> Self {
+        PassthroughSpec {
+            name: name.into(),
+            documentation: None,
         }
     }
 }
@@ -629,6 +920,12 @@ impl std::fmt::Display for OptionSpec {
 pub enum Component {
     Positional(PositionalSpec),
     Option(OptionSpec),
+
+    /**
+     * Collects the option-like arguments that don't match any option of
+     * the command, so that they can be forwarded to another program.
+     */
+    Passthrough(PassthroughSpec),
 }
 
 impl Component {
@@ -638,6 +935,30 @@ impl Component {
             _ => None,
         }
     }
+
+    /**
+     * The values accepted by the component, if restricted to a fixed set.
+     */
+    pub fn choices(&self) -> &[String] {
+        match self {
+            Component::Option(spec) => &spec.choices,
+            Component::Positional(PositionalSpec::Dynamic {choices, ..}) => choices,
+            Component::Positional(PositionalSpec::Keyword {..}) => &[],
+            Component::Passthrough(_) => &[],
+        }
+    }
+
+    /**
+     * The checks the component values must pass once parsed.
+     */
+    pub fn constraints(&self) -> &[Constraint] {
+        match self {
+            Component::Option(spec) => &spec.constraints,
+            Component::Positional(PositionalSpec::Dynamic {constraints, ..}) => constraints,
+            Component::Positional(PositionalSpec::Keyword {..}) => &[],
+            Component::Passthrough(_) => &[],
+        }
+    }
 }
 
 impl std::fmt::Display for Component {
@@ -648,6 +969,64 @@ impl std::fmt::Display for Component {
 
             Component::Option(spec)
                 => write!(f, "{}", spec),
+
+            Component::Passthrough(spec)
+                => write!(f, "[--…{}]", spec.name),
+        }
+    }
+}
+
+/**
+ * Where a command or option stands in its release cycle. Deprecated items
+ * still work but print a warning, while experimental ones are refused unless
+ * the user opts into them.
+ */
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+#[cfg_attr(feature = "serde", derive(ts_rs::TS))]
+#[cfg_attr(feature = "serde", ts(export, export_to = "index.ts"))]
+pub struct Lifecycle {
+    pub deprecated: Option<String>,
+    pub is_experimental: bool,
+    pub since: Option<String>,
+}
+
+/**
+ * A check applied to the values of an option or positional argument. Bounds
+ * are kept as written in the command definition so they can be displayed
+ * as-is; lengths are counted in characters.
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(tag = "type", rename_all = "camelCase"))]
+#[cfg_attr(feature = "serde", derive(ts_rs::TS))]
+#[cfg_attr(feature = "serde", ts(export, export_to = "index.ts"))]
+pub enum Constraint {
+    Min {
+        value: String,
+    },
+    Max {
+        value: String,
+    },
+    Len {
+        min: usize,
+        max: Option<usize>,
+    },
+    Pattern {
+        pattern: String,
+    },
+}
+
+impl std::fmt::Display for Constraint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Constraint::Min {value} => write!(f, "at least {}", value),
+            Constraint::Max {value} => write!(f, "at most {}", value),
+            Constraint::Len {min, max: Some(max)} if min == max => write!(f, "exactly {} characters", min),
+            Constraint::Len {min, max: Some(max)} => write!(f, "between {} and {} characters", min, max),
+            Constraint::Len {min, max: None} => write!(f, "at least {} characters", min),
+            Constraint::Pattern {pattern} => write!(f, "a value matching `{}`", pattern),
         }
     }
 }
@@ -662,6 +1041,41 @@ pub struct Example {
     pub description: String,
 }
 
+/**
+ * A relation between the options of a command, checked once the command line
+ * has been parsed. Options are referenced by their component id.
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase", rename_all_fields = "camelCase", tag = "type"))]
+#[cfg_attr(feature = "serde", derive(ts_rs::TS))]
+#[cfg_attr(feature = "serde", ts(export, export_to = "index.ts"))]
+pub enum OptionGroup {
+    // At most one of the options may be set
+    Exclusive {
+        options: Vec<usize>,
+    },
+    // At least one of the options must be set
+    AtLeastOne {
+        options: Vec<usize>,
+    },
+    // If the option is set, all the requirements must be set as well
+    Requires {
+        option: usize,
+        requirements: Vec<usize>,
+    },
+    // The option must be set if any of the conditions is set
+    RequiredIf {
+        option: usize,
+        conditions: Vec<usize>,
+    },
+    // The option must be set unless any of the conditions is set
+    RequiredUnless {
+        option: usize,
+        conditions: Vec<usize>,
+    },
+}
+
 #[derive(Clone, Debug, Default, PartialEq, Eq)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize))]
 #[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
@@ -675,6 +1089,9 @@ pub struct CommandSpec {
     pub examples: Vec<Example>,
     pub components: Vec<Component>,
     pub required_options: Vec<usize>,
+    pub option_groups: Vec<OptionGroup>,
+    pub lifecycle: Lifecycle,
+    pub is_hidden: bool,
 }
 
 impl std::fmt::Display for CommandSpec {
@@ -688,7 +1105,7 @@ impl std::fmt::Display for CommandSpec {
             = prefix_components.into_iter()
                 .map(|component| component.to_string())
                 .chain(self.primary_path.iter().map(|segment| segment.to_string()))
-                .chain(suffix_components.into_iter().map(|component| component.to_string()));
+                .chain(suffix_components.into_iter().filter_map(|component| self.format_grouped_component(component)));
 
         format_collection(f, components, " ")?;
 
@@ -697,35 +1114,144 @@ impl std::fmt::Display for CommandSpec {
 }
 
 impl CommandSpec {
+    /**
+     * Exclusive options are displayed together as `[--json | --yaml]` at the
+     * position of the first one; the others are skipped.
+     */
+    fn format_grouped_component(&self, component: &Component) -> Option<String> {
+        let Component::Option(_) = component else {
+            return Some(component.to_string());
+        };
+
+        let component_id = self.components.iter()
+            .position(|candidate| std::ptr::eq(candidate, component))?;
+
+        let group = self.option_groups.iter().find_map(|group| match group {
+            OptionGroup::Exclusive {options} if options.contains(&component_id) => Some(options),
+            _ => None,
+        });
+
+        let Some(group) = group else {
+            return Some(component.to_string());
+        };
+
+        let visible_options = group.iter()
+            .filter_map(|option_id| self.components[*option_id].is_option())
+            .filter(|option| !option.is_hidden)
+            .collect::<Vec<_>>();
+
+        if !visible_options.first().is_some_and(|option| std::ptr::eq(*option, component.is_option().unwrap())) {
+            return None;
+        }
+
+        let alternatives = visible_options.iter()
+            .map(|option| option.definition())
+            .collect::<Vec<_>>();
+
+        Some(format!("[{}]", alternatives.join(" | ")))
+    }
+
+    /**
+     * Whether the option is the `--no-*` counterpart generated for another
+     * option of the command.
+     */
+    pub fn is_negation(&self, option: &OptionSpec) -> bool {
+        let Some(negated_name) = option.primary_name.strip_prefix("--no-") else {
+            return false;
+        };
+
+        self.components.iter()
+            .filter_map(|component| component.is_option())
+            .any(|other| other.all_names().any(|name| name.strip_prefix("--") == Some(negated_name)))
+    }
+
+    /**
+     * Return the component id of the option with the given name.
+     */
+    pub fn option_id(&self, name: &str) -> Option<usize> {
+        self.components.iter()
+            .position(|component| component.is_option().is_some_and(|option| option.all_names().any(|option_name| option_name == name)))
+    }
+
     pub fn is_default(&self) -> bool {
         self.primary_path.is_empty() || self.aliases.iter().any(|path| path.is_empty())
     }
 
+    /**
+     * Return a copy of the spec where the options without an explicit
+     * environment variable get the one derived from the given prefix.
+     */
+    pub fn with_env_prefix(&self, env_prefix: Option<&str>) -> CommandSpec {
+        let mut command_spec
+            = self.clone();
+
+        for component in &mut command_spec.components {
+            if let Component::Option(option) = component {
+                option.env = option.env_var_name(env_prefix);
+            }
+        }
+
+        command_spec
+    }
+
     pub fn usage(&self) -> CommandUsageResult {
         CommandUsageResult::new(self.clone())
     }
 
+    pub fn usage_with(&self, opts: CommandUsageOptions) -> CommandUsageResult {
+        CommandUsageResult::with_options(self.clone(), opts)
+    }
+
     pub fn build(&'_ self, command_id: usize) -> Machine<'_> {
-        CommandBuilderContext::new(&self, command_id).build()
+        self.build_with_options(command_id, &ParserOptions::default())
+    }
+
+    pub fn build_with_options(&'_ self, command_id: usize, options: &ParserOptions) -> Machine<'_> {
+        CommandBuilderContext::new(self, command_id, options).build()
     }
 }
 
+/**
+ * Parser settings shared by all the commands of a program.
+ */
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ParserOptions {
+    /**
+     * Accept arguments that look like negative numbers (`-5`, `-0.5`) as
+     * values, as long as no option of the command has that name.
+     */
+    pub negative_numbers: bool,
+
+    /**
+     * Determinize the compiled state machine, so that the runner tracks a
+     * single state for the nodes it would otherwise visit in lockstep. The
+     * parse results are the same either way.
+     */
+    pub determinize: bool,
+}
+
 pub struct CommandBuilderContext<'cmds> {
     machine: Machine<'cmds>,
     spec: &'cmds CommandSpec,
     batch_check: Vec<char>,
     batch_resolve: Vec<(char, usize)>,
+    option_names: Vec<&'cmds str>,
+    hyphen_values: Option<Vec<&'cmds str>>,
+    passthrough_id: Option<usize>,
     inhibit_options: usize,
     proxy_options: usize,
+    negative_numbers: bool,
     has_option_h: bool,
     has_option_help: bool,
 }
 
 impl<'cmds> CommandBuilderContext<'cmds> {
-    fn new(spec: &'cmds CommandSpec, command_id: usize) -> Self {
-        // -v,--verbose => vec!["v"]
+    fn new(spec: &'cmds CommandSpec, command_id: usize, options: &ParserOptions) -> Self {
+        // -v,--verbose => vec!["v"]; only options without values can be
+        // batched freely, the others have to come last
         let batch_check = spec.components.iter()
             .filter_map(|component| component.is_option())
+            .filter(|option| option.is_flag())
             .flat_map(|option| option.all_names())
             .filter(|name| name.starts_with("-") && !name.starts_with("--"))
             .flat_map(|name| name.chars().skip(1))
@@ -735,11 +1261,24 @@ impl<'cmds> CommandBuilderContext<'cmds> {
         let batch_resolve = spec.components.iter()
             .enumerate()
             .filter_map(|(component_id, component)| component.is_option().map(|option| (option, component_id)))
+            .filter(|(option, _)| option.is_flag())
             .flat_map(|(option, component_id)| option.all_names().map(move |name| (name, component_id)))
             .filter(|(name, _)| name.starts_with("-") && !name.starts_with("--"))
-            .map(|(name, component_id)| (name.chars().skip(1).next().unwrap(), component_id))
+            .map(|(name, component_id)| (name.chars().nth(1).unwrap(), component_id))
+            .collect::<Vec<_>>();
+
+        // The names that still mean an option when values may start with
+        // a hyphen; -h and --help stay reserved for the help builtin
+        let option_names = spec.components.iter()
+            .filter_map(|component| component.is_option())
+            .flat_map(|option| option.all_names())
+            .chain(["-h", "--help"])
+            .unique()
             .collect::<Vec<_>>();
 
+        let passthrough_id = spec.components.iter()
+            .position(|component| matches!(component, Component::Passthrough(_)));
+
         let has_option_h = spec.components.iter()
             .filter_map(|component| component.is_option())
             .flat_map(|option| option.all_names())
@@ -755,8 +1294,12 @@ impl<'cmds> CommandBuilderContext<'cmds> {
             spec,
             batch_check,
             batch_resolve,
+            option_names,
+            hyphen_values: None,
+            passthrough_id,
             inhibit_options: 0,
             proxy_options: 0,
+            negative_numbers: options.negative_numbers,
             has_option_h,
             has_option_help,
         }
@@ -778,9 +1321,21 @@ impl<'cmds> CommandBuilderContext<'cmds> {
         self.proxy_options -= 1;
     }
 
+    fn enter_hyphen_values(&mut self, hyphen_values: Option<Vec<&'cmds str>>) -> Option<Vec<&'cmds str>> {
+        std::mem::replace(&mut self.hyphen_values, hyphen_values)
+    }
+
+    fn exit_hyphen_values(&mut self, hyphen_values: Option<Vec<&'cmds str>>) {
+        self.hyphen_values = hyphen_values;
+    }
+
     fn get_positional_check(&self) -> Option<Check<'cmds>> {
         if self.proxy_options > 0 {
             None
+        } else if let Some(names) = &self.hyphen_values {
+            Some(Check::IsHyphenValue(names.clone()))
+        } else if self.negative_numbers {
+            Some(Check::IsNotOptionLikeOrNumber(self.option_names.clone()))
         } else {
             Some(Check::IsNotOptionLike)
         }
@@ -813,6 +1368,15 @@ impl<'cmds> CommandBuilderContext<'cmds> {
             Some(Reducer::ResolveBatch(self.batch_resolve.clone())),
         );
 
+        if let Some(passthrough_id) = self.passthrough_id {
+            self.machine.register_dynamic(
+                pre_options_node_id,
+                Some(Check::IsUnknownOption(self.option_names.clone())),
+                pre_options_node_id,
+                Some(Reducer::CollectOption(passthrough_id)),
+            );
+        }
+
         let options = self.spec.components.iter()
             .enumerate()
             .filter_map(|(i, component)| match component {
@@ -839,6 +1403,22 @@ impl<'cmds> CommandBuilderContext<'cmds> {
                 let accepts_arguments
                     = option.min_len > 0 || option.extra_len != Some(0);
 
+                // -xvf archive.tar; the option ends the batch and takes its
+                // values from the following arguments
+                let short_name = match name.len() == 2 && name.starts_with('-') && name != "--" {
+                    true => name.chars().nth(1),
+                    false => None,
+                };
+
+                if let Some(c) = short_name.filter(|_| accepts_arguments) {
+                    self.machine.register_dynamic(
+                        pre_options_node_id,
+                        Some(Check::IsBatchOption(self.batch_check.clone(), c)),
+                        post_option_node_id,
+                        Some(Reducer::ResolveBatchOption(self.batch_resolve.clone(), option_id)),
+                    );
+                }
+
                 if option.allow_boolean && accepts_arguments && option.min_len > 0 {
                     self.machine.register_shortcut(
                         post_option_node_id,
@@ -849,6 +1429,11 @@ impl<'cmds> CommandBuilderContext<'cmds> {
                 if accepts_arguments {
                     self.enter_inhibit_options();
 
+                    // Option values are always taken as-is when allowed
+                    // to start with a hyphen, even if they name an option
+                    let hyphen_values
+                        = self.enter_hyphen_values(option.allow_hyphen_values.then(Vec::new));
+
                     post_option_node_id = self.attach_variadic(
                         post_option_node_id,
                         option.min_len,
@@ -857,15 +1442,26 @@ impl<'cmds> CommandBuilderContext<'cmds> {
                         Reducer::PushValue(Attachment::Option),
                     );
 
+                    self.exit_hyphen_values(hyphen_values);
                     self.exit_inhibit_options();
 
                     if option.min_len + option.extra_len.unwrap_or(0) == 1 {
-                        self.machine.register_dynamic(
-                            pre_options_node_id,
-                            Some(Check::IsOptionBinding(name)),
-                            post_option_node_id,
-                            Some(Reducer::BindValue(name.len(), option_id)),
-                        );
+                        if let Some(c) = short_name {
+                            // -ofile, -o=file, -xvofile
+                            self.machine.register_dynamic(
+                                pre_options_node_id,
+                                Some(Check::IsBatchBinding(self.batch_check.clone(), c)),
+                                post_option_node_id,
+                                Some(Reducer::ResolveBatchBinding(self.batch_resolve.clone(), option_id)),
+                            );
+                        } else {
+                            self.machine.register_dynamic(
+                                pre_options_node_id,
+                                Some(Check::IsOptionBinding(name)),
+                                post_option_node_id,
+                                Some(Reducer::BindValue(name.len(), option_id)),
+                            );
+                        }
                     }
                 }
 
@@ -1005,11 +1601,14 @@ impl<'cmds> CommandBuilderContext<'cmds> {
                         = self.attach_options(next_node_id, false);
                 },
 
-                PositionalSpec::Dynamic {min_len, extra_len, is_proxy, ..} => {
+                PositionalSpec::Dynamic {min_len, extra_len, allow_hyphen_values, is_proxy, ..} => {
                     if *is_proxy {
                         self.enter_proxy_options();
                     }
 
+                    let hyphen_values
+                        = self.enter_hyphen_values(allow_hyphen_values.then(|| self.option_names.clone()));
+
                     current_node_id = self.attach_variadic(
                         current_node_id,
                         *min_len,
@@ -1018,6 +1617,8 @@ impl<'cmds> CommandBuilderContext<'cmds> {
                         Reducer::PushValue(Attachment::Positional),
                     );
 
+                    self.exit_hyphen_values(hyphen_values);
+
                     if *is_proxy {
                         self.exit_proxy_options();
                     }
@@ -1120,47 +1721,179 @@ impl<'cmds> CommandBuilderContext<'cmds> {
 #[derive(Clone)]
 pub struct CliBuilder<'cmds> {
     commands: Vec<&'cmds CommandSpec>,
+    options: ParserOptions,
+
+    // Set by `precompile` and shared between the clones of the builder;
+    // reset whenever the commands or the options change
+    machine: Option<Arc<Machine<'cmds>>>,
+
+    // Built on the first run that isn't precompiled, and shared between the
+    // clones of the builder; reset whenever the commands change
+    dispatch_trie: Arc<OnceLock<DispatchTrie>>,
+}
+
+impl<'cmds> Default for CliBuilder<'cmds> {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl<'cmds> CliBuilder<'cmds> {
     pub fn new() -> Self {
         CliBuilder {
             commands: vec![],
+            options: ParserOptions::default(),
+            machine: None,
+            dispatch_trie: Default::default(),
         }
     }
 
+    /**
+     * Treat arguments such as `-5` or `-0.5` as values rather than options,
+     * unless the command defines an option with that name.
+     */
+    pub fn set_negative_numbers(&mut self, enabled: bool) -> &mut Self {
+        self.options.negative_numbers = enabled;
+        self.machine = None;
+        self
+    }
+
+    /**
+     * Determinize the state machine when compiling it; see
+     * `Machine::determinize`.
+     */
+    pub fn set_determinize(&mut self, enabled: bool) -> &mut Self {
+        self.options.determinize = enabled;
+        self.machine = None;
+        self
+    }
+
     pub fn add_command(&mut self, spec: &'cmds CommandSpec) -> &mut Self {
         self.commands.push(spec);
+        self.machine = None;
+        self.dispatch_trie = Default::default();
+        self
+    }
+
+    /**
+     * Return the registered commands, indexed by command id.
+     */
+    pub fn commands(&self) -> &[&'cmds CommandSpec] {
+        &self.commands
+    }
+
+    /**
+     * Compile the state machine ahead of time; the builder and its clones
+     * then reuse it instead of compiling it again on each run.
+     */
+    pub fn precompile(&mut self) -> &mut Self {
+        self.machine = Some(Arc::new(self.compile()));
         self
     }
 
+    /**
+     * Return the precompiled state machine if there's one, or compile it.
+     */
+    pub fn machine(&self) -> Arc<Machine<'cmds>> {
+        match &self.machine {
+            Some(machine) => machine.clone(),
+            None => Arc::new(self.compile()),
+        }
+    }
+
     pub fn compile(&self) -> Machine<'cmds> {
+        self.compile_commands(&(0..self.commands.len()).collect::<Vec<_>>())
+    }
+
+    /**
+     * Compile a state machine that only recognizes the given commands; the
+     * states it produces still refer to them by their ids in the builder.
+     */
+    fn compile_commands(&self, command_ids: &[usize]) -> Machine<'cmds> {
         let command_machines: Vec<Machine<'cmds>>
-            = self.commands.iter()
-                .enumerate()
-                .map(|(command_id, &command)| command.build(command_id))
+            = command_ids.iter()
+                .map(|&command_id| self.commands[command_id].build_with_options(command_id, &self.options))
                 .collect::<Vec<_>>();
 
         let mut machine
             = Machine::new_any_of(command_machines);
 
+        // The node contexts are positions in the list of machines we just
+        // merged, but the selector expects command ids
+        for node in machine.nodes.iter_mut().skip(CUSTOM_NODE_ID) {
+            node.context = command_ids[node.context];
+        }
+
         machine.simplify_machine();
+
+        if self.options.determinize {
+            if let Some(determinized_machine) = machine.determinize() {
+                return determinized_machine;
+            }
+        }
+
         machine
     }
 
+    /**
+     * Compile a state machine for the commands reachable from the keywords
+     * the command line starts with (see `DispatchTrie`), or return `None` if
+     * it doesn't start with any.
+     */
+    fn compile_dispatched(&self, args: &[&str]) -> Option<Machine<'cmds>> {
+        let dispatch_trie
+            = self.dispatch_trie.get_or_init(|| DispatchTrie::new(&self.commands));
+
+        dispatch_trie.dispatch(args)
+            .map(|command_ids| self.compile_commands(&command_ids))
+    }
+
     pub fn run_partial<'args>(&self, args: &[&'args str]) -> Vec<State<'args>> {
         fn on_error<'args>(mut state: State<'args>, _: Arg<'args>) -> State<'args> {
             state.set_node_id(ERROR_NODE_ID);
             state
         }
 
+        let states: Vec<State<'args>>
+            = runner::Runner::run_partial(&self.machine(), on_error, args);
+
+        states
+    }
+
+    /**
+     * List the tokens that could be inserted at the given position of the
+     * command line. Arguments before the cursor are parsed as usual, the one
+     * under the cursor (if any) is used as a prefix filter, and the ones after
+     * it are ignored.
+     */
+    pub fn complete<'args>(&self, args: &[&'args str], cursor: usize) -> Vec<Completion> {
+        fn on_error<'args>(mut state: State<'args>, _: Arg<'args>) -> State<'args> {
+            state.set_node_id(ERROR_NODE_ID);
+            state
+        }
+
+        let cursor
+            = cursor.min(args.len());
+
+        let partial
+            = args.get(cursor).copied().unwrap_or("");
+
         let machine
-            = self.compile();
+            = self.machine();
 
         let states: Vec<State<'args>>
-            = runner::Runner::run_partial(&machine, on_error, args);
+            = runner::Runner::run_partial(&machine, on_error, &args[..cursor]);
 
-        states
+        completion::collect_completions(&machine, &self.commands, &states, partial)
+    }
+
+    /**
+     * Find out which arguments are likely to be typos of a command path or
+     * option name; used to build the "did you mean" errors and to implement
+     * autocorrection.
+     */
+    pub fn suggest(&self, args: &[&str]) -> Vec<Suggestion<'cmds>> {
+        suggestions::suggest(&self.commands, args)
     }
 
     pub fn run<'args>(&self, args: &[&'args str]) -> Result<Selector<'cmds, 'args>, Error<'cmds>> {
@@ -1169,11 +1902,40 @@ impl<'cmds> CliBuilder<'cmds> {
             state
         }
 
-        let machine
-            = self.compile();
+        // Without a precompiled machine we only compile the commands the
+        // command line can reach; help and errors are still reported from
+        // the full machine
+        let dispatched_states: Option<Vec<State<'args>>>
+            = self.machine.is_none()
+                .then(|| self.compile_dispatched(args))
+                .flatten()
+                .map(|machine| runner::Runner::run(&machine, on_error, args))
+                .filter(|states| states.iter().any(|state| state.node_id == SUCCESS_NODE_ID));
+
+        let states: Vec<State<'args>> = match dispatched_states {
+            Some(states) => states,
+            None => runner::Runner::run(&self.machine(), on_error, args),
+        };
+
+        let selector: Selector<'cmds, 'args>
+            = Selector::new(self.commands.clone(), args.to_vec(), states);
+
+        Ok(selector)
+    }
+
+    /**
+     * Same as `run`, but report each transition the runner takes to the
+     * given sink. Traced runs always go through the full state machine, so
+     * that the trace shows every command competing for the arguments.
+     */
+    pub fn run_with_trace<'args>(&self, args: &[&'args str], trace: &mut dyn TraceSink) -> Result<Selector<'cmds, 'args>, Error<'cmds>> {
+        fn on_error<'args>(mut state: State<'args>, _: Arg<'args>) -> State<'args> {
+            state.set_node_id(ERROR_NODE_ID);
+            state
+        }
 
         let states: Vec<State<'args>>
-            = runner::Runner::run(&machine, on_error, args);
+            = runner::Runner::run_with_trace(&self.machine(), on_error, args, trace);
 
         let selector: Selector<'cmds, 'args>
             = Selector::new(self.commands.clone(), args.to_vec(), states);
@@ -1514,3 +2276,242 @@ fn it_should_parse_batch_options() {
         (0, vec![]),
     ]);
 }
+
+#[test]
+fn it_should_parse_batch_options_with_values() {
+    let spec = CommandSpec {
+        components: vec![
+            Component::Option(OptionSpec::boolean("-x")),
+            Component::Option(OptionSpec::boolean("-v")),
+            Component::Option(OptionSpec {is_required: false, ..OptionSpec::parametrized("-f,--file")}),
+            Component::Option(OptionSpec {is_required: false, ..OptionSpec::parametrized("-n")}),
+        ],
+        ..Default::default()
+    };
+
+    let cases: Vec<(&[&str], _)> = vec![
+        (&["-xvf", "archive.tar"], vec![(0, vec![]), (1, vec![]), (2, vec!["archive.tar"])]),
+        (&["-xvfarchive.tar"], vec![(0, vec![]), (1, vec![]), (2, vec!["archive.tar"])]),
+        (&["-ffile"], vec![(2, vec!["file"])]),
+        (&["-f=file"], vec![(2, vec!["file"])]),
+        (&["-vf=file"], vec![(1, vec![]), (2, vec!["file"])]),
+        (&["-n5"], vec![(3, vec!["5"])]),
+        (&["-xn", "5", "-v"], vec![(0, vec![]), (1, vec![]), (3, vec!["5"])]),
+        (&["--file=-x"], vec![(2, vec!["-x"])]),
+    ];
+
+    for (args, expected) in cases {
+        let mut cli_builder
+            = CliBuilder::new();
+
+        cli_builder.add_command(&spec);
+
+        let Ok(mut selector) = cli_builder.run(args) else {
+            panic!("Expected a selector result for {:?}", args);
+        };
+
+        let selector_result
+            = selector.resolve_state(|_| Ok(())).unwrap();
+
+        let SelectionResult::Command(_, state, _) = selector_result else {
+            panic!("Expected a command result for {:?}: {:?}", args, selector_result);
+        };
+
+        assert_eq!(state.values(), expected, "for {:?}", args);
+    }
+}
+
+#[test]
+fn it_should_bind_the_rest_of_the_batch_to_value_options() {
+    let mut cli_builder
+        = CliBuilder::new();
+
+    let spec = CommandSpec {
+        components: vec![
+            Component::Option(OptionSpec::boolean("-x")),
+            Component::Option(OptionSpec::parametrized("-f")),
+        ],
+        ..Default::default()
+    };
+
+    cli_builder.add_command(&spec);
+
+    // Like getopt, -fx means "-f x" rather than "-f -x"
+    let mut selector = cli_builder.run(&["-fx"])
+        .unwrap();
+
+    let SelectionResult::Command(_, state, _) = selector.resolve_state(|_| Ok(())).unwrap() else {
+        panic!("Expected a command result");
+    };
+
+    assert_eq!(state.values(), vec![
+        (1, vec!["x"]),
+    ]);
+}
+
+#[cfg(feature = "tokens")]
+#[test]
+fn it_should_emit_tokens_for_batch_options_with_values() {
+    let mut cli_builder
+        = CliBuilder::new();
+
+    let spec = CommandSpec {
+        components: vec![
+            Component::Option(OptionSpec::boolean("-v")),
+            Component::Option(OptionSpec::parametrized("-f")),
+        ],
+        ..Default::default()
+    };
+
+    cli_builder.add_command(&spec);
+
+    let mut selector = cli_builder.run(&["-vf=file"])
+        .unwrap();
+
+    let SelectionResult::Command(_, state, _) = selector.resolve_state(|_| Ok(())).unwrap() else {
+        panic!("Expected a command result");
+    };
+
+    assert_eq!(state.tokens, vec![
+        Token::Option {arg_index: 0, slice: 0..2, component_id: 0},
+        Token::Option {arg_index: 0, slice: 2..3, component_id: 1},
+        Token::Assign {arg_index: 0, slice: 3..4, component_id: 1},
+        Token::Value {arg_index: 0, slice: 4..8, component_id: 1},
+    ]);
+}
+
+#[test]
+fn it_should_format_option_definitions_without_brackets() {
+    let required_option
+        = OptionSpec::parametrized("-f,--file");
+    let optional_option
+        = OptionSpec {is_required: false, ..OptionSpec::parametrized("-f,--file")};
+
+    assert_eq!(required_option.definition(), "-f,--file <arg>");
+    assert_eq!(required_option.to_string(), "<-f,--file <arg>>");
+    assert_eq!(optional_option.to_string(), "[-f,--file <arg>]");
+    assert_eq!(OptionSpec::boolean("-v").definition(), "-v");
+}
+
+#[test]
+fn it_should_share_the_precompiled_machine_between_clones() {
+    let mut cli_builder
+        = CliBuilder::new();
+
+    let spec1 = CommandSpec {
+        primary_path: vec!["foo".to_string()],
+        ..Default::default()
+    };
+
+    let spec2 = CommandSpec {
+        primary_path: vec!["bar".to_string()],
+        ..Default::default()
+    };
+
+    cli_builder.add_command(&spec1);
+    cli_builder.precompile();
+
+    let cloned_builder
+        = cli_builder.clone();
+
+    assert!(Arc::ptr_eq(&cli_builder.machine(), &cloned_builder.machine()));
+
+    cli_builder.add_command(&spec2);
+
+    assert!(!Arc::ptr_eq(&cli_builder.machine(), &cloned_builder.machine()));
+    assert!(cli_builder.run(&["bar"]).unwrap().resolve_state(|_| Ok(())).is_ok());
+}
+
+#[test]
+fn it_should_only_compile_the_dispatched_commands() {
+    let mut cli_builder
+        = CliBuilder::new();
+
+    let mut prefix
+        = PositionalSpec::required();
+
+    if let PositionalSpec::Dynamic {is_prefix, ..} = &mut prefix {
+        *is_prefix = true;
+    }
+
+    let spec1 = CommandSpec {
+        primary_path: vec!["db".to_string()],
+        components: vec![Component::Positional(prefix)],
+        ..Default::default()
+    };
+
+    let spec2 = CommandSpec {
+        primary_path: vec!["serve".to_string()],
+        ..Default::default()
+    };
+
+    let spec3 = CommandSpec {
+        components: vec![Component::Positional(PositionalSpec::required())],
+        ..Default::default()
+    };
+
+    cli_builder.add_command(&spec1);
+    cli_builder.add_command(&spec2);
+    cli_builder.add_command(&spec3);
+
+    let result
+        = cli_builder.run(&["serve"]).unwrap().resolve_state(|_| Ok(()));
+
+    assert!(matches!(result, Ok(SelectionResult::Command(spec, _, _)) if std::ptr::eq(spec, &spec2)));
+
+    // The prefix positional of the first command consumes the argument, so
+    // it can't reach its path
+    let result
+        = cli_builder.run(&["db"]).unwrap().resolve_state(|_| Ok(()));
+
+    assert!(matches!(result, Ok(SelectionResult::Command(spec, _, _)) if std::ptr::eq(spec, &spec3)));
+}
+
+#[test]
+fn it_should_trace_how_the_command_got_selected() {
+    let mut cli_builder
+        = CliBuilder::new();
+
+    let spec1 = CommandSpec {
+        primary_path: vec!["foo".to_string()],
+        components: vec![Component::Positional(PositionalSpec::required())],
+        ..Default::default()
+    };
+
+    let spec2 = CommandSpec {
+        primary_path: vec!["foo".to_string()],
+        ..Default::default()
+    };
+
+    let spec3 = CommandSpec {
+        components: vec![Component::Positional(PositionalSpec::optional())],
+        ..Default::default()
+    };
+
+    cli_builder.add_command(&spec1);
+    cli_builder.add_command(&spec2);
+    cli_builder.add_command(&spec3);
+
+    let mut trace: Vec<TraceEvent>
+        = vec![];
+
+    let mut selector
+        = cli_builder.run_with_trace(&["foo"], &mut trace).unwrap();
+
+    let result
+        = selector.resolve_state_with_trace(|_| Ok(()), &mut trace);
+
+    assert!(matches!(result, Ok(SelectionResult::Command(spec, _, _)) if std::ptr::eq(spec, &spec2)));
+
+    assert_eq!(trace.first(), Some(&TraceEvent::StartOfInput));
+    assert!(trace.contains(&TraceEvent::Argument {index: 0, value: "foo".to_string()}));
+
+    // The default command consumed the argument as a positional rather than
+    // as a keyword
+    assert!(trace.iter().any(|event| matches!(event, TraceEvent::Trim {context_id: 2, keyword_count: 0, max_keyword_count: 1, ..})));
+
+    // The first command is missing its positional argument
+    assert!(trace.iter().any(|event| matches!(event, TraceEvent::Drop {context_id: 0, reason: DropReason::Unsuccessful, ..})));
+
+    assert!(matches!(trace.last(), Some(TraceEvent::Selected {context_id: 1, ..})));
+}
@@ -0,0 +1,214 @@
+use std::collections::BTreeSet;
+
+use crate::{runner::ValidateTransition, shared::ArgKey, Attachment, Check, CommandSpec, Component, Machine, Reducer, State};
+
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase", rename_all_fields = "camelCase", tag = "type"))]
+#[cfg_attr(feature = "serde", derive(ts_rs::TS))]
+#[cfg_attr(feature = "serde", ts(export, export_to = "index.ts"))]
+pub enum Completion {
+    Keyword {
+        value: String,
+    },
+    Option {
+        value: String,
+        command_id: usize,
+        component_id: usize,
+    },
+    Choice {
+        value: String,
+        command_id: usize,
+        component_id: usize,
+    },
+    Value {
+        command_id: usize,
+        component_id: usize,
+    },
+}
+
+impl Completion {
+    /**
+     * Returns the literal word the shell should insert, if any. Value slots
+     * don't have one; shells usually fall back to file completion for them.
+     */
+    pub fn word(&self) -> Option<&str> {
+        match self {
+            Completion::Keyword {value} => Some(value),
+            Completion::Option {value, ..} => Some(value),
+            Completion::Choice {value, ..} => Some(value),
+            Completion::Value {..} => None,
+        }
+    }
+}
+
+/**
+ * Inspect the nodes the given states are sitting on and list every token
+ * that would let them make progress. The `partial` word is the one under the
+ * cursor; keywords and options that don't start with it are discarded.
+ */
+pub fn collect_completions<'cmds, 'args>(machine: &Machine<'cmds, Option<Check<'cmds>>, Option<Reducer>>, commands: &[&'cmds CommandSpec], states: &[State<'args>], partial: &'args str) -> Vec<Completion> {
+    let mut completions
+        = BTreeSet::new();
+
+    for state in states {
+        if commands.get(state.context_id).is_some_and(|command| command.is_hidden) {
+            continue;
+        }
+
+        let node
+            = &machine.nodes[state.node_id];
+
+        for key in node.statics.keys() {
+            if let ArgKey::User(value) = key {
+                if value.starts_with(partial) {
+                    completions.insert(Completion::Keyword {
+                        value: value.to_string(),
+                    });
+                }
+            }
+        }
+
+        for (check, transition) in node.dynamics.iter() {
+            match (check, &transition.reducer) {
+                (Some(Check::IsOption(name)), Some(Reducer::StartValue(Attachment::Option, component_id))) => {
+                    let is_hidden
+                        = matches!(&commands[state.context_id].components[*component_id], Component::Option(option) if option.is_hidden);
+
+                    if !state.post_double_dash && !is_hidden && name.starts_with(partial) {
+                        completions.insert(Completion::Option {
+                            value: name.to_string(),
+                            command_id: state.context_id,
+                            component_id: *component_id,
+                        });
+                    }
+                },
+
+                (Some(Check::IsNotOptionLike | Check::IsHyphenValue(_) | Check::IsNotOptionLikeOrNumber(_)) | None, Some(reducer)) => {
+                    let component_id = match reducer {
+                        Reducer::StartValue(_, component_id)
+                            => Some(*component_id),
+
+                        Reducer::PushValue(Attachment::Option)
+                            => state.option_values.last_id(),
+
+                        Reducer::PushValue(Attachment::Positional)
+                            => state.positional_values.last_id(),
+
+                        _ => None,
+                    };
+
+                    if let Some(component_id) = component_id {
+                        let choices
+                            = commands[state.context_id].components[component_id].choices();
+
+                        if !choices.is_empty() {
+                            completions.extend(choices.iter().filter(|choice| choice.starts_with(partial)).map(|choice| Completion::Choice {
+                                value: choice.clone(),
+                                command_id: state.context_id,
+                                component_id,
+                            }));
+                        } else if check.check(state, partial) {
+                            completions.insert(Completion::Value {
+                                command_id: state.context_id,
+                                component_id,
+                            });
+                        }
+                    }
+                },
+
+                _ => {},
+            }
+        }
+    }
+
+    completions.into_iter()
+        .collect()
+}
+
+#[cfg(test)]
+use crate::{CliBuilder, OptionSpec, PositionalSpec};
+
+#[test]
+fn it_should_complete_keywords() {
+    let mut cli_builder
+        = CliBuilder::new();
+
+    let spec1 = CommandSpec {
+        primary_path: vec!["config".to_string(), "get".to_string()],
+        ..Default::default()
+    };
+
+    let spec2 = CommandSpec {
+        primary_path: vec!["config".to_string(), "set".to_string()],
+        ..Default::default()
+    };
+
+    let spec3 = CommandSpec {
+        primary_path: vec!["commit".to_string()],
+        ..Default::default()
+    };
+
+    cli_builder.add_command(&spec1);
+    cli_builder.add_command(&spec2);
+    cli_builder.add_command(&spec3);
+
+    assert_eq!(cli_builder.complete(&["co"], 0), vec![
+        Completion::Keyword {value: "commit".to_string()},
+        Completion::Keyword {value: "config".to_string()},
+    ]);
+
+    assert_eq!(cli_builder.complete(&["config", ""], 1), vec![
+        Completion::Keyword {value: "get".to_string()},
+        Completion::Keyword {value: "set".to_string()},
+    ]);
+}
+
+#[test]
+fn it_should_complete_options() {
+    let mut cli_builder
+        = CliBuilder::new();
+
+    let spec = CommandSpec {
+        primary_path: vec!["commit".to_string()],
+        components: vec![
+            Component::Option(OptionSpec::boolean("-a,--all")),
+            Component::Option(OptionSpec::parametrized("-m,--message")),
+        ],
+        ..Default::default()
+    };
+
+    cli_builder.add_command(&spec);
+
+    assert_eq!(cli_builder.complete(&["commit", "--"], 1), vec![
+        Completion::Option {value: "--all".to_string(), command_id: 0, component_id: 0},
+        Completion::Option {value: "--message".to_string(), command_id: 0, component_id: 1},
+    ]);
+}
+
+#[test]
+fn it_should_report_value_slots() {
+    let mut cli_builder
+        = CliBuilder::new();
+
+    let spec = CommandSpec {
+        primary_path: vec!["commit".to_string()],
+        components: vec![
+            Component::Option(OptionSpec::parametrized("-m,--message")),
+            Component::Positional(PositionalSpec::required()),
+        ],
+        ..Default::default()
+    };
+
+    cli_builder.add_command(&spec);
+
+    assert_eq!(cli_builder.complete(&["commit", "-m", ""], 2), vec![
+        Completion::Value {command_id: 0, component_id: 0},
+    ]);
+
+    assert_eq!(cli_builder.complete(&["commit", ""], 1), vec![
+        Completion::Option {value: "--message".to_string(), command_id: 0, component_id: 0},
+        Completion::Option {value: "-m".to_string(), command_id: 0, component_id: 0},
+        Completion::Value {command_id: 0, component_id: 1},
+    ]);
+}
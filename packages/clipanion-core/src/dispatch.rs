@@ -0,0 +1,172 @@
+use std::{collections::HashMap, iter::once};
+
+use crate::{CommandSpec, Component, PositionalSpec};
+
+/**
+ * Index the commands by the keywords their paths start with, so that parsing
+ * a command line only requires compiling the commands it can reach.
+ *
+ * After each argument the runner discards the states that consumed fewer
+ * keywords than the others. Once the leading arguments of a command line
+ * have been matched as the keywords of some paths, the commands whose paths
+ * don't start with them can't contribute to the result anymore.
+ */
+#[derive(Debug, Default)]
+pub struct DispatchTrie {
+    children: HashMap<String, DispatchTrie>,
+
+    // The commands with a path going through this node
+    commands: Vec<usize>,
+
+    // The commands with a path ending at this node that can consume more
+    // keywords through keyword positionals; they remain candidates whatever
+    // the arguments that follow
+    open_commands: Vec<usize>,
+}
+
+impl DispatchTrie {
+    pub fn new(commands: &[&CommandSpec]) -> Self {
+        let mut root
+            = DispatchTrie::default();
+
+        for (command_id, command) in commands.iter().enumerate() {
+            let has_prefix_positionals = command.components.iter()
+                .any(|component| matches!(component, Component::Positional(PositionalSpec::Dynamic {is_prefix: true, ..})));
+
+            // These commands may consume positionals before reaching their
+            // path, so we can't tell in advance which arguments will be
+            // matched against it
+            if has_prefix_positionals {
+                root.open_commands.push(command_id);
+                continue;
+            }
+
+            let has_keyword_positionals = command.components.iter()
+                .any(|component| matches!(component, Component::Positional(PositionalSpec::Keyword {..})));
+
+            for path in command.aliases.iter().chain(once(&command.primary_path)) {
+                let mut node
+                    = &mut root;
+
+                for segment in path {
+                    node = node.children.entry(segment.clone()).or_default();
+
+                    if node.commands.last() != Some(&command_id) {
+                        node.commands.push(command_id);
+                    }
+                }
+
+                if has_keyword_positionals && node.open_commands.last() != Some(&command_id) {
+                    node.open_commands.push(command_id);
+                }
+            }
+        }
+
+        root
+    }
+
+    /**
+     * Return the sorted ids of the commands that can match the given command
+     * line, or `None` if it doesn't start with any of the path keywords, in
+     * which case all the commands have to be considered.
+     */
+    pub fn dispatch(&self, args: &[&str]) -> Option<Vec<usize>> {
+        let mut node
+            = self;
+
+        let mut command_ids
+            = self.open_commands.clone();
+
+        for arg in args {
+            let Some(child) = node.children.get(*arg) else {
+                break;
+            };
+
+            node = child;
+            command_ids.extend(&node.open_commands);
+        }
+
+        if std::ptr::eq(node, self) {
+            return None;
+        }
+
+        command_ids.extend(&node.commands);
+        command_ids.sort_unstable();
+        command_ids.dedup();
+
+        Some(command_ids)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{CommandSpec, Component, PositionalSpec};
+
+    use super::DispatchTrie;
+
+    fn command(path: &[&str], aliases: &[&[&str]], components: Vec<Component>) -> CommandSpec {
+        CommandSpec {
+            primary_path: path.iter().map(|segment| segment.to_string()).collect(),
+            aliases: aliases.iter().map(|alias| alias.iter().map(|segment| segment.to_string()).collect()).collect(),
+            components,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn it_dispatches_on_the_longest_matching_prefix() {
+        let commands = [
+            command(&["db"], &[], vec![Component::Positional(PositionalSpec::rest())]),
+            command(&["db", "migrate"], &[&["db", "up"]], vec![]),
+            command(&["db", "seed"], &[], vec![]),
+            command(&["serve"], &[], vec![]),
+            command(&[], &[], vec![]),
+        ];
+
+        let trie
+            = DispatchTrie::new(&commands.iter().collect::<Vec<_>>());
+
+        assert_eq!(trie.dispatch(&["db", "migrate", "--force"]), Some(vec![1]));
+        assert_eq!(trie.dispatch(&["db", "up"]), Some(vec![1]));
+        assert_eq!(trie.dispatch(&["db", "foo"]), Some(vec![0, 1, 2]));
+        assert_eq!(trie.dispatch(&["serve"]), Some(vec![3]));
+        assert_eq!(trie.dispatch(&["foo"]), None);
+        assert_eq!(trie.dispatch(&[]), None);
+    }
+
+    #[test]
+    fn it_keeps_the_commands_with_keyword_positionals() {
+        let commands = [
+            command(&["db"], &[], vec![Component::Positional(PositionalSpec::rest()), Component::Positional(PositionalSpec::keyword("migrate"))]),
+            command(&["db", "migrate"], &[], vec![]),
+            command(&[], &[], vec![Component::Positional(PositionalSpec::keyword("db"))]),
+        ];
+
+        let trie
+            = DispatchTrie::new(&commands.iter().collect::<Vec<_>>());
+
+        assert_eq!(trie.dispatch(&["db", "migrate"]), Some(vec![0, 1, 2]));
+    }
+
+    #[test]
+    fn it_keeps_the_commands_with_prefix_positionals() {
+        let mut prefix
+            = PositionalSpec::required();
+
+        if let PositionalSpec::Dynamic {is_prefix, ..} = &mut prefix {
+            *is_prefix = true;
+        }
+
+        let commands = [
+            command(&["db"], &[], vec![Component::Positional(prefix)]),
+            command(&["db", "migrate"], &[], vec![]),
+            command(&["serve"], &[], vec![]),
+        ];
+
+        let trie
+            = DispatchTrie::new(&commands.iter().collect::<Vec<_>>());
+
+        assert_eq!(trie.dispatch(&["db", "migrate"]), Some(vec![0, 1]));
+        assert_eq!(trie.dispatch(&["serve"]), Some(vec![0, 2]));
+    }
+}
@@ -1,4 +1,11 @@
-use crate::builder::CommandSpec;
+use crate::builder::{ArgSource, CommandSpec, Constraint};
+
+fn format_suggestions(suggestions: &[String]) -> String {
+    match suggestions.len() {
+        0 => String::new(),
+        _ => format!(" (did you mean {}?)", suggestions.join(", ")),
+    }
+}
 
 #[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
 pub enum CommandError {
@@ -8,17 +15,45 @@ pub enum CommandError {
     #[error("Missing required option argument(s): {name}", name = .0.join(", "))]
     MissingOptionArguments(Vec<String>),
 
-    #[error("Unsupported option name")]
-    UnknownOption,
+    #[error("Unsupported option name: {0}{suggestions}", suggestions = format_suggestions(.1))]
+    UnknownOption(String, Vec<String>),
 
     #[error("Invalid option name")]
     InvalidOption,
 
+    #[error("Invalid value `{0}`; expected one of {choices}", choices = .1.join(", "))]
+    InvalidChoice(String, Vec<String>),
+
+    #[error("Invalid value `{1}` for {0}; expected {2}")]
+    ConstraintViolation(String, String, Constraint),
+
+    #[error("Options {options} can't be used together", options = .0.join(", "))]
+    ConflictingOptions(Vec<String>),
+
+    #[error("Option {0} requires {requirements}", requirements = .1.join(", "))]
+    MissingRequirements(String, Vec<String>),
+
+    #[error("Option {0} is required when {conditions} is set", conditions = .1.join(" or "))]
+    MissingOptionIf(String, Vec<String>),
+
+    #[error("Option {0} is required unless {conditions} is set", conditions = .1.join(" or "))]
+    MissingOptionUnless(String, Vec<String>),
+
+    #[error("At least one of {options} must be set", options = .0.join(", "))]
+    MissingOneOfOptions(Vec<String>),
+
+    #[error("{0} is experimental; set {1}=1 to enable it")]
+    Experimental(String, String),
+
     #[error("Missing required positional argument")]
     MissingPositionalArguments,
 
     #[error("Extraneous positional arguments")]
     ExtraneousPositionalArguments,
+
+    // An error caused by an argument that was read from a response file
+    #[error("{1} (from {0})")]
+    FromSource(ArgSource, Box<CommandError>),
 }
 
 impl From<String> for CommandError {
@@ -0,0 +1,279 @@
+use std::{iter::once, ops::Range};
+
+use itertools::Itertools;
+use rand::Rng;
+
+use crate::builder::{CommandSpec, Component, OptionSpec, PositionalSpec};
+
+fn gen_string<R: Rng>(rng: &mut R, len: Range<usize>) -> String {
+    let mut s = String::new();
+    for _ in 0..rng.random_range(len) {
+        s.push(rng.random_range(b'a'..=b'z') as char);
+    }
+    s
+}
+
+fn gen_optional<R: Rng, T>(rng: &mut R, f: impl Fn(&mut R) -> T) -> Option<T> {
+    if rng.random_bool(0.5) {
+        Some(f(rng))
+    } else {
+        None
+    }
+}
+
+pub fn gen_random_keyword<R: Rng>(rng: &mut R) -> String {
+    format!("keyword-{}", gen_string(rng, 1..10))
+}
+
+pub fn gen_random_option_name<R: Rng>(rng: &mut R) -> String {
+    format!("--option-{}", gen_string(rng, 1..10))
+}
+
+pub fn gen_random_value<R: Rng>(rng: &mut R) -> String {
+    format!("value-{}", gen_string(rng, 1..10))
+}
+
+pub fn gen_random_values<R: Rng>(rng: &mut R, min_len: usize, extra_len: Option<usize>) -> Vec<String> {
+    let mut values = vec![];
+
+    let extra_len
+        = rng.random_range(0..=extra_len.unwrap_or(4));
+
+    for _ in 0..min_len+extra_len {
+        values.push(gen_random_value(rng));
+    }
+
+    values
+}
+
+pub fn gen_random_positional_spec<R: Rng>(rng: &mut R, use_optional_positionals: bool) -> PositionalSpec {
+    match rng.random_range(0..=1) {
+        0 => PositionalSpec::Keyword {
+            expected: gen_random_keyword(rng),
+        },
+
+        1 => PositionalSpec::Dynamic {
+            name: "positional".to_string(),
+            documentation: None,
+            choices: vec![],
+            constraints: vec![],
+            min_len: rng.random_range(0..3),
+            extra_len: match use_optional_positionals {
+                true => gen_optional(rng, |rng| rng.random_range(0..3)),
+                false => Some(0),
+            },
+            allow_hyphen_values: false,
+            is_prefix: false,
+            is_proxy: false,
+        },
+
+        _ => unreachable!(),
+    }
+}
+
+pub fn gen_random_option_spec<R: Rng>(rng: &mut R, use_optional_positionals: bool) -> OptionSpec {
+    OptionSpec {
+        primary_name: gen_random_option_name(rng),
+        aliases: vec![],
+        documentation: None,
+        default_value: None,
+        env: None,
+        choices: vec![],
+        constraints: vec![],
+        min_len: rng.random_range(0..3),
+        allow_binding: rng.random_bool(0.5),
+        allow_hyphen_values: false,
+        lifecycle: Default::default(),
+        is_hidden: false,
+        is_required: rng.random_bool(0.5),
+
+        // We can't use optional values for options when the command has optional positionals,
+        // as it becomes ambiguous. For example, let's say we have this:
+        //
+        //   my-cmd [foo] [...foo] [--opt <val> [...val]]
+        //
+        // How should we parse `my-cmd hello --opt world`? What about `my-cmd --opt hello world`? And
+        // it's the same thing with allow_boolean.
+
+        extra_len: match use_optional_positionals {
+            true => Some(0),
+            false => gen_optional(rng, |rng| rng.random_range(0..3)),
+        },
+
+        allow_boolean: match use_optional_positionals {
+            true => false,
+            false => rng.random_bool(0.5),
+        },
+    }
+}
+
+pub fn gen_random_command_spec<R: Rng>(rng: &mut R, use_optional_positionals: bool) -> CommandSpec {
+    let mut components = vec![];
+
+    for _ in 0..rng.random_range(0..10) {
+        components.push(match rng.random_range(0..=1) {
+            0 => Component::Positional(gen_random_positional_spec(rng, use_optional_positionals)),
+            1 => Component::Option(gen_random_option_spec(rng, use_optional_positionals)),
+            _ => unreachable!(),
+        });
+    }
+
+    CommandSpec {
+        components,
+        ..Default::default()
+    }
+}
+
+pub fn gen_random_command_values<R: Rng>(rng: &mut R, command_spec: &CommandSpec) -> Vec<(usize, Vec<String>)> {
+    let mut values = vec![];
+    let mut allow_extra_values = true;
+
+    for (i, component) in command_spec.components.iter().enumerate() {
+        match component {
+            Component::Positional(positional_spec) => {
+                match positional_spec {
+                    PositionalSpec::Keyword {..} => {
+                        allow_extra_values = true;
+                    },
+
+                    PositionalSpec::Dynamic {min_len, extra_len, ..} => {
+                        let extra_len = if allow_extra_values {
+                            *extra_len
+                        } else {
+                            Some(0)
+                        };
+
+                        let positional_values
+                            = gen_random_values(rng, *min_len, extra_len);
+
+                        if !positional_values.is_empty() {
+                            values.push((i, positional_values));
+                        }
+
+                        allow_extra_values = false;
+                    },
+                }
+            },
+
+            Component::Option(option_spec) => {
+                for _ in 0..rng.random_range(0..=3) {
+                    let option_args = if option_spec.allow_boolean && rng.random_bool(0.5) {
+                        vec![]
+                    } else {
+                        gen_random_values(rng, option_spec.min_len, option_spec.extra_len)
+                    };
+
+                    values.push((i, option_args));
+                }
+            },
+
+            Component::Passthrough(_) => {},
+        }
+    }
+
+    values
+}
+
+fn insert_b_into_a_randomly<R: Rng, T: Clone>(rng: &mut R, mut a: Vec<T>, b: Vec<T>) -> Vec<T> {
+    let mut insert_positions = vec![];
+
+    for _ in 0..b.len() {
+        insert_positions.push(rng.random_range(0..=a.len()));
+    }
+
+    insert_positions.sort(); // Ensure order to preserve B's sequence
+
+    a.reserve(a.len() + b.len());
+
+    for (i, b) in b.into_iter().enumerate() {
+        a.insert(insert_positions[i] + i, b);
+    }
+
+    a
+}
+
+pub fn gen_random_command_line<R: Rng>(rng: &mut R, command_spec: &CommandSpec, command_values: &[(usize, Vec<String>)]) -> Vec<String> {
+    let mut indexed_command_values
+        = command_values.iter()
+            .into_group_map_by(|(i, _)| *i);
+
+    let mut positionals = vec![];
+
+    // Options that can be set anywhere in the command line without ambiguities.
+    let mut interlaced_options = vec![];
+
+    // Options that MUST be at the end of the command line because they could tolerate more values than we provide.
+    let mut trailing_options = vec![];
+
+    for (i, component) in command_spec.components.iter().enumerate() {
+        match component {
+            Component::Positional(positional_spec) => {
+                match positional_spec {
+                    PositionalSpec::Keyword {expected, ..} => {
+                        positionals.push(expected.clone());
+                    },
+
+                    PositionalSpec::Dynamic {..} => {
+                        for (_, values) in indexed_command_values.entry(i).or_default() {
+                            positionals.extend(values.iter().cloned());
+                        }
+                    },
+                }
+            },
+
+            Component::Option(option_spec) => {
+                let mut force_trailing
+                    = false;
+
+                let all_names
+                    = once(&option_spec.primary_name)
+                        .chain(option_spec.aliases.iter())
+                        .collect::<Vec<_>>();
+
+                for (_, values) in indexed_command_values.entry(i).or_default() {
+                    let name
+                        = all_names[rng.random_range(0..all_names.len())];
+
+                    let mut args
+                        = vec![name.to_string()];
+
+                    if values.is_empty() && option_spec.allow_boolean && (option_spec.min_len > 0 || option_spec.extra_len != Some(0)) {
+                        force_trailing = true;
+                    } else {
+                        let current_extra_len
+                            = values.len() - option_spec.min_len;
+
+                        args.extend(values.iter().cloned());
+
+                        if option_spec.extra_len != Some(0) || current_extra_len > 0 {
+                            force_trailing = true;
+                        }
+                    }
+
+                    if force_trailing {
+                        trailing_options.push(args);
+                    } else {
+                        interlaced_options.push(args);
+                    }
+                }
+            },
+
+            Component::Passthrough(_) => {},
+        }
+    }
+
+    let mut command_line_segments
+        = positionals.into_iter()
+            .map(|positional| vec![positional])
+            .collect::<Vec<_>>();
+
+    command_line_segments
+        = insert_b_into_a_randomly(rng, command_line_segments, interlaced_options);
+
+    command_line_segments
+        .extend(trailing_options);
+
+    command_line_segments.into_iter()
+        .flatten()
+        .collect()
+}
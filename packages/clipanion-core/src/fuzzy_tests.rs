@@ -1,274 +1,9 @@
-use std::{iter::once, ops::Range};
+use std::iter::once;
 
-use itertools::Itertools;
 use rand::Rng;
 use rand_seeder::SipHasher;
 
-use crate::{builder::{CliBuilder, CommandSpec, Component, OptionSpec, PositionalSpec}, SelectionResult};
-
-fn gen_string<R: Rng>(rng: &mut R, len: Range<usize>) -> String {
-    let mut s = String::new();
-    for _ in 0..rng.random_range(len) {
-        s.push(rng.random_range(b'a'..=b'z') as char);
-    }
-    s
-}
-
-fn gen_optional<R: Rng, T>(rng: &mut R, f: impl Fn(&mut R) -> T) -> Option<T> {
-    if rng.random_bool(0.5) {
-        Some(f(rng))
-    } else {
-        None
-    }
-}
-
-fn gen_random_keyword<R: Rng>(rng: &mut R) -> String {
-    format!("keyword-{}", gen_string(rng, 1..10))
-}
-
-fn gen_random_option_name<R: Rng>(rng: &mut R) -> String {
-    format!("--option-{}", gen_string(rng, 1..10))
-}
-
-fn gen_random_value<R: Rng>(rng: &mut R) -> String {
-    format!("value-{}", gen_string(rng, 1..10))
-}
-
-fn gen_random_values<R: Rng>(rng: &mut R, min_len: usize, extra_len: Option<usize>) -> Vec<String> {
-    let mut values = vec![];
-
-    let extra_len
-        = rng.random_range(0..=extra_len.unwrap_or(4));
-
-    for _ in 0..min_len+extra_len {
-        values.push(gen_random_value(rng));
-    }
-
-    values
-}
-
-fn gen_random_positional_spec<R: Rng>(rng: &mut R, use_optional_positionals: bool) -> PositionalSpec {
-    match rng.random_range(0..=1) {
-        0 => PositionalSpec::Keyword {
-            expected: gen_random_keyword(rng),
-        },
-
-        1 => PositionalSpec::Dynamic {
-            name: "positional".to_string(),
-            documentation: None,
-            min_len: rng.random_range(0..3),
-            extra_len: match use_optional_positionals {
-                true => gen_optional(rng, |rng| rng.random_range(0..3)),
-                false => Some(0),
-            },
-            is_prefix: false,
-            is_proxy: false,
-        },
-
-        _ => unreachable!(),
-    }
-}
-
-fn gen_random_option_spec<R: Rng>(rng: &mut R, use_optional_positionals: bool) -> OptionSpec {
-    OptionSpec {
-        primary_name: gen_random_option_name(rng),
-        aliases: vec![],
-        documentation: None,
-        default_value: None,
-        min_len: rng.random_range(0..3),
-        allow_binding: rng.random_bool(0.5),
-        is_hidden: false,
-        is_required: rng.random_bool(0.5),
-
-        // We can't use optional values for options when the command has optional positionals,
-        // as it becomes ambiguous. For example, let's say we have this:
-        //
-        //   my-cmd [foo] [...foo] [--opt <val> [...val]]
-        //
-        // How should we parse `my-cmd hello --opt world`? What about `my-cmd --opt hello world`? And
-        // it's the same thing with allow_boolean.
-
-        extra_len: match use_optional_positionals {
-            true => Some(0),
-            false => gen_optional(rng, |rng| rng.random_range(0..3)),
-        },
-
-        allow_boolean: match use_optional_positionals {
-            true => false,
-            false => rng.random_bool(0.5),
-        },
-    }
-}
-
-fn gen_random_command_spec<R: Rng>(rng: &mut R, use_optional_positionals: bool) -> CommandSpec {
-    let mut components = vec![];
-
-    for _ in 0..rng.random_range(0..10) {
-        components.push(match rng.random_range(0..=1) {
-            0 => Component::Positional(gen_random_positional_spec(rng, use_optional_positionals)),
-            1 => Component::Option(gen_random_option_spec(rng, use_optional_positionals)),
-            _ => unreachable!(),
-        });
-    }
-
-    CommandSpec {
-        components,
-        ..Default::default()
-    }
-}
-
-fn gen_random_command_values<R: Rng>(rng: &mut R, command_spec: &CommandSpec) -> Vec<(usize, Vec<String>)> {
-    let mut values = vec![];
-    let mut allow_extra_values = true;
-
-    for (i, component) in command_spec.components.iter().enumerate() {
-        match component {
-            Component::Positional(positional_spec) => {
-                match positional_spec {
-                    PositionalSpec::Keyword {..} => {
-                        allow_extra_values = true;
-                    },
-
-                    PositionalSpec::Dynamic {min_len, extra_len, ..} => {
-                        let extra_len = if allow_extra_values {
-                            *extra_len
-                        } else {
-                            Some(0)
-                        };
-
-                        let positional_values
-                            = gen_random_values(rng, *min_len, extra_len);
-
-                        if positional_values.len() > 0 {
-                            values.push((i, positional_values));
-                        }
-
-                        allow_extra_values = false;
-                    },
-                }
-            },
-
-            Component::Option(option_spec) => {
-                for _ in 0..rng.random_range(0..=3) {
-                    let option_args = if option_spec.allow_boolean && rng.random_bool(0.5) {
-                        vec![]
-                    } else {
-                        gen_random_values(rng, option_spec.min_len, option_spec.extra_len)
-                    };
-
-                    values.push((i, option_args));
-                }
-            }
-        }
-    }
-
-    values
-}
-
-fn insert_b_into_a_randomly<R: Rng, T: Clone>(rng: &mut R, mut a: Vec<T>, b: Vec<T>) -> Vec<T> {
-    let mut insert_positions = vec![];
-
-    for _ in 0..b.len() {
-        insert_positions.push(rng.random_range(0..=a.len()));
-    }
-
-    let mut insert_positions = insert_positions;
-    insert_positions.sort(); // Ensure order to preserve B's sequence
-
-    a.reserve(a.len() + b.len());
-
-    for (i, b) in b.into_iter().enumerate() {
-        a.insert(insert_positions[i] + i, b);
-    }
-
-    a
-}
-
-fn gen_random_command_line<R: Rng>(rng: &mut R, command_spec: &CommandSpec, command_values: &[(usize, Vec<String>)]) -> Vec<String> {
-    let mut indexed_command_values
-        = command_values.iter()
-            .into_group_map_by(|(i, _)| *i);
-
-    let mut positionals = vec![];
-
-    // Options that can be set anywhere in the command line without ambiguities.
-    let mut interlaced_options = vec![];
-
-    // Options that MUST be at the end of the command line because they could tolerate more values than we provide.
-    let mut trailing_options = vec![];
-
-    for (i, component) in command_spec.components.iter().enumerate() {
-        match component {
-            Component::Positional(positional_spec) => {
-                match positional_spec {
-                    PositionalSpec::Keyword {expected, ..} => {
-                        positionals.push(expected.clone());
-                    },
-
-                    PositionalSpec::Dynamic {..} => {
-                        for (_, values) in indexed_command_values.entry(i).or_default() {
-                            positionals.extend(values.iter().cloned());
-                        }
-                    },
-                }
-            },
-
-            Component::Option(option_spec) => {
-                let mut force_trailing
-                    = false;
-
-                let all_names
-                    = once(&option_spec.primary_name)
-                        .chain(option_spec.aliases.iter())
-                        .collect::<Vec<_>>();
-
-                for (_, values) in indexed_command_values.entry(i).or_default() {
-                    let name
-                        = all_names[rng.random_range(0..all_names.len())];
-
-                    let mut args
-                        = vec![name.to_string()];
-
-                    if values.len() == 0 && option_spec.allow_boolean && (option_spec.min_len > 0 || option_spec.extra_len != Some(0)) {
-                        force_trailing = true;
-                    } else {
-                        let current_extra_len
-                            = values.len() - option_spec.min_len;
-
-                        args.extend(values.iter().cloned());
-
-                        if option_spec.extra_len != Some(0) || current_extra_len > 0 {
-                            force_trailing = true;
-                        }
-                    }
-
-                    if force_trailing {
-                        trailing_options.push(args);
-                    } else {
-                        interlaced_options.push(args);
-                    }
-                }
-            },
-        }
-    }
-
-    let mut command_line_segments
-        = positionals.into_iter()
-            .map(|positional| vec![positional])
-            .collect::<Vec<_>>();
-
-    command_line_segments
-        = insert_b_into_a_randomly(rng, command_line_segments, interlaced_options);
-
-    command_line_segments
-        .extend(trailing_options);
-
-    let command_line = command_line_segments.into_iter()
-        .flatten()
-        .collect();
-
-    command_line
-}
+use crate::{builder::CliBuilder, fuzzy::{gen_random_command_line, gen_random_command_spec, gen_random_command_values, gen_random_keyword}, Component, PositionalSpec, SelectionResult};
 
 #[test]
 fn test_gen_random_command_line() {
@@ -286,6 +21,22 @@ fn test_gen_random_command_line() {
         let command_spec
             = gen_random_command_spec(&mut rng, use_optional_positionals);
 
+        let mut cli_builder
+            = CliBuilder::new();
+
+        cli_builder
+            .add_command(&command_spec)
+            .precompile();
+
+        let mut determinized_cli_builder
+            = cli_builder.clone();
+
+        determinized_cli_builder
+            .set_determinize(true)
+            .precompile();
+
+        assert!(cli_builder.compile().determinize().is_some());
+
         for n2 in 0..100 {
             let command_values
                 = gen_random_command_values(&mut rng, &command_spec);
@@ -299,12 +50,6 @@ fn test_gen_random_command_line() {
                 }
 
                 let result = std::panic::catch_unwind(|| {
-                    let mut cli_builder
-                        = CliBuilder::new();
-
-                    cli_builder
-                        .add_command(&command_spec);
-
                     let command_line_args
                         = command_line.iter().map(|s| s.as_str()).collect::<Vec<_>>();
 
@@ -340,6 +85,16 @@ fn test_gen_random_command_line() {
                     println!("{:?}", state.values());
 
                     assert_eq!(state.values(), command_values_str);
+
+                    let determinized_selection_result
+                        = determinized_cli_builder.run(&command_line_args).unwrap()
+                            .resolve_state(|_| Ok(())).unwrap();
+
+                    let SelectionResult::Command(_, determinized_state, _) = determinized_selection_result else {
+                        panic!("Expected a command result from the determinized machine");
+                    };
+
+                    assert_eq!(determinized_state, state);
                 });
 
                 if let Err(err) = result {
@@ -350,3 +105,148 @@ fn test_gen_random_command_line() {
         }
     }
 }
+
+#[test]
+fn test_determinized_machine_with_multiple_commands() {
+    let mut rng = SipHasher::from("testy")
+        .into_rng();
+
+    for n1 in 0..200 {
+        let command_specs = (0..rng.random_range(2..5)).map(|_| {
+            let use_optional_positionals
+                = rng.random_bool(0.5);
+
+            gen_random_command_spec(&mut rng, use_optional_positionals)
+        }).collect::<Vec<_>>();
+
+        let mut cli_builder
+            = CliBuilder::new();
+
+        for command_spec in &command_specs {
+            cli_builder.add_command(command_spec);
+        }
+
+        let mut determinized_cli_builder
+            = cli_builder.clone();
+
+        determinized_cli_builder
+            .set_determinize(true)
+            .precompile();
+
+        cli_builder.precompile();
+
+        assert!(cli_builder.compile().determinize().is_some());
+
+        for n2 in 0..100 {
+            let command_spec
+                = &command_specs[rng.random_range(0..command_specs.len())];
+
+            let command_values
+                = gen_random_command_values(&mut rng, command_spec);
+
+            let mut command_line
+                = gen_random_command_line(&mut rng, command_spec, &command_values);
+
+            // Also exercise the error paths
+            if rng.random_bool(0.2) && !command_line.is_empty() {
+                command_line.remove(rng.random_range(0..command_line.len()));
+            }
+
+            let command_line_args
+                = command_line.iter().map(|s| s.as_str()).collect::<Vec<_>>();
+
+            let resolve = |cli_builder: &CliBuilder| {
+                format!("{:?}", cli_builder.run(&command_line_args).and_then(|mut selector| selector.resolve_state(|_| Ok(()))))
+            };
+
+            assert_eq!(resolve(&determinized_cli_builder), resolve(&cli_builder), "{} / {}: {:?}", n1, n2, command_line);
+        }
+    }
+}
+
+#[test]
+fn test_dispatched_machine_with_multiple_commands() {
+    let mut rng = SipHasher::from("dispatch")
+        .into_rng();
+
+    for n1 in 0..200 {
+        let vocabulary = (0..3)
+            .map(|_| gen_random_keyword(&mut rng))
+            .collect::<Vec<_>>();
+
+        let gen_random_path = |rng: &mut _| {
+            (0..Rng::random_range(rng, 0..3))
+                .map(|_| vocabulary[Rng::random_range(rng, 0..vocabulary.len())].clone())
+                .collect::<Vec<_>>()
+        };
+
+        let command_specs = (0..rng.random_range(2..6)).map(|_| {
+            let use_optional_positionals
+                = rng.random_bool(0.5);
+
+            let mut command_spec
+                = gen_random_command_spec(&mut rng, use_optional_positionals);
+
+            command_spec.primary_path = gen_random_path(&mut rng);
+
+            if rng.random_bool(0.3) {
+                command_spec.aliases.push(gen_random_path(&mut rng));
+            }
+
+            // Let some keyword positionals compete with the paths
+            for component in &mut command_spec.components {
+                if let Component::Positional(PositionalSpec::Keyword {expected}) = component {
+                    if rng.random_bool(0.3) {
+                        *expected = vocabulary[rng.random_range(0..vocabulary.len())].clone();
+                    }
+                }
+            }
+
+            command_spec
+        }).collect::<Vec<_>>();
+
+        let mut lazy_cli_builder
+            = CliBuilder::new();
+
+        for command_spec in &command_specs {
+            lazy_cli_builder.add_command(command_spec);
+        }
+
+        let mut cli_builder
+            = lazy_cli_builder.clone();
+
+        cli_builder.precompile();
+
+        for n2 in 0..100 {
+            let command_spec
+                = &command_specs[rng.random_range(0..command_specs.len())];
+
+            let command_values
+                = gen_random_command_values(&mut rng, command_spec);
+
+            let path = once(&command_spec.primary_path)
+                .chain(&command_spec.aliases)
+                .nth(rng.random_range(0..=command_spec.aliases.len()))
+                .unwrap();
+
+            let mut command_line
+                = path.iter().cloned()
+                    .chain(gen_random_command_line(&mut rng, command_spec, &command_values))
+                    .collect::<Vec<_>>();
+
+            // Also exercise the error paths
+            if rng.random_bool(0.2) && !command_line.is_empty() {
+                command_line.remove(rng.random_range(0..command_line.len()));
+            }
+
+            let command_line_args
+                = command_line.iter().map(|s| s.as_str()).collect::<Vec<_>>();
+
+            let resolve = |cli_builder: &CliBuilder| {
+                format!("{:?}", cli_builder.run(&command_line_args).and_then(|mut selector| selector.resolve_state(|_| Ok(()))))
+            };
+
+            assert_eq!(resolve(&lazy_cli_builder), resolve(&cli_builder), "{} / {}: {:?}", n1, n2, command_line);
+        }
+    }
+}
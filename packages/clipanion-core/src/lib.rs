@@ -1,11 +1,24 @@
+mod completion;
+mod dispatch;
 mod errors;
 mod machine;
 mod node;
 mod selector;
 mod shared;
+mod suggestions;
+mod trace;
+mod trail;
 mod transition;
 mod usage;
 
+/**
+ * Generators for random command specs and the command lines that match them;
+ * used by the fuzzy tests and the benchmarks.
+ */
+#[cfg(any(test, feature = "fuzzy"))]
+#[doc(hidden)]
+pub mod fuzzy;
+
 #[cfg(test)]
 mod fuzzy_tests;
 
@@ -13,9 +26,13 @@ pub mod builder;
 pub mod runner;
 
 pub use builder::*;
+pub use completion::Completion;
 pub use errors::*;
 pub use machine::Machine;
 pub use runner::*;
 pub use selector::*;
 pub use shared::{HELP_COMMAND_INDEX, UserArg};
+pub use suggestions::{autocorrect, edit_distance, Suggestion};
+pub use trace::{DropReason, PruneStep, StderrTraceSink, TraceEvent, TraceSink};
+pub use trail::{Trail, ValueList};
 pub use usage::*;
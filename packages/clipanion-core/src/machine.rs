@@ -1,4 +1,4 @@
-use std::{collections::HashSet, fmt::Debug};
+use std::{collections::{HashMap, HashSet}, fmt::Debug};
 
 use crate::{node::Node, shared::{is_terminal_node, ArgKey, CUSTOM_NODE_ID, ERROR_NODE_ID, INITIAL_NODE_ID, SUCCESS_NODE_ID}, transition::Transition};
 
@@ -72,9 +72,9 @@ impl<'a, TCheck, TReducer> Machine<'a, TCheck, TReducer> {
         self.nodes[from].statics.entry(key).or_default().push(Transition::new(to, reducer));
     }
 
-    pub fn new_any_of<I>(machines: I) -> Self where TCheck: Clone, TReducer: Clone + Default, I: IntoIterator<Item = Self> {
+    fn new_empty(contexts: Vec<usize>) -> Self {
         let mut out = Machine {
-            contexts: vec![],
+            contexts,
             nodes: vec![],
         };
 
@@ -82,6 +82,13 @@ impl<'a, TCheck, TReducer> Machine<'a, TCheck, TReducer> {
             out.nodes.push(Node::new());
         }
 
+        out
+    }
+
+    pub fn new_any_of<I>(machines: I) -> Self where TCheck: Clone, TReducer: Clone + Default, I: IntoIterator<Item = Self> {
+        let mut out
+            = Machine::new_empty(vec![]);
+
         for machine in machines {
             let context_offset
                 = out.contexts.len();
@@ -196,4 +203,212 @@ impl<'a, TCheck, TReducer> Machine<'a, TCheck, TReducer> {
             }
         }
     }
+
+    /**
+     * Collect the nodes the runner visits when reaching the given node, in
+     * the order it pushes their states (shortcuts first).
+     */
+    fn collect_closure(&self, id: usize, visited: &mut HashSet<usize>, out: &mut Vec<usize>) {
+        visited.insert(id);
+
+        for shortcut in &self.nodes[id].shortcuts {
+            if !visited.contains(&shortcut.to) {
+                self.collect_closure(shortcut.to, visited, out);
+            }
+        }
+
+        out.push(id);
+    }
+
+    /**
+     * Build an equivalent machine where each node stands for the set of nodes
+     * the runner would otherwise track separately: the ones linked through
+     * shortcuts, and the targets of transitions that apply the same reducer
+     * on the same input. States then only need to be cloned once per set
+     * rather than once per node.
+     *
+     * Dynamic transitions are kept as guarded transitions, and transitions to
+     * the terminal nodes are preserved (up to two of each, which is enough to
+     * detect ambiguities) so that the selector sees the same candidates.
+     * Returns `None` if the machine can't be determinized, for instance if a
+     * shortcut applies a reducer, or if the result would be too large.
+     */
+    pub fn determinize(&self) -> Option<Self> where TCheck: Clone + PartialEq, TReducer: Clone + PartialEq + Default {
+        let has_reducing_shortcuts = self.nodes.iter()
+            .flat_map(|node| node.shortcuts.iter())
+            .any(|shortcut| shortcut.reducer != TReducer::default());
+
+        let initial_node
+            = &self.nodes[INITIAL_NODE_ID];
+
+        if has_reducing_shortcuts || !initial_node.statics.is_empty() || !initial_node.dynamics.is_empty() {
+            return None;
+        }
+
+        let mut determinizer = Determinizer {
+            nfa: self,
+            dfa: Machine::new_empty(self.contexts.clone()),
+            ids: HashMap::new(),
+            queue: vec![],
+            max_nodes: self.nodes.len() * 8 + 64,
+        };
+
+        let mut initial_shortcuts
+            = vec![];
+
+        for shortcut in &initial_node.shortcuts {
+            let members
+                = determinizer.closure_of([shortcut.to]);
+
+            determinizer.push_transitions(members, &shortcut.reducer, &mut initial_shortcuts)?;
+        }
+
+        determinizer.dfa.nodes[INITIAL_NODE_ID].shortcuts
+            = initial_shortcuts;
+
+        while let Some((dfa_id, members)) = determinizer.queue.pop() {
+            let mut statics: HashMap<ArgKey<'a>, Vec<(TReducer, Vec<usize>)>>
+                = HashMap::new();
+            let mut dynamics: Vec<(TCheck, TReducer, Vec<usize>)>
+                = vec![];
+
+            for &member in &members {
+                let node
+                    = &self.nodes[member];
+
+                for (key, transitions) in &node.statics {
+                    let groups
+                        = statics.entry(*key).or_default();
+
+                    for transition in transitions {
+                        match groups.iter_mut().find(|(reducer, _)| *reducer == transition.reducer) {
+                            Some((_, targets)) => targets.push(transition.to),
+                            None => groups.push((transition.reducer.clone(), vec![transition.to])),
+                        }
+                    }
+                }
+
+                for (check, transition) in &node.dynamics {
+                    match dynamics.iter_mut().find(|(other_check, reducer, _)| other_check == check && *reducer == transition.reducer) {
+                        Some((_, _, targets)) => targets.push(transition.to),
+                        None => dynamics.push((check.clone(), transition.reducer.clone(), vec![transition.to])),
+                    }
+                }
+            }
+
+            for (key, groups) in statics {
+                let mut transitions
+                    = vec![];
+
+                for (reducer, targets) in groups {
+                    let members
+                        = determinizer.closure_of(targets);
+
+                    determinizer.push_transitions(members, &reducer, &mut transitions)?;
+                }
+
+                determinizer.dfa.nodes[dfa_id].statics.insert(key, transitions);
+            }
+
+            for (check, reducer, targets) in dynamics {
+                let members
+                    = determinizer.closure_of(targets);
+
+                let mut transitions
+                    = vec![];
+
+                determinizer.push_transitions(members, &reducer, &mut transitions)?;
+
+                for transition in transitions {
+                    determinizer.dfa.nodes[dfa_id].dynamics.push((check.clone(), transition));
+                }
+            }
+        }
+
+        Some(determinizer.dfa)
+    }
+}
+
+// Past this many occurrences of a node in a set, the runner would only
+// produce states that are indistinguishable from the ones it already has
+const MAX_MULTIPLICITY: usize = 2;
+
+struct Determinizer<'m, 'a, TCheck, TReducer> {
+    nfa: &'m Machine<'a, TCheck, TReducer>,
+    dfa: Machine<'a, TCheck, TReducer>,
+
+    // The nodes of the original machine each node stands for, in the order
+    // the runner would have visited them
+    ids: HashMap<Vec<usize>, usize>,
+    queue: Vec<(usize, Vec<usize>)>,
+
+    max_nodes: usize,
+}
+
+impl<'m, 'a, TCheck, TReducer> Determinizer<'m, 'a, TCheck, TReducer> {
+    fn closure_of<I: IntoIterator<Item = usize>>(&self, targets: I) -> Vec<usize> {
+        let mut members
+            = vec![];
+
+        for target in targets {
+            let mut chain
+                = vec![];
+
+            self.nfa.collect_closure(target, &mut HashSet::new(), &mut chain);
+
+            for id in chain {
+                if members.iter().filter(|&&member| member == id).count() < MAX_MULTIPLICITY {
+                    members.push(id);
+                }
+            }
+        }
+
+        members
+    }
+
+    /**
+     * Add the transitions leading to the given nodes of the original machine:
+     * one to the node standing for the non-terminal ones, and one for each
+     * terminal node.
+     */
+    fn push_transitions(&mut self, members: Vec<usize>, reducer: &TReducer, out: &mut Vec<Transition<TReducer>>) -> Option<()> where TReducer: Clone {
+        let (terminals, members): (Vec<_>, Vec<_>)
+            = members.into_iter().partition(|&id| is_terminal_node(id));
+
+        if !members.is_empty() {
+            out.push(Transition::new(self.node_for(members)?, reducer.clone()));
+        }
+
+        for terminal in terminals {
+            out.push(Transition::new(terminal, reducer.clone()));
+        }
+
+        Some(())
+    }
+
+    fn node_for(&mut self, members: Vec<usize>) -> Option<usize> {
+        if let Some(id) = self.ids.get(&members) {
+            return Some(*id);
+        }
+
+        let context
+            = self.nfa.nodes[members[0]].context;
+
+        if members.iter().any(|&id| self.nfa.nodes[id].context != context) || self.dfa.nodes.len() >= self.max_nodes {
+            return None;
+        }
+
+        let mut node
+            = Node::new();
+
+        node.context = context;
+
+        let id
+            = self.dfa.inject_node(node);
+
+        self.ids.insert(members.clone(), id);
+        self.queue.push((id, members));
+
+        Some(id)
+    }
 }
@@ -40,7 +40,7 @@ impl<'a, TCheck, TReducer> Node<'a, TCheck, TReducer> {
         }
 
         for (key, transitions) in self.statics.iter() {
-            out.statics.insert(key.clone(), transitions.iter().map(|t| t.clone_to_offset(offset)).collect::<Vec<_>>());
+            out.statics.insert(*key, transitions.iter().map(|t| t.clone_to_offset(offset)).collect::<Vec<_>>());
         }
 
         out
@@ -1,6 +1,6 @@
 use std::fmt::Debug;
 
-use crate::{shared::{Arg, UserArg, ERROR_NODE_ID, INITIAL_NODE_ID}, transition::Transition, Machine};
+use crate::{shared::{is_terminal_node, Arg, UserArg, ERROR_NODE_ID, INITIAL_NODE_ID}, trace::{TraceEvent, TraceSink, Tracer}, transition::Transition, Machine};
 
 pub trait RunnerState {
     fn get_context_id(&self) -> usize;
@@ -18,22 +18,34 @@ pub trait ValidateTransition<'args, TState> {
 
 impl<'args, T, TState> ValidateTransition<'args, TState> for Option<T> where T: ValidateTransition<'args, TState> {
     fn check(&self, state: &TState, arg: &'args str) -> bool {
-        self.as_ref().map_or(true, |reducer| reducer.check(state, arg))
+        self.as_ref().is_none_or(|reducer| reducer.check(state, arg))
     }
 }
 
 pub trait DeriveState<'args, TState> {
     fn derive(&self, state: &mut TState, target_id: usize, arg: Arg<'args>) -> () where TState: RunnerState;
+
+    /**
+     * Describe the reducer in the parse traces; `None` if there's nothing to
+     * report.
+     */
+    fn describe(&self) -> Option<String> {
+        None
+    }
 }
 
 impl<'args, T, TState> DeriveState<'args, TState> for Option<T> where T: DeriveState<'args, TState> {
-    fn derive(&self, state: &mut TState, target_id: usize, arg: Arg<'args>) -> () where TState: RunnerState {
+    fn derive(&self, state: &mut TState, target_id: usize, arg: Arg<'args>) where TState: RunnerState {
         if let Some(reducer) = self {
             reducer.derive(state, target_id, arg)
         }
 
         state.set_node_id(target_id);
     }
+
+    fn describe(&self) -> Option<String> {
+        self.as_ref().and_then(|reducer| reducer.describe())
+    }
 }
 
 pub struct Runner<'machine, 'cmds, TCheck, TReducer, TFallback, TState> {
@@ -43,9 +55,16 @@ pub struct Runner<'machine, 'cmds, TCheck, TReducer, TFallback, TState> {
     states: Vec<TState>,
     next_states: Vec<TState>,
 
+    // States that reached the error node can't transition anymore; we keep
+    // them aside rather than moving them through every update, which would
+    // make long command lines quadratic.
+    error_states: Vec<TState>,
+
     // Colors are used to avoid infinite loops.
     node_colors: Vec<usize>,
     current_color: usize,
+
+    tracer: Tracer<'machine>,
 }
 
 impl<'machine, 'cmds, TCheck, TReducer, TFallback, TState> Runner<'machine, 'cmds, TCheck, TReducer, TFallback, TState> {
@@ -68,6 +87,27 @@ impl<'machine, 'cmds, TCheck, TReducer, TFallback, TState> Runner<'machine, 'cmd
         runner.digest()
     }
 
+    /**
+     * Same as `run`, but report each step to the given sink.
+     */
+    pub fn run_with_trace<'args>(machine: &'machine Machine<'cmds, TCheck, TReducer>, fallback: TFallback, args: &[&'args str], trace: &'machine mut dyn TraceSink) -> Vec<TState>
+    where
+        TCheck: ValidateTransition<'args, TState>,
+        TReducer: DeriveState<'args, TState> + Debug,
+        TFallback: Fn(TState, Arg<'args>) -> TState,
+        TState: Clone + RunnerState + Debug + Default
+    {
+        let mut runner
+            = Runner::<'machine, 'cmds, TCheck, TReducer, TFallback, TState>::new(machine, fallback);
+
+        runner.tracer = Tracer::new(Some(trace));
+
+        runner.send(args);
+
+        runner.update(Arg::EndOfInput);
+        runner.digest()
+    }
+
     /**
      * Run the state machine with the given arguments. Unlike `run`, this method will mark
      * all states that are not in an error state as successful. This can be useful when you
@@ -90,6 +130,8 @@ impl<'machine, 'cmds, TCheck, TReducer, TFallback, TState> Runner<'machine, 'cmd
             state.get_node_id() != ERROR_NODE_ID
         });
 
+        runner.error_states.clear();
+
         runner.digest()
     }
 
@@ -102,10 +144,12 @@ impl<'machine, 'cmds, TCheck, TReducer, TFallback, TState> Runner<'machine, 'cmd
         let mut runner = Runner {
             states: vec![],
             next_states: vec![],
+            error_states: vec![],
             machine,
             fallback,
             node_colors: vec![0; machine.nodes.len()],
             current_color: 0,
+            tracer: Tracer::disabled(),
         };
 
         let initial_state
@@ -135,13 +179,9 @@ impl<'machine, 'cmds, TCheck, TReducer, TFallback, TState> Runner<'machine, 'cmd
         TState: Clone + RunnerState,
         TState: Default + std::fmt::Debug
     {
-        if std::env::var("CLIPANION_DEBUG").is_ok() {
-            println!("========== Parsing ==========");
-        }
-
         self.update(Arg::StartOfInput);
 
-        for state in self.states.iter_mut() {
+        for state in self.states.iter_mut().chain(self.error_states.iter_mut()) {
             let node
                 = &self.machine.nodes[state.get_node_id()];
 
@@ -153,7 +193,7 @@ impl<'machine, 'cmds, TCheck, TReducer, TFallback, TState> Runner<'machine, 'cmd
         }
     }
 
-    fn transition_to<'args>(&mut self, from_state: &TState, transition: &Transition<TReducer>, token: Arg<'args>) -> ()
+    fn transition_to<'args>(&mut self, from_state: &TState, transition: &Transition<TReducer>, token: Arg<'args>)
     where
         TCheck: ValidateTransition<'args, TState>,
         TReducer: DeriveState<'args, TState> + Debug,
@@ -163,7 +203,7 @@ impl<'machine, 'cmds, TCheck, TReducer, TFallback, TState> Runner<'machine, 'cmd
         self.transition_to_color(from_state, transition, token, self.current_color);
     }
 
-    fn transition_to_color<'args>(&mut self, from_state: &TState, transition: &Transition<TReducer>, token: Arg<'args>, color: usize) -> ()
+    fn transition_to_color<'args>(&mut self, from_state: &TState, transition: &Transition<TReducer>, token: Arg<'args>, color: usize)
     where
         TCheck: ValidateTransition<'args, TState>,
         TReducer: DeriveState<'args, TState> + Debug,
@@ -174,9 +214,19 @@ impl<'machine, 'cmds, TCheck, TReducer, TFallback, TState> Runner<'machine, 'cmd
 
         transition.reducer.derive(&mut next_state, transition.to, token);
 
-        if std::env::var("CLIPANION_DEBUG").is_ok() {
-            println!("  [{}] {} -> {} (reducer: {:?})", from_state.get_context_id(), from_state.get_node_id(), transition.to, transition.reducer);
-        }
+        // States only get their context once the start of the input has been
+        // processed, but the nodes know which command they belong to
+        let context_node_id = match is_terminal_node(transition.to) {
+            true => from_state.get_node_id(),
+            false => transition.to,
+        };
+
+        self.tracer.record(|| TraceEvent::Transition {
+            context_id: self.machine.nodes[context_node_id].context,
+            from: from_state.get_node_id(),
+            to: transition.to,
+            reducer: transition.reducer.describe(),
+        });
 
         self.node_colors[transition.to] = color;
 
@@ -192,23 +242,25 @@ impl<'machine, 'cmds, TCheck, TReducer, TFallback, TState> Runner<'machine, 'cmd
         self.next_states.push(next_state);
     }
 
-    pub fn update<'args>(&mut self, token: Arg<'args>) -> ()
+    pub fn update<'args>(&mut self, token: Arg<'args>)
     where
         TCheck: ValidateTransition<'args, TState>,
         TReducer: DeriveState<'args, TState> + Debug,
         TFallback: Fn(TState, Arg<'args>) -> TState,
         TState: Clone + RunnerState + Debug
     {
-        if std::env::var("CLIPANION_DEBUG").is_ok() {
-            println!("{:?}", token);
-        }
+        self.tracer.record(|| match token {
+            Arg::StartOfInput => TraceEvent::StartOfInput,
+            Arg::User(UserArg {value, index}) => TraceEvent::Argument {index, value: value.to_string()},
+            Arg::EndOfInput | Arg::EndOfPartialInput => TraceEvent::EndOfInput,
+        });
 
         let states
             = std::mem::take(&mut self.states);
 
         for state in states {
             if state.get_node_id() == ERROR_NODE_ID {
-                self.next_states.push(state);
+                self.error_states.push(state);
                 continue;
             }
 
@@ -239,6 +291,11 @@ impl<'machine, 'cmds, TCheck, TReducer, TFallback, TState> Runner<'machine, 'cmd
             }
 
             if !transitioned {
+                self.tracer.record(|| TraceEvent::NoTransition {
+                    context_id: state.get_context_id(),
+                    from: state.get_node_id(),
+                });
+
                 self.next_states.push((self.fallback)(state, token));
             }
         }
@@ -252,21 +309,43 @@ impl<'machine, 'cmds, TCheck, TReducer, TFallback, TState> Runner<'machine, 'cmd
         self.trim_shortest_branches();
     }
 
-    fn trim_shortest_branches(&mut self) -> ()
+    fn trim_shortest_branches(&mut self)
     where
         TState: RunnerState
     {
         let max_keyword_count
             = self.states.iter()
+                .chain(self.error_states.iter())
                 .map(|state| state.get_keyword_count())
                 .max();
 
         if let Some(max_keyword_count) = max_keyword_count {
-            self.states.retain(|state| state.get_keyword_count() == max_keyword_count);
+            let tracer
+                = &mut self.tracer;
+
+            let mut is_longest_branch = |state: &TState| {
+                let keyword_count
+                    = state.get_keyword_count();
+
+                if keyword_count != max_keyword_count {
+                    tracer.record(|| TraceEvent::Trim {
+                        context_id: state.get_context_id(),
+                        node_id: state.get_node_id(),
+                        keyword_count,
+                        max_keyword_count,
+                    });
+                }
+
+                keyword_count == max_keyword_count
+            };
+
+            self.states.retain(&mut is_longest_branch);
+            self.error_states.retain(&mut is_longest_branch);
         }
     }
 
-    pub fn digest(self) -> Vec<TState> {
-        self.states
+    pub fn digest(mut self) -> Vec<TState> {
+        self.error_states.append(&mut self.states);
+        self.error_states
     }
 }
@@ -1,8 +1,63 @@
-use std::collections::BTreeSet;
+use std::collections::{BTreeSet, HashMap};
 
 use itertools::Itertools;
 
-use crate::{shared::{ERROR_NODE_ID, SUCCESS_NODE_ID}, BuiltinCommand, CommandError, CommandSpec, Component, Error, State};
+use crate::{shared::{UserArg, ERROR_NODE_ID, SUCCESS_NODE_ID}, suggestions, trace::{DropReason, PruneStep, TraceEvent, TraceSink, Tracer}, BuiltinCommand, CommandError, CommandSpec, Component, Error, OptionGroup, OptionSpec, State};
+
+/**
+ * Check the relations between the options set in the given state, returning
+ * the first one that isn't satisfied.
+ */
+fn check_option_groups(command: &CommandSpec, state: &State<'_>) -> Option<CommandError> {
+    let is_set = |option_id: &usize| {
+        state.option_values.contains(*option_id)
+    };
+
+    let name = |option_id: &usize| {
+        command.components[*option_id].is_option()
+            .map_or_else(String::new, |option| option.primary_name.clone())
+    };
+
+    for group in &command.option_groups {
+        match group {
+            OptionGroup::Exclusive {options} => {
+                let set_options = options.iter()
+                    .filter(|option_id| is_set(option_id))
+                    .collect::<Vec<_>>();
+
+                if set_options.len() > 1 {
+                    return Some(CommandError::ConflictingOptions(set_options.into_iter().map(name).collect()));
+                }
+            },
+
+            OptionGroup::AtLeastOne {options} => {
+                if !options.iter().any(is_set) {
+                    return Some(CommandError::MissingOneOfOptions(options.iter().map(name).collect()));
+                }
+            },
+
+            OptionGroup::Requires {option, requirements} => {
+                if is_set(option) && !requirements.iter().all(is_set) {
+                    return Some(CommandError::MissingRequirements(name(option), requirements.iter().filter(|option_id| !is_set(option_id)).map(name).collect()));
+                }
+            },
+
+            OptionGroup::RequiredIf {option, conditions} => {
+                if !is_set(option) && conditions.iter().any(is_set) {
+                    return Some(CommandError::MissingOptionIf(name(option), conditions.iter().filter(|option_id| is_set(option_id)).map(name).collect()));
+                }
+            },
+
+            OptionGroup::RequiredUnless {option, conditions} => {
+                if !is_set(option) && !conditions.iter().any(is_set) {
+                    return Some(CommandError::MissingOptionUnless(name(option), conditions.iter().map(name).collect()));
+                }
+            },
+        }
+    }
+
+    None
+}
 
 #[derive(Debug)]
 pub enum SelectionResult<'cmds, 'args, T> {
@@ -30,25 +85,174 @@ impl<'cmds, 'args> Selector<'cmds, 'args> {
         }
     }
 
-    fn prune_unsuccessful_nodes<'a>(&mut self) -> Result<(), Error<'cmds>> {
+    /**
+     * Fill the options that haven't been set on the command line with values
+     * coming from elsewhere (environment, configuration files, ...), so that
+     * they go through the same hydration and required checks as regular
+     * arguments. Flags expect a single boolean-like value; falsy values are
+     * routed to the `--no-` variant of the option when it exists.
+     *
+     * Sources are applied by decreasing precedence: once an option has been
+     * set by one of them, later calls will leave it alone. Options exclusive
+     * with one set on the command line are left alone as well, so that the
+     * command line can override them without triggering a conflict.
+     */
+    pub fn apply_fallbacks<F: Fn(&'cmds CommandSpec, &'cmds OptionSpec) -> Option<Vec<&'args str>>>(&mut self, f: F) {
+        let fallback_index
+            = self.args.len();
+
+        for state in &mut self.states {
+            if state.node_id != SUCCESS_NODE_ID {
+                continue;
+            }
+
+            let command
+                = self.commands[state.context_id];
+
+            let mut used_names = state.option_values.iter()
+                .filter_map(|(id, _)| command.components[id].is_option())
+                .flat_map(|option| option.all_names())
+                .collect::<Vec<_>>();
+
+            let cli_ids = state.option_values.iter()
+                .map(|(id, _)| id)
+                .filter(|id| !state.fallback_ids.contains(id))
+                .collect::<Vec<_>>();
+
+            for (option_id, component) in command.components.iter().enumerate() {
+                let Some(option) = component.is_option() else {
+                    continue;
+                };
+
+                if command.is_negation(option) || option.all_names().any(|name| used_names.contains(&name)) {
+                    continue;
+                }
+
+                let negated_names = option.all_names()
+                    .filter_map(|name| name.strip_prefix("--"))
+                    .map(|name| format!("--no-{}", name))
+                    .collect::<Vec<_>>();
+
+                if negated_names.iter().any(|name| used_names.contains(&name.as_str())) {
+                    continue;
+                }
+
+                let is_overridden = command.option_groups.iter().any(|group| match group {
+                    OptionGroup::Exclusive {options} => options.contains(&option_id) && options.iter().any(|id| cli_ids.contains(id)),
+                    _ => false,
+                });
+
+                if is_overridden {
+                    continue;
+                }
+
+                let Some(values) = f(command, option) else {
+                    continue;
+                };
+
+                let (target_id, values) = if option.min_len == 0 && option.extra_len == Some(0) {
+                    match values.first().map(|value| value.to_lowercase()).as_deref() {
+                        Some("1" | "true" | "yes" | "on") => {
+                            (option_id, vec![])
+                        },
+
+                        Some("0" | "false" | "no" | "off") => {
+                            let negated_id = command.components.iter()
+                                .position(|component| component.is_option().is_some_and(|other| negated_names.contains(&other.primary_name)));
+
+                            let Some(negated_id) = negated_id else {
+                                continue;
+                            };
+
+                            (negated_id, vec![])
+                        },
+
+                        _ => {
+                            continue;
+                        },
+                    }
+                } else {
+                    (option_id, values)
+                };
+
+                if values.len() < command.components[target_id].is_option().unwrap().min_len {
+                    continue;
+                }
+
+                let values = values.into_iter()
+                    .map(|value| UserArg {value, index: fallback_index})
+                    .collect();
+
+                used_names.extend(command.components[target_id].is_option().unwrap().all_names());
+                state.option_values.push((target_id, values));
+                state.fallback_ids.push(target_id);
+            }
+        }
+    }
+
+    /**
+     * Fill the options that haven't been set on the command line with the
+     * value of their environment variable, if any. Options accepting more
+     * than one value split it on whitespaces.
+     */
+    pub fn apply_env_vars(&mut self, env_vars: &'args HashMap<String, String>, env_prefix: Option<&str>) {
+        self.apply_fallbacks(|_, option| {
+            let value
+                = env_vars.get(&option.env_var_name(env_prefix)?)?;
+
+            match option.min_len <= 1 && option.extra_len.is_some_and(|extra_len| extra_len <= 1) {
+                true => Some(vec![value.as_str()]),
+                false => Some(value.split_whitespace().collect()),
+            }
+        });
+    }
+
+    fn trace_prune_step(&self, tracer: &mut Tracer<'_>, step: PruneStep) {
+        tracer.record(|| TraceEvent::Prune {
+            step,
+            remaining: self.candidates.clone(),
+        });
+    }
+
+    fn prune_unsuccessful_nodes(&mut self, tracer: &mut Tracer<'_>) -> Result<(), Error<'cmds>> {
         let owned_candidates
             = std::mem::take(&mut self.candidates);
 
         let successful_candidates
             = owned_candidates.into_iter()
-                .filter(|id| self.states[*id].node_id == SUCCESS_NODE_ID)
+                .filter(|id| {
+                    let state
+                        = &self.states[*id];
+
+                    if state.node_id != SUCCESS_NODE_ID {
+                        tracer.record(|| TraceEvent::Drop {
+                            state_id: *id,
+                            context_id: state.context_id,
+                            reason: DropReason::Unsuccessful,
+                        });
+                    }
+
+                    state.node_id == SUCCESS_NODE_ID
+                })
                 .collect::<Vec<_>>();
 
         self.candidates = successful_candidates;
         Ok(())
     }
 
-    fn fail_missing_required_options<'a>(&mut self) -> Result<(), Error<'cmds>> {
+    fn fail_missing_required_options(&mut self, tracer: &mut Tracer<'_>) -> Result<Vec<(usize, CommandError)>, Error<'cmds>> {
         let mut has_valid_states
             = false;
         let mut has_otherwise_valid_states
             = None;
 
+        // The states moved to the error node below; they'd be pruned along
+        // with the unsuccessful ones otherwise, but we want to report them
+        // as dropped here, and to keep their errors around in case no other
+        // state makes it through
+        let mut rejections
+            = vec![];
+
         for &id in self.candidates.iter() {
             let state
                 = &mut self.states[id];
@@ -62,36 +266,56 @@ impl<'cmds, 'args> Selector<'cmds, 'args> {
 
             let missing_required_options
                 = command.required_options.iter()
-                    .filter(|&option_id| !state.option_values.iter().any(|(id, _)| id == option_id))
+                    .filter(|&option_id| !state.option_values.contains(*option_id))
                     .map(|option_id| self.commands[state.context_id].components[*option_id].is_option().unwrap().primary_name.as_str())
                     .collect::<Vec<_>>();
 
-            if missing_required_options.len() == 0 {
-                has_valid_states = true;
-            } else {
+            let error = match missing_required_options.is_empty() {
+                true => check_option_groups(command, state),
+                false => Some(CommandError::MissingOptionArguments(missing_required_options.into_iter().map(|option| option.to_string()).collect::<Vec<_>>())),
+            };
+
+            if let Some(error) = error {
+                tracer.record(|| TraceEvent::Drop {
+                    state_id: id,
+                    context_id: state.context_id,
+                    reason: DropReason::InvalidOptions {error: error.to_string()},
+                });
+
+                rejections.push((id, error.clone()));
                 state.node_id = ERROR_NODE_ID;
                 if has_otherwise_valid_states.is_none() {
-                    has_otherwise_valid_states = Some(Some((command, missing_required_options)));
+                    has_otherwise_valid_states = Some(Some((command, error)));
                 } else if has_otherwise_valid_states.is_some() {
                     has_otherwise_valid_states = Some(None);
                 }
+            } else {
+                has_valid_states = true;
             }
         }
 
         if !has_valid_states {
-            if let Some(Some((command, missing_required_options))) = has_otherwise_valid_states {
-                return Err(Error::CommandError(command, CommandError::MissingOptionArguments(missing_required_options.into_iter().map(|option| option.to_string()).collect::<Vec<_>>())));
+            if let Some(Some((command, error))) = has_otherwise_valid_states {
+                return Err(Error::CommandError(command, error));
             }
         }
 
-        Ok(())
+        self.candidates.retain(|id| !rejections.iter().any(|(rejected_id, _)| rejected_id == id));
+
+        Ok(rejections)
     }
 
-    fn prune_by_hydration_results(&mut self, mut hydration_errors: Vec<(usize, CommandError)>) -> Result<(), Error<'cmds>> {
+    fn prune_by_hydration_results(&mut self, mut hydration_errors: Vec<(usize, CommandError)>, tracer: &mut Tracer<'_>) -> Result<(), Error<'cmds>> {
         let mut failed_hydrations
             = vec![false; self.states.len()];
 
-        for (id, _) in hydration_errors.iter() {
+        for (id, error) in hydration_errors.iter() {
+            tracer.record(|| TraceEvent::Drop {
+                state_id: *id,
+                context_id: self.states[*id].context_id,
+                reason: DropReason::HydrationFailed {error: error.to_string()},
+            });
+
             failed_hydrations[*id] = true;
         }
 
@@ -99,7 +323,7 @@ impl<'cmds, 'args> Selector<'cmds, 'args> {
             !failed_hydrations[*id]
         });
 
-        if self.candidates.len() == 0 {
+        if self.candidates.is_empty() {
             if hydration_errors.len() == 1 {
                 let (id, error)
                     = hydration_errors.remove(0);
@@ -113,7 +337,7 @@ impl<'cmds, 'args> Selector<'cmds, 'args> {
                 return Err(Error::CommandError(command_spec, error));
             } else {
                 let suggested_commands = hydration_errors.into_iter()
-                    .map(|(id, _)| self.commands[id])
+                    .map(|(id, _)| self.commands[self.states[id].context_id])
                     .collect::<Vec<_>>();
 
                 return Err(Error::NotFound(suggested_commands));
@@ -135,7 +359,7 @@ impl<'cmds, 'args> Selector<'cmds, 'args> {
      * 
      * The first option is more greedy, so we remove the second one.
      */
-    fn prune_by_greediness<'a>(&mut self) {
+    fn prune_by_greediness(&mut self, tracer: &mut Tracer<'_>) {
         let owned_candidates
             = std::mem::take(&mut self.candidates);
 
@@ -210,7 +434,7 @@ impl<'cmds, 'args> Selector<'cmds, 'args> {
 
         impl PartialOrd for Flip {
             fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-                Some(other.0.cmp(&self.0))
+                Some(self.cmp(other))
             }
         }
 
@@ -222,7 +446,7 @@ impl<'cmds, 'args> Selector<'cmds, 'args> {
 
         let mut states_with_positional_tracks = owned_candidates.into_iter().map(|id| {
             let positional_track = self.states[id].positional_values.iter().map(|(idx, values)| {
-                (Flip(*idx), values.len(), values.iter().map(|value| Flip(value.index)).collect::<Vec<_>>())
+                (Flip(idx), values.len(), values.iter().map(|value| Flip(value.index)).collect::<Vec<_>>())
             }).collect::<Vec<_>>();
 
             (id, positional_track)
@@ -234,17 +458,23 @@ impl<'cmds, 'args> Selector<'cmds, 'args> {
 
         // We're now going to remove all the entries except for the first
         // one for each different command.
-        let mut seen
-            = vec![false; self.commands.len()];
+        let mut kept_ids
+            = vec![None; self.commands.len()];
 
         states_with_positional_tracks.retain(|(id, _)| {
             let context_id
                 = self.states[*id].context_id;
 
-            if seen[context_id] {
+            if let Some(kept_state_id) = kept_ids[context_id] {
+                tracer.record(|| TraceEvent::Drop {
+                    state_id: *id,
+                    context_id,
+                    reason: DropReason::LessGreedy {kept_state_id},
+                });
+
                 false
             } else {
-                seen[context_id] = true;
+                kept_ids[context_id] = Some(*id);
                 true
             }
         });
@@ -254,7 +484,7 @@ impl<'cmds, 'args> Selector<'cmds, 'args> {
             .collect::<Vec<_>>();
     }
 
-    fn prune_by_unused_positionals<'a>(&mut self) {
+    fn prune_by_unused_positionals(&mut self, tracer: &mut Tracer<'_>) {
         let mut states_with_unused_positional_count
             = self.candidates.iter()
                 .map(|&candidate_id| {
@@ -268,7 +498,7 @@ impl<'cmds, 'args> Selector<'cmds, 'args> {
                         = vec![false; command.components.len()];
 
                     for (idx, _) in state.positional_values.iter() {
-                        seen[*idx] = true;
+                        seen[idx] = true;
                     }
 
                     let unused_positionals_count = command.components.iter()
@@ -286,12 +516,22 @@ impl<'cmds, 'args> Selector<'cmds, 'args> {
         });
 
         let (_, min_unused_positional_count)
-            = states_with_unused_positional_count.first()
-                .expect("Expected at least one state")
-                .clone();
+            = *states_with_unused_positional_count.first()
+                .expect("Expected at least one state");
+
+        states_with_unused_positional_count.retain(|&(id, unused_positionals_count)| {
+            if unused_positionals_count != min_unused_positional_count {
+                tracer.record(|| TraceEvent::Drop {
+                    state_id: id,
+                    context_id: self.states[id].context_id,
+                    reason: DropReason::UnusedPositionals {
+                        unused_count: unused_positionals_count,
+                        min_unused_count: min_unused_positional_count,
+                    },
+                });
+            }
 
-        states_with_unused_positional_count.retain(|(_, unused_positionals_count)| {
-            *unused_positionals_count == min_unused_positional_count
+            unused_positionals_count == min_unused_positional_count
         });
 
         self.candidates = states_with_unused_positional_count.into_iter()
@@ -299,7 +539,7 @@ impl<'cmds, 'args> Selector<'cmds, 'args> {
             .collect();
     }
 
-    fn handle_everything_is_an_error<T>(&mut self) -> Result<SelectionResult<'cmds, 'args, T>, Error<'cmds>> {
+    fn handle_everything_is_an_error<T>(&mut self, rejections: Vec<(usize, CommandError)>, tracer: &mut Tracer<'_>) -> Result<SelectionResult<'cmds, 'args, T>, Error<'cmds>> {
         if self.args.len() == 1 && matches!(self.args[0], "--version" | "-v") {
             return Ok(SelectionResult::Builtin(BuiltinCommand::Version));
         }
@@ -312,13 +552,75 @@ impl<'cmds, 'args> Selector<'cmds, 'args> {
             return Ok(SelectionResult::Builtin(BuiltinCommand::Describe));
         }
 
-        if self.args.len() > 0 && self.args[0].starts_with("--clipanion-tokens") {
+        if !self.args.is_empty() && self.args[0] == "--clipanion-man" {
+            return Ok(SelectionResult::Builtin(BuiltinCommand::Manual(self.args[1..].to_vec())));
+        }
+
+        if !self.args.is_empty() && self.args[0].starts_with("--clipanion-complete") {
+            let command_line
+                = self.args[1..].to_vec();
+
+            let cursor
+                = self.args[0].strip_prefix("--clipanion-complete=")
+                    .and_then(|cursor| cursor.parse().ok())
+                    .unwrap_or(command_line.len().saturating_sub(1));
+
+            return Ok(SelectionResult::Builtin(BuiltinCommand::Complete(command_line, cursor)));
+        }
+
+        if !self.args.is_empty() && matches!(self.args[0], "--clipanion-explain" | "--clipanion-explain=json") {
+            return Ok(SelectionResult::Builtin(BuiltinCommand::Explain(self.args[1..].to_vec(), self.args[0] == "--clipanion-explain=json")));
+        }
+
+        if !self.args.is_empty() && self.args[0].starts_with("--clipanion-tokens") {
             return Ok(SelectionResult::Builtin(BuiltinCommand::Tokenize(self.args[1..].to_vec())));
         }
 
+        // Some commands matched the whole command line but were missing
+        // options; their errors are more precise than any typo suggestion
+        if let Some((id, error)) = rejections.first() {
+            let context_ids = rejections.iter()
+                .map(|(id, _)| self.states[*id].context_id)
+                .collect::<BTreeSet<_>>();
+
+            if context_ids.len() == 1 {
+                return Err(Error::CommandError(self.commands[self.states[*id].context_id], error.clone()));
+            }
+
+            let commands = context_ids.into_iter()
+                .map(|id| self.commands[id])
+                .collect::<Vec<_>>();
+
+            return Err(Error::NotFound(commands));
+        }
+
+        let suggestions
+            = suggestions::suggest(&self.commands, &self.args);
+
+        if let Some(suggestion) = suggestions.first() {
+            let is_single_command = suggestions.iter()
+                .all(|other| std::ptr::eq(other.command_spec, suggestion.command_spec));
+
+            if suggestion.is_option() && is_single_command {
+                return Err(Error::CommandError(suggestion.command_spec, CommandError::UnknownOption(suggestion.original.clone(), suggestion.candidates.clone())));
+            }
+
+            let commands = suggestions.iter()
+                .map(|suggestion| suggestion.command_spec)
+                .fold(vec![], |mut commands: Vec<&CommandSpec>, command| {
+                    if !commands.iter().any(|other| std::ptr::eq(*other, command)) {
+                        commands.push(command);
+                    }
+
+                    commands
+                });
+
+            return Err(Error::NotFound(commands));
+        }
+
         self.candidates = (0..self.states.len()).collect();
 
-        self.prune_by_greediness();
+        self.prune_by_greediness(tracer);
 
         let owned_candidates
             = std::mem::take(&mut self.candidates);
@@ -327,20 +629,35 @@ impl<'cmds, 'args> Selector<'cmds, 'args> {
             .map(|id| self.states[id].context_id)
             .collect::<BTreeSet<_>>();
 
+        // Hidden commands are still selectable, but shouldn't be advertised
+        // to the user when they didn't manage to reach them
         let commands = context_ids.into_iter()
             .map(|id| self.commands[id])
+            .filter(|command_spec| !command_spec.is_hidden)
             .collect::<Vec<_>>();
 
         Err(Error::NotFound(commands))
     }
 
     pub fn resolve_state<F: Fn(&State<'args>) -> Result<T, CommandError>, T>(&mut self, f: F) -> Result<SelectionResult<'cmds, 'args, T>, Error<'cmds>> {
-        if std::env::var("CLIPANION_DEBUG").is_ok() {
-            println!("========== Pre-selection states ==========");
+        self.resolve_state_traced(f, Tracer::disabled())
+    }
 
-            for state in &self.states {
-                println!("- {:?}", state);
-            }
+    /**
+     * Same as `resolve_state`, but report to the given sink why each of the
+     * candidates got discarded.
+     */
+    pub fn resolve_state_with_trace<F: Fn(&State<'args>) -> Result<T, CommandError>, T>(&mut self, f: F, trace: &mut dyn TraceSink) -> Result<SelectionResult<'cmds, 'args, T>, Error<'cmds>> {
+        self.resolve_state_traced(f, Tracer::new(Some(trace)))
+    }
+
+    fn resolve_state_traced<F: Fn(&State<'args>) -> Result<T, CommandError>, T>(&mut self, f: F, mut tracer: Tracer<'_>) -> Result<SelectionResult<'cmds, 'args, T>, Error<'cmds>> {
+        for (state_id, state) in self.states.iter().enumerate() {
+            tracer.record(|| TraceEvent::Candidate {
+                state_id,
+                context_id: state.context_id,
+                is_successful: state.node_id == SUCCESS_NODE_ID,
+            });
         }
 
         let help_contexts = self.states.iter()
@@ -351,33 +668,19 @@ impl<'cmds, 'args> Selector<'cmds, 'args> {
             .map(|id| self.commands[id])
             .collect::<Vec<_>>();
 
-        if help_contexts.len() > 0 && !self.states.iter().any(|state| state.node_id == SUCCESS_NODE_ID) {
+        if !help_contexts.is_empty() && !self.states.iter().any(|state| state.node_id == SUCCESS_NODE_ID) {
             return Ok(SelectionResult::Builtin(BuiltinCommand::Help(help_contexts)));
         }
 
-        self.fail_missing_required_options()?;
-
-        if std::env::var("CLIPANION_DEBUG").is_ok() {
-            println!("========== After failing missing required options ==========");
-
-            for state in &self.states {
-                println!("- {:?}", state);
-            }
-        }
-
-        if std::env::var("CLIPANION_DEBUG").is_ok() {
-            println!("========== Candidate filtering ==========");
-            println!("initial candidates: {:?}", self.candidates);
-        }
-
-        self.prune_unsuccessful_nodes()?;
+        let rejections
+            = self.fail_missing_required_options(&mut tracer)?;
+        self.trace_prune_step(&mut tracer, PruneStep::RequiredOptions);
 
-        if std::env::var("CLIPANION_DEBUG").is_ok() {
-            println!("after prune_unsuccessful_nodes: {:?}", self.candidates);
-        }
+        self.prune_unsuccessful_nodes(&mut tracer)?;
+        self.trace_prune_step(&mut tracer, PruneStep::UnsuccessfulNodes);
 
-        if self.candidates.len() == 0 {
-            return self.handle_everything_is_an_error();
+        if self.candidates.is_empty() {
+            return self.handle_everything_is_an_error(rejections, &mut tracer);
         }
 
         let hydration_results = self.candidates.iter()
@@ -391,23 +694,14 @@ impl<'cmds, 'args> Selector<'cmds, 'args> {
             = hydration_results.into_iter()
                 .partition_result();
 
-        self.prune_by_hydration_results(unsuccessful_hydrations)?;
-
-        if std::env::var("CLIPANION_DEBUG").is_ok() {
-            println!("after prune_by_hydration_results: {:?}", self.candidates);
-        }
-
-        self.prune_by_greediness();
-
-        if std::env::var("CLIPANION_DEBUG").is_ok() {
-            println!("after prune_by_greediness: {:?}", self.candidates);
-        }
+        self.prune_by_hydration_results(unsuccessful_hydrations, &mut tracer)?;
+        self.trace_prune_step(&mut tracer, PruneStep::HydrationResults);
 
-        self.prune_by_unused_positionals();
+        self.prune_by_greediness(&mut tracer);
+        self.trace_prune_step(&mut tracer, PruneStep::Greediness);
 
-        if std::env::var("CLIPANION_DEBUG").is_ok() {
-            println!("after prune_by_empty_positional_values: {:?}", self.candidates);
-        }
+        self.prune_by_unused_positionals(&mut tracer);
+        self.trace_prune_step(&mut tracer, PruneStep::UnusedPositionals);
 
         let owned_candidates
             = std::mem::take(&mut self.candidates);
@@ -427,11 +721,11 @@ impl<'cmds, 'args> Selector<'cmds, 'args> {
         let index
             = owned_candidates.first().unwrap();
 
-        if std::env::var("CLIPANION_DEBUG").is_ok() {
-            println!("========== Selected state ==========");
-            println!("{:?}", self.states[*index]);
-        }
-    
+        tracer.record(|| TraceEvent::Selected {
+            state_id: *index,
+            context_id: self.states[*index].context_id,
+        });
+
         let state
             = self.states.swap_remove(*index);
         let command_spec
@@ -0,0 +1,412 @@
+use crate::{CommandSpec, OptionSpec};
+
+/**
+ * A guess at what the user meant to type instead of one of their arguments.
+ * Keyword suggestions target a segment of the command path, option
+ * suggestions target an option name that none of the commands support.
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Suggestion<'cmds> {
+    pub command_spec: &'cmds CommandSpec,
+    pub index: usize,
+    pub original: String,
+    pub candidates: Vec<String>,
+    pub distance: usize,
+}
+
+impl<'cmds> Suggestion<'cmds> {
+    pub fn is_option(&self) -> bool {
+        self.original.starts_with('-')
+    }
+}
+
+/**
+ * Optimal string alignment distance; same as Levenshtein, except that
+ * swapping two adjacent characters only counts as a single edit.
+ */
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let a = a.chars().collect::<Vec<_>>();
+    let b = b.chars().collect::<Vec<_>>();
+
+    let mut rows
+        = vec![vec![0; b.len() + 1]; a.len() + 1];
+
+    for (i, row) in rows.iter_mut().enumerate() {
+        row[0] = i;
+    }
+
+    for (j, cell) in rows[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost
+                = if a[i - 1] == b[j - 1] {0} else {1};
+
+            let mut distance = (rows[i - 1][j] + 1)
+                .min(rows[i][j - 1] + 1)
+                .min(rows[i - 1][j - 1] + cost);
+
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(rows[i - 2][j - 2] + 1);
+            }
+
+            rows[i][j] = distance;
+        }
+    }
+
+    rows[a.len()][b.len()]
+}
+
+fn max_distance(value: &str) -> usize {
+    (value.trim_start_matches('-').chars().count() / 3).clamp(1, 3)
+}
+
+fn is_option_like(arg: &str) -> bool {
+    arg.starts_with('-') && arg != "-"
+}
+
+/**
+ * How many of the following arguments the given option consumes as values.
+ * Options whose values are optional aren't assumed to take any.
+ */
+fn value_count(options: &[&OptionSpec], arg: &str) -> usize {
+    if arg.contains('=') {
+        return 0;
+    }
+
+    let find_option = |name: &str| {
+        options.iter().find(|option| option.all_names().any(|option_name| option_name == name))
+    };
+
+    let required_values = |option: &OptionSpec| match option.allow_boolean {
+        true => 0,
+        false => option.min_len,
+    };
+
+    if let Some(option) = find_option(arg) {
+        return required_values(option);
+    }
+
+    if arg.starts_with("--") || arg.len() <= 2 {
+        return 0;
+    }
+
+    // -xvf archive.tar; the first option taking a value ends the batch, and
+    // only reads the next argument if nothing follows it within the batch
+    let short_names
+        = arg.chars().skip(1).collect::<Vec<_>>();
+
+    for (i, c) in short_names.iter().enumerate() {
+        if let Some(option) = find_option(&format!("-{}", c)).filter(|option| !option.is_flag()) {
+            return match i == short_names.len() - 1 {
+                true => required_values(option),
+                false => 0,
+            };
+        }
+    }
+
+    0
+}
+
+/**
+ * The arguments that the command could match against its path, along with
+ * their index; that's all of them except the options and their values.
+ */
+fn positionals<'a>(command_spec: &CommandSpec, args: &[&'a str]) -> Vec<(usize, &'a str)> {
+    let options = command_spec.components.iter()
+        .filter_map(|component| component.is_option())
+        .collect::<Vec<_>>();
+
+    let mut positionals
+        = vec![];
+    let mut pending_values
+        = 0;
+
+    for (index, &arg) in args.iter().enumerate() {
+        if arg == "--" {
+            break;
+        }
+
+        if pending_values > 0 {
+            pending_values -= 1;
+            continue;
+        }
+
+        if is_option_like(arg) {
+            pending_values = value_count(&options, arg);
+            continue;
+        }
+
+        positionals.push((index, arg));
+    }
+
+    positionals
+}
+
+fn suggest_keyword<'cmds>(command_spec: &'cmds CommandSpec, path: &[String], positionals: &[(usize, &str)]) -> Option<Suggestion<'cmds>> {
+    let mismatch = path.iter()
+        .zip(positionals.iter())
+        .position(|(segment, (_, arg))| segment != arg)?;
+
+    if positionals.len() < path.len() {
+        return None;
+    }
+
+    let is_rest_matching = path.iter()
+        .zip(positionals.iter())
+        .skip(mismatch + 1)
+        .all(|(segment, (_, arg))| segment == arg);
+
+    if !is_rest_matching {
+        return None;
+    }
+
+    let (index, arg)
+        = positionals[mismatch];
+
+    let distance
+        = edit_distance(arg, &path[mismatch]);
+
+    if distance > max_distance(&path[mismatch]) {
+        return None;
+    }
+
+    Some(Suggestion {
+        command_spec,
+        index,
+        original: arg.to_string(),
+        candidates: vec![path[mismatch].clone()],
+        distance,
+    })
+}
+
+fn suggest_options<'cmds>(command_spec: &'cmds CommandSpec, args: &[&str]) -> Vec<Suggestion<'cmds>> {
+    let options = command_spec.components.iter()
+        .filter_map(|component| component.is_option())
+        .collect::<Vec<_>>();
+
+    let is_known = |name: &str| {
+        matches!(name, "-h" | "--help") || options.iter().any(|option| option.all_names().any(|option_name| option_name == name))
+    };
+
+    // -xvf, -xvfarchive.tar; the first option taking a value ends the batch
+    let is_known_batch = |name: &str| {
+        if name.starts_with("--") || name.len() <= 2 {
+            return false;
+        }
+
+        for c in name.chars().skip(1) {
+            let short_name
+                = format!("-{}", c);
+
+            let option = options.iter()
+                .find(|option| option.all_names().any(|option_name| option_name == short_name));
+
+            match option {
+                Some(option) if !option.is_flag() => return true,
+                Some(_) => continue,
+                None if is_known(&short_name) => continue,
+                None => return false,
+            }
+        }
+
+        true
+    };
+
+    let mut suggestions
+        = vec![];
+
+    for (index, arg) in args.iter().enumerate() {
+        if *arg == "--" {
+            break;
+        }
+
+        if !is_option_like(arg) {
+            continue;
+        }
+
+        let name = arg.split_once('=')
+            .map_or(*arg, |(name, _)| name);
+
+        if is_known(name) || is_known_batch(name) {
+            continue;
+        }
+
+        let candidates = options.iter()
+            .filter(|option| !option.is_hidden)
+            .flat_map(|option| option.all_names())
+            .map(|option_name| (edit_distance(name, option_name), option_name))
+            .filter(|(distance, option_name)| *distance <= max_distance(option_name))
+            .collect::<Vec<_>>();
+
+        let distance = candidates.iter()
+            .map(|(distance, _)| *distance)
+            .min()
+            .unwrap_or(usize::MAX);
+
+        suggestions.push(Suggestion {
+            command_spec,
+            index,
+            original: name.to_string(),
+            candidates: candidates.into_iter()
+                .filter(|(candidate_distance, _)| *candidate_distance == distance)
+                .map(|(_, option_name)| option_name.to_string())
+                .collect(),
+            distance,
+        });
+    }
+
+    suggestions
+}
+
+/**
+ * Compare the arguments against the paths and option names of the given
+ * commands, and return the closest matches. Only the suggestions sharing the
+ * lowest edit distance are kept; option suggestions may have no candidates
+ * at all when the option is simply unknown to a command whose path matched.
+ */
+pub fn suggest<'cmds>(commands: &[&'cmds CommandSpec], args: &[&str]) -> Vec<Suggestion<'cmds>> {
+    let mut keyword_suggestions
+        = vec![];
+    let mut option_suggestions
+        = vec![];
+
+    for &command_spec in commands {
+        if command_spec.is_hidden {
+            continue;
+        }
+
+        let positionals
+            = positionals(command_spec, args);
+
+        let paths = std::iter::once(&command_spec.primary_path)
+            .chain(command_spec.aliases.iter());
+
+        let mut is_path_matching
+            = false;
+
+        for path in paths {
+            let is_prefix = path.iter()
+                .zip(positionals.iter())
+                .all(|(segment, (_, arg))| segment == arg);
+
+            if is_prefix && positionals.len() >= path.len() {
+                is_path_matching = true;
+            } else if let Some(suggestion) = suggest_keyword(command_spec, path, &positionals) {
+                keyword_suggestions.push(suggestion);
+            }
+        }
+
+        if is_path_matching {
+            option_suggestions.extend(suggest_options(command_spec, args));
+        }
+    }
+
+    let mut suggestions = match keyword_suggestions.is_empty() {
+        true => option_suggestions,
+        false => keyword_suggestions,
+    };
+
+    let min_distance = suggestions.iter()
+        .map(|suggestion| suggestion.distance)
+        .min();
+
+    suggestions.retain(|suggestion| Some(suggestion.distance) == min_distance);
+    suggestions.dedup_by(|a, b| std::ptr::eq(a.command_spec, b.command_spec) && a.index == b.index);
+
+    suggestions
+}
+
+/**
+ * Return the index and replacement of the mistyped argument if all the
+ * suggestions agree on a single fix, which is the only case where it's safe
+ * to apply it without asking the user to pick one.
+ */
+pub fn autocorrect(suggestions: &[Suggestion<'_>], args: &[&str]) -> Option<(usize, String)> {
+    let (first, rest)
+        = suggestions.split_first()?;
+
+    if first.candidates.len() != 1 {
+        return None;
+    }
+
+    let is_unambiguous = rest.iter()
+        .all(|suggestion| suggestion.index == first.index && suggestion.candidates == first.candidates);
+
+    if !is_unambiguous || first.index >= args.len() {
+        return None;
+    }
+
+    let replacement = match args[first.index].split_once('=') {
+        Some((_, value)) if first.is_option() => format!("{}={}", first.candidates[0], value),
+        _ => first.candidates[0].clone(),
+    };
+
+    Some((first.index, replacement))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{CommandSpec, Component, OptionSpec};
+
+    use super::{autocorrect, edit_distance, suggest};
+
+    fn command(path: &[&str], options: &[&str]) -> CommandSpec {
+        CommandSpec {
+            primary_path: path.iter().map(|segment| segment.to_string()).collect(),
+            components: options.iter().map(|name| Component::Option(OptionSpec::boolean(name))).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn it_computes_edit_distances() {
+        assert_eq!(edit_distance("commit", "commit"), 0);
+        assert_eq!(edit_distance("comit", "commit"), 1);
+        assert_eq!(edit_distance("cmomit", "commit"), 1);
+        assert_eq!(edit_distance("", "add"), 3);
+    }
+
+    #[test]
+    fn it_suggests_keywords() {
+        let commit = command(&["commit"], &[]);
+        let config = command(&["config", "get"], &[]);
+
+        let suggestions
+            = suggest(&[&commit, &config], &["comit", "-m", "msg"]);
+
+        assert_eq!(suggestions.len(), 1);
+        assert_eq!(suggestions[0].candidates, vec!["commit".to_string()]);
+        assert_eq!(autocorrect(&suggestions, &["comit", "-m", "msg"]), Some((0, "commit".to_string())));
+    }
+
+    #[test]
+    fn it_suggests_options_once_the_path_matches() {
+        let commit = command(&["commit"], &["--amend", "-a,--all"]);
+
+        let suggestions
+            = suggest(&[&commit], &["commit", "--ammend=true", "-a"]);
+
+        assert_eq!(suggestions.len(), 1);
+        assert_eq!(suggestions[0].original, "--ammend");
+        assert_eq!(autocorrect(&suggestions, &["commit", "--ammend=true", "-a"]), Some((1, "--amend=true".to_string())));
+    }
+
+    #[test]
+    fn it_skips_the_values_of_the_options() {
+        let mut commit
+            = command(&["commit"], &["-a,--all"]);
+
+        commit.components.push(Component::Option(OptionSpec::parametrized("-m,--message")));
+
+        for args in [&["-m", "msg", "comit"][..], &["-am", "msg", "comit"][..], &["--message", "msg", "comit"][..]] {
+            let suggestions
+                = suggest(&[&commit], args);
+
+            assert_eq!(suggestions.len(), 1);
+            assert_eq!(autocorrect(&suggestions, args), Some((2, "commit".to_string())));
+        }
+    }
+}
@@ -0,0 +1,218 @@
+/**
+ * Why a candidate state was discarded while selecting the command to run.
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase", rename_all_fields = "camelCase", tag = "type"))]
+#[cfg_attr(feature = "serde", derive(ts_rs::TS))]
+#[cfg_attr(feature = "serde", ts(export, export_to = "index.ts"))]
+pub enum DropReason {
+    // The state didn't reach the end of the command line
+    Unsuccessful,
+
+    // The command line doesn't satisfy the required options or the option
+    // groups of the command
+    InvalidOptions {
+        error: String,
+    },
+
+    // The command rejected the values it received
+    HydrationFailed {
+        error: String,
+    },
+
+    // Another state of the same command assigned the positional arguments
+    // more greedily
+    LessGreedy {
+        kept_state_id: usize,
+    },
+
+    // Other states left fewer positional arguments without values
+    UnusedPositionals {
+        unused_count: usize,
+        min_unused_count: usize,
+    },
+}
+
+impl std::fmt::Display for DropReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DropReason::Unsuccessful => write!(f, "didn't match the command line"),
+            DropReason::InvalidOptions {error} => write!(f, "invalid options ({})", error),
+            DropReason::HydrationFailed {error} => write!(f, "rejected by the command ({})", error),
+            DropReason::LessGreedy {kept_state_id} => write!(f, "less greedy than state {}", kept_state_id),
+            DropReason::UnusedPositionals {unused_count, min_unused_count} => write!(f, "leaves {} positionals unused, against {} for others", unused_count, min_unused_count),
+        }
+    }
+}
+
+/**
+ * The selection steps that can discard candidates.
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+#[cfg_attr(feature = "serde", derive(ts_rs::TS))]
+#[cfg_attr(feature = "serde", ts(export, export_to = "index.ts"))]
+pub enum PruneStep {
+    RequiredOptions,
+    UnsuccessfulNodes,
+    HydrationResults,
+    Greediness,
+    UnusedPositionals,
+}
+
+impl std::fmt::Display for PruneStep {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PruneStep::RequiredOptions => write!(f, "required options"),
+            PruneStep::UnsuccessfulNodes => write!(f, "unsuccessful nodes"),
+            PruneStep::HydrationResults => write!(f, "hydration results"),
+            PruneStep::Greediness => write!(f, "greediness"),
+            PruneStep::UnusedPositionals => write!(f, "unused positionals"),
+        }
+    }
+}
+
+/**
+ * A step of the parsing process. The runner reports the transitions it takes
+ * for each argument, then the selector reports the states it got from the
+ * runner and how it narrowed them down to a single one.
+ *
+ * Nodes refer to the compiled machine, contexts to the command ids, and
+ * states to their position in the list the selector received.
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase", rename_all_fields = "camelCase", tag = "type"))]
+#[cfg_attr(feature = "serde", derive(ts_rs::TS))]
+#[cfg_attr(feature = "serde", ts(export, export_to = "index.ts"))]
+pub enum TraceEvent {
+    StartOfInput,
+
+    Argument {
+        index: usize,
+        value: String,
+    },
+
+    EndOfInput,
+
+    Transition {
+        context_id: usize,
+        from: usize,
+        to: usize,
+        reducer: Option<String>,
+    },
+
+    // No transition accepted the argument, so the state moved to the error
+    // node
+    NoTransition {
+        context_id: usize,
+        from: usize,
+    },
+
+    // The state consumed fewer keywords than others, and was discarded
+    Trim {
+        context_id: usize,
+        node_id: usize,
+        keyword_count: usize,
+        max_keyword_count: usize,
+    },
+
+    Candidate {
+        state_id: usize,
+        context_id: usize,
+        is_successful: bool,
+    },
+
+    Drop {
+        state_id: usize,
+        context_id: usize,
+        reason: DropReason,
+    },
+
+    Prune {
+        step: PruneStep,
+        remaining: Vec<usize>,
+    },
+
+    Selected {
+        state_id: usize,
+        context_id: usize,
+    },
+}
+
+impl std::fmt::Display for TraceEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TraceEvent::StartOfInput => write!(f, "<start of input>"),
+            TraceEvent::Argument {index, value} => write!(f, "argument #{}: {:?}", index, value),
+            TraceEvent::EndOfInput => write!(f, "<end of input>"),
+
+            TraceEvent::Transition {context_id, from, to, reducer: Some(reducer)} => write!(f, "  [{}] {} -> {} ({})", context_id, from, to, reducer),
+            TraceEvent::Transition {context_id, from, to, reducer: None} => write!(f, "  [{}] {} -> {}", context_id, from, to),
+            TraceEvent::NoTransition {context_id, from} => write!(f, "  [{}] {} -> error", context_id, from),
+
+            TraceEvent::Trim {context_id, node_id, keyword_count, max_keyword_count} => write!(f, "  [{}] {} trimmed ({} keywords, against {})", context_id, node_id, keyword_count, max_keyword_count),
+
+            TraceEvent::Candidate {state_id, context_id, is_successful: true} => write!(f, "state {} [{}]: successful", state_id, context_id),
+            TraceEvent::Candidate {state_id, context_id, is_successful: false} => write!(f, "state {} [{}]: failed", state_id, context_id),
+
+            TraceEvent::Drop {state_id, context_id, reason} => write!(f, "  state {} [{}] dropped: {}", state_id, context_id, reason),
+            TraceEvent::Prune {step, remaining} => write!(f, "after pruning by {}: {:?}", step, remaining),
+            TraceEvent::Selected {state_id, context_id} => write!(f, "selected state {} [{}]", state_id, context_id),
+        }
+    }
+}
+
+/**
+ * Receive the steps of the parsing process; see `CliBuilder::run_with_trace`
+ * and `Selector::resolve_state_with_trace`.
+ */
+pub trait TraceSink {
+    fn record(&mut self, event: TraceEvent);
+}
+
+impl TraceSink for Vec<TraceEvent> {
+    fn record(&mut self, event: TraceEvent) {
+        self.push(event);
+    }
+}
+
+/**
+ * Print the events to stderr as they come, one per line.
+ */
+#[derive(Debug, Default)]
+pub struct StderrTraceSink;
+
+impl TraceSink for StderrTraceSink {
+    fn record(&mut self, event: TraceEvent) {
+        eprintln!("{}", event);
+    }
+}
+
+/**
+ * Forward events to a sink if there's one; events are only built when they
+ * have somewhere to go, so an untraced run doesn't pay for them.
+ */
+pub(crate) struct Tracer<'a> {
+    sink: Option<&'a mut dyn TraceSink>,
+}
+
+impl<'a> Tracer<'a> {
+    pub fn new(sink: Option<&'a mut dyn TraceSink>) -> Self {
+        Self {
+            sink,
+        }
+    }
+
+    pub fn disabled() -> Self {
+        Self::new(None)
+    }
+
+    pub fn record<F: FnOnce() -> TraceEvent>(&mut self, f: F) {
+        if let Some(sink) = &mut self.sink {
+            sink.record(f());
+        }
+    }
+}
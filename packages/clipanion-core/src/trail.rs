@@ -0,0 +1,422 @@
+use std::sync::Arc;
+
+use crate::shared::UserArg;
+
+// Items are stored in chunks so that dropping a long trail doesn't recurse
+// once per item; a chunk is only appended to in place when nobody else
+// references it, and otherwise copied
+const CHUNK_SIZE: usize = 32;
+
+struct TrailChunk<T> {
+    items: Vec<T>,
+    parent: Option<Arc<TrailChunk<T>>>,
+}
+
+/**
+ * An append-only list whose clones share their common prefix. The runner
+ * clones its state on every transition, so storing the parsed values in a
+ * `Vec` would make long command lines quadratic; cloning a trail is O(1), and
+ * pushing to it is bounded by the chunk size.
+ */
+pub struct Trail<T> {
+    head: Option<Arc<TrailChunk<T>>>,
+    len: usize,
+}
+
+impl<T> Trail<T> {
+    pub fn new() -> Self {
+        Self {
+            head: None,
+            len: 0,
+        }
+    }
+
+    pub fn last(&self) -> Option<&T> {
+        self.head.as_ref().and_then(|chunk| chunk.items.last())
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /**
+     * Iterate over the items from the most recent to the oldest; unlike
+     * `iter`, this doesn't need to allocate.
+     */
+    pub fn iter_rev(&self) -> impl Iterator<Item = &T> {
+        std::iter::successors(self.head.as_deref(), |chunk| chunk.parent.as_deref())
+            .flat_map(|chunk| chunk.items.iter().rev())
+    }
+
+    /**
+     * Iterate over the items from the oldest to the most recent; only the
+     * chunks need to be collected, not the items themselves.
+     */
+    pub fn iter(&self) -> TrailIter<'_, T> {
+        let mut chunks = std::iter::successors(self.head.as_deref(), |chunk| chunk.parent.as_deref())
+            .map(|chunk| chunk.items.as_slice())
+            .collect::<Vec<_>>();
+
+        chunks.reverse();
+
+        TrailIter {
+            items: chunks.into_iter().flatten(),
+            len: self.len,
+        }
+    }
+}
+
+pub struct TrailIter<'a, T> {
+    items: std::iter::Flatten<std::vec::IntoIter<&'a [T]>>,
+    len: usize,
+}
+
+impl<'a, T> Iterator for TrailIter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let item
+            = self.items.next()?;
+
+        self.len -= 1;
+        Some(item)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<'a, T> DoubleEndedIterator for TrailIter<'a, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let item
+            = self.items.next_back()?;
+
+        self.len -= 1;
+        Some(item)
+    }
+}
+
+impl<'a, T> ExactSizeIterator for TrailIter<'a, T> {}
+
+impl<T: Clone> Trail<T> {
+    pub fn push(&mut self, value: T) {
+        self.len += 1;
+
+        if let Some(head) = &mut self.head {
+            if head.items.len() < CHUNK_SIZE {
+                if let Some(chunk) = Arc::get_mut(head) {
+                    chunk.items.push(value);
+                    return;
+                }
+
+                let mut items
+                    = Vec::with_capacity(CHUNK_SIZE);
+
+                items.extend(head.items.iter().cloned());
+                items.push(value);
+
+                *head = Arc::new(TrailChunk {
+                    items,
+                    parent: head.parent.clone(),
+                });
+
+                return;
+            }
+        }
+
+        let mut items
+            = Vec::with_capacity(CHUNK_SIZE);
+
+        items.push(value);
+
+        self.head = Some(Arc::new(TrailChunk {
+            items,
+            parent: self.head.take(),
+        }));
+    }
+
+    pub fn to_vec(&self) -> Vec<T> {
+        self.iter().cloned().collect()
+    }
+}
+
+impl<T> Clone for Trail<T> {
+    fn clone(&self) -> Self {
+        Self {
+            head: self.head.clone(),
+            len: self.len,
+        }
+    }
+}
+
+impl<T> Default for Trail<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: std::fmt::Debug> std::fmt::Debug for Trail<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<T: PartialEq> PartialEq for Trail<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len && self.iter_rev().eq(other.iter_rev())
+    }
+}
+
+impl<T: Eq> Eq for Trail<T> {}
+
+impl<T: PartialEq> PartialEq<Vec<T>> for Trail<T> {
+    fn eq(&self, other: &Vec<T>) -> bool {
+        self.len == other.len() && self.iter_rev().eq(other.iter().rev())
+    }
+}
+
+impl<T: Clone> FromIterator<T> for Trail<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut trail
+            = Self::new();
+
+        for value in iter {
+            trail.push(value);
+        }
+
+        trail
+    }
+}
+
+#[derive(Clone, PartialEq, Eq)]
+enum ValueRecord<'args> {
+    Start(usize),
+    Value(UserArg<'args>),
+}
+
+/**
+ * The values assigned to the components of a command, grouped by occurrence:
+ * each entry is a component id along with the arguments it received that
+ * time. Entries are only ever appended to, and values are only ever added to
+ * the last entry, which lets us store them in a `Trail`.
+ */
+#[derive(Clone, Default)]
+pub struct ValueList<'args> {
+    records: Trail<ValueRecord<'args>>,
+    last_id: Option<usize>,
+    len: usize,
+
+    // Each component id that got an entry, once; lets `contains` skip the
+    // values themselves
+    component_ids: Trail<usize>,
+}
+
+impl<'args> ValueList<'args> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, (component_id, values): (usize, Vec<UserArg<'args>>)) {
+        self.records.push(ValueRecord::Start(component_id));
+
+        for value in values {
+            self.records.push(ValueRecord::Value(value));
+        }
+
+        if !self.contains(component_id) {
+            self.component_ids.push(component_id);
+        }
+
+        self.last_id = Some(component_id);
+        self.len += 1;
+    }
+
+    /**
+     * Add a value to the last entry, and return its component id; does
+     * nothing if there's no entry yet.
+     */
+    pub fn push_value(&mut self, value: UserArg<'args>) -> Option<usize> {
+        if self.last_id.is_some() {
+            self.records.push(ValueRecord::Value(value));
+        }
+
+        self.last_id
+    }
+
+    pub fn last_id(&self) -> Option<usize> {
+        self.last_id
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn contains(&self, component_id: usize) -> bool {
+        self.component_ids.iter_rev().any(|other_id| *other_id == component_id)
+    }
+
+    pub fn to_vec(&self) -> Vec<(usize, Vec<UserArg<'args>>)> {
+        self.iter().collect()
+    }
+
+    pub fn iter(&self) -> ValueListIter<'_, 'args> {
+        ValueListIter {
+            records: self.records.iter().peekable(),
+        }
+    }
+}
+
+/**
+ * Iterate over the entries of a `ValueList` without copying them upfront;
+ * only the values of the entry being yielded are collected.
+ */
+pub struct ValueListIter<'a, 'args> {
+    records: std::iter::Peekable<TrailIter<'a, ValueRecord<'args>>>,
+}
+
+impl<'a, 'args> Iterator for ValueListIter<'a, 'args> {
+    type Item = (usize, Vec<UserArg<'args>>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let component_id = self.records.by_ref().find_map(|record| match record {
+            ValueRecord::Start(component_id) => Some(*component_id),
+            ValueRecord::Value(_) => None,
+        })?;
+
+        let mut values
+            = vec![];
+
+        while let Some(ValueRecord::Value(value)) = self.records.next_if(|record| matches!(record, ValueRecord::Value(_))) {
+            values.push(*value);
+        }
+
+        Some((component_id, values))
+    }
+}
+
+impl<'args> std::fmt::Debug for ValueList<'args> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<'args> PartialEq for ValueList<'args> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len && self.records == other.records
+    }
+}
+
+impl<'args> Eq for ValueList<'args> {}
+
+impl<'args> PartialEq<Vec<(usize, Vec<UserArg<'args>>)>> for ValueList<'args> {
+    fn eq(&self, other: &Vec<(usize, Vec<UserArg<'args>>)>) -> bool {
+        self.len == other.len() && self.to_vec() == *other
+    }
+}
+
+impl<'args> FromIterator<(usize, Vec<UserArg<'args>>)> for ValueList<'args> {
+    fn from_iter<I: IntoIterator<Item = (usize, Vec<UserArg<'args>>)>>(iter: I) -> Self {
+        let mut list
+            = Self::new();
+
+        for entry in iter {
+            list.push(entry);
+        }
+
+        list
+    }
+}
+
+impl<'a, 'args> IntoIterator for &'a ValueList<'args> {
+    type Item = (usize, Vec<UserArg<'args>>);
+    type IntoIter = ValueListIter<'a, 'args>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn arg(value: &str, index: usize) -> UserArg<'_> {
+        UserArg {value, index}
+    }
+
+    #[test]
+    fn it_keeps_clones_independent() {
+        let mut a: Trail<usize>
+            = (0..100).collect();
+
+        let mut b
+            = a.clone();
+
+        a.push(100);
+        b.push(200);
+        b.push(201);
+
+        assert_eq!(a, (0..101).collect::<Vec<_>>());
+        assert_eq!(b, (0..100).chain([200, 201]).collect::<Vec<_>>());
+        assert_eq!(b.last(), Some(&201));
+    }
+
+    #[test]
+    fn it_iterates_across_chunks() {
+        let trail: Trail<usize>
+            = (0..100).collect();
+
+        assert!(trail.iter().copied().eq(0..100));
+        assert!(trail.iter().rev().copied().eq((0..100).rev()));
+        assert_eq!(trail.iter().len(), 100);
+    }
+
+    #[test]
+    fn it_drops_long_trails() {
+        let trail: Trail<usize>
+            = (0..200_000).collect();
+
+        let clone
+            = trail.clone();
+
+        drop(trail);
+        drop(clone);
+    }
+
+    #[test]
+    fn it_groups_values_by_entry() {
+        let mut values
+            = ValueList::new();
+
+        assert_eq!(values.push_value(arg("ignored", 0)), None);
+
+        values.push((2, vec![]));
+        values.push((0, vec![arg("foo", 1)]));
+
+        let mut fork
+            = values.clone();
+
+        assert_eq!(fork.push_value(arg("bar", 2)), Some(0));
+
+        assert_eq!(values, vec![(2, vec![]), (0, vec![arg("foo", 1)])]);
+        assert_eq!(fork, vec![(2, vec![]), (0, vec![arg("foo", 1), arg("bar", 2)])]);
+        assert_eq!(fork.len(), 2);
+        assert!(fork.contains(2));
+        assert!(!fork.contains(1));
+
+        fork.push((1, vec![arg("baz", 3)]));
+
+        assert!(fork.contains(1));
+        assert!(!values.contains(1));
+    }
+}
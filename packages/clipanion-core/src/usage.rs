@@ -1,40 +1,152 @@
 use colored::Colorize;
 
-use crate::{CommandSpec, Info};
+use crate::{CommandSpec, Component, Constraint, Info, Lifecycle, PositionalSpec};
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct CommandUsageOptions {
     pub detailed: bool,
     pub inline_options: bool,
 }
 
+impl Default for CommandUsageOptions {
+    fn default() -> Self {
+        Self {
+            detailed: false,
+            inline_options: true,
+        }
+    }
+}
+
 pub struct CommandUsageResult {
     pub command_spec: CommandSpec,
+    pub usage_line: String,
+    pub alias_lines: Vec<String>,
+    pub positionals: Vec<PositionalUsage>,
+    pub options: Vec<OptionUsage>,
+}
+
+fn format_usage_line(command_spec: &CommandSpec, inline_options: bool) -> String {
+    if inline_options {
+        return command_spec.to_string();
+    }
+
+    let has_visible_options = command_spec.components.iter()
+        .filter_map(|component| component.is_option())
+        .any(|option| !option.is_hidden);
+
+    let (prefix_components, suffix_components): (Vec<_>, Vec<_>)
+        = command_spec.components.iter()
+            .filter(|component| matches!(component, Component::Positional(_)))
+            .partition(|component| matches!(component, Component::Positional(PositionalSpec::Dynamic {is_prefix: true, ..})));
+
+    let passthrough_components = command_spec.components.iter()
+        .filter(|component| matches!(component, Component::Passthrough(_)));
+
+    prefix_components.into_iter()
+        .map(|component| component.to_string())
+        .chain(command_spec.primary_path.iter().cloned())
+        .chain(has_visible_options.then(|| "[options]".to_string()))
+        .chain(passthrough_components.map(|component| component.to_string()))
+        .chain(suffix_components.into_iter().map(|component| component.to_string()))
+        .collect::<Vec<_>>()
+        .join(" ")
 }
 
 impl CommandUsageResult {
     pub fn new(command_spec: CommandSpec) -> Self {
+        Self::with_options(command_spec, CommandUsageOptions::default())
+    }
+
+    pub fn with_options(command_spec: CommandSpec, opts: CommandUsageOptions) -> Self {
+        let usage_line
+            = format_usage_line(&command_spec, opts.inline_options);
+
+        let mut alias_lines
+            = vec![];
+        let mut positionals
+            = vec![];
+        let mut options
+            = vec![];
+
+        if opts.detailed {
+            for alias in &command_spec.aliases {
+                let alias_spec = CommandSpec {
+                    primary_path: alias.clone(),
+                    aliases: vec![],
+                    ..command_spec.clone()
+                };
+
+                alias_lines.push(format_usage_line(&alias_spec, opts.inline_options));
+            }
+
+            for component in &command_spec.components {
+                match component {
+                    Component::Positional(spec @ PositionalSpec::Dynamic {name, documentation, constraints, min_len, ..}) => {
+                        positionals.push(PositionalUsage {
+                            name: name.clone(),
+                            definition: spec.to_string(),
+                            description: documentation.as_ref().map(|documentation| documentation.description.clone()).unwrap_or_default(),
+                            constraints: constraints.clone(),
+                            required: *min_len > 0,
+                        });
+                    },
+
+                    Component::Option(option) if !option.is_hidden => {
+                        options.push(OptionUsage {
+                            preferred_name: option.primary_name.clone(),
+                            name_set: option.all_names().map(|name| name.to_string()).collect(),
+                            // The table marks required options on its own
+                            definition: option.definition(),
+                            description: option.documentation.as_ref().map(|documentation| documentation.description.clone()).unwrap_or_default(),
+                            default_value: option.default_value.clone(),
+                            env: option.env.clone(),
+                            constraints: option.constraints.clone(),
+                            lifecycle: option.lifecycle.clone(),
+                            required: option.is_required,
+                        });
+                    },
+
+                    _ => {},
+                }
+            }
+        }
+
         Self {
             command_spec,
+            usage_line,
+            alias_lines,
+            positionals,
+            options,
         }
     }
 
     pub fn oneliner(&self, info: &Info) -> String {
         let usage_line
-            = format!("› {} {}", info.binary_name, self.command_spec);
+            = format!("› {} {}", info.binary_name, self.usage_line);
 
-        let usage_line = match info.colorized {
+        match info.colorized {
             true => usage_line.bright_white().to_string(),
             false => usage_line.to_string(),
-        };
-
-        usage_line
+        }
     }
 }
 
+pub struct PositionalUsage {
+    pub name: String,
+    pub definition: String,
+    pub description: String,
+    pub constraints: Vec<Constraint>,
+    pub required: bool,
+}
+
 pub struct OptionUsage {
     pub preferred_name: String,
     pub name_set: Vec<String>,
     pub definition: String,
     pub description: String,
+    pub default_value: Option<String>,
+    pub env: Option<String>,
+    pub constraints: Vec<Constraint>,
+    pub lifecycle: Lifecycle,
     pub required: bool,
 }
@@ -186,7 +186,8 @@ impl GitConfigSetCommand {
     }
 }
 
-#[cli::program(async)]
+#[cli::program(async, completion)]
+#[allow(clippy::enum_variant_names)]
 enum MyCli {
     GitAdd(GitAddCommand),
     GitBranch(GitBranchCommand),
@@ -73,3 +73,14 @@ pub fn cli_provider(args: TokenStream, input: TokenStream) -> TokenStream {
         Err(err) => err.to_compile_error().into(),
     }
 }
+
+#[proc_macro_derive(ValueEnum, attributes(value))]
+pub fn value_enum(input: TokenStream) -> TokenStream {
+    let input
+        = parse_macro_input!(input as DeriveInput);
+
+    match macros::value_enum::value_enum_macro(input) {
+        Ok(token_stream) => token_stream,
+        Err(err) => err.to_compile_error().into(),
+    }
+}
@@ -35,11 +35,15 @@ pub fn cli_enum_macro(_args: TokenStream, mut input: DeriveInput) -> Result<Toke
             panic!("Only one field is supported");
         }
 
+        let variant_field
+            = fields.unnamed.first().unwrap();
         let variant_ty
-            = &fields.unnamed.first().unwrap().ty;
+            = &variant_field.ty;
 
-        let partial_ty: syn::Type
-            = syn::parse_quote!{<#variant_ty as clipanion::details::CommandController>::Partial};
+        let partial_ty: syn::Type = match shared::get_mount_path(variant_field)? {
+            Some(_) => syn::parse_quote!{<#variant_ty as clipanion::details::CliEnums>::PartialEnum},
+            None => syn::parse_quote!{<#variant_ty as clipanion::details::CommandController>::Partial},
+        };
     
         {
             let mut partial_fields
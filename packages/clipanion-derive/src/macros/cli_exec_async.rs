@@ -2,7 +2,9 @@ extern crate proc_macro;
 
 use proc_macro::TokenStream;
 use quote::quote;
-use syn::DeriveInput;
+use syn::{DeriveInput, Fields};
+
+use crate::shared;
 
 pub fn cli_exec_async_macro(_args: TokenStream, mut input: DeriveInput) -> Result<TokenStream, syn::Error> {
     let syn::Data::Enum(enum_input) = &mut input.data else {
@@ -19,6 +21,19 @@ pub fn cli_exec_async_macro(_args: TokenStream, mut input: DeriveInput) -> Resul
         let variant_ident
             = &variant.ident;
 
+        let is_mounted = match &variant.fields {
+            Fields::Unnamed(fields) => fields.unnamed.first().map(shared::get_mount_path).transpose()?.flatten().is_some(),
+            _ => false,
+        };
+
+        if is_mounted {
+            match_arms.push(quote! {
+                Self::#variant_ident(program) => ::clipanion::details::CommandExecutorAsync::execute(program, env).await,
+            });
+
+            continue;
+        }
+
         match_arms.push(quote! {
             Self::#variant_ident(command) => command.execute().await.into(),
         });
@@ -2,9 +2,41 @@ extern crate proc_macro;
 
 use proc_macro::TokenStream;
 use quote::quote;
-use syn::{DeriveInput, Fields};
+use syn::{DeriveInput, Expr, ExprLit, Fields, Lit};
+
+use crate::{shared::{self, expect_lit}, utils::AttributeBag};
+
+pub fn cli_provider_macro(args: TokenStream, mut input: DeriveInput) -> Result<TokenStream, syn::Error> {
+    let mut provider_attribute_bag
+        = syn::parse::<AttributeBag>(args)?;
+
+    let negative_numbers = provider_attribute_bag.take("negative_numbers")
+        .map(expect_lit!(Lit::Bool))
+        .transpose()?
+        .map(|lit| lit.value)
+        .unwrap_or(false);
+
+    let determinize = provider_attribute_bag.take("determinize")
+        .map(expect_lit!(Lit::Bool))
+        .transpose()?
+        .map(|lit| lit.value)
+        .unwrap_or(false);
+
+    let lazy = provider_attribute_bag.take("lazy")
+        .map(expect_lit!(Lit::Bool))
+        .transpose()?
+        .map(|lit| lit.value)
+        .unwrap_or(false);
+
+    provider_attribute_bag.expect_empty()?;
+
+    // The clones returned by `build_cli` share the compiled machine; lazy
+    // programs instead only compile the commands each command line can reach
+    let precompile = match lazy {
+        true => quote! {},
+        false => quote! {builder.precompile();},
+    };
 
-pub fn cli_provider_macro(_args: TokenStream, mut input: DeriveInput) -> Result<TokenStream, syn::Error> {
     let syn::Data::Enum(enum_input) = &mut input.data else {
         panic!("Only enums are supported");
     };
@@ -12,11 +44,15 @@ pub fn cli_provider_macro(_args: TokenStream, mut input: DeriveInput) -> Result<
     let enum_ident
         = &input.ident;
 
-    let mut variant_tys
+    let mut command_counts
+        = vec![];
+    let mut command_registrations
+        = vec![];
+    let mut hydraters
         = vec![];
 
-    for variant in &enum_input.variants {
-        let Fields::Unnamed(fields) = &variant.fields else {
+    for variant in &mut enum_input.variants {
+        let Fields::Unnamed(fields) = &mut variant.fields else {
             panic!("Only unnamed fields are supported");
         };
 
@@ -24,10 +60,66 @@ pub fn cli_provider_macro(_args: TokenStream, mut input: DeriveInput) -> Result<
             panic!("Only one field is supported");
         }
 
+        let variant_field
+            = fields.unnamed.first_mut().unwrap();
+
+        let mount_path
+            = shared::get_mount_path(variant_field)?;
+
+        variant_field.attrs.retain(|attr| !shared::is_mount_attribute(attr));
+
         let variant_ty
-            = &fields.unnamed.first().unwrap().ty;
+            = &variant_field.ty;
+
+        let Some(mount_path) = mount_path else {
+            command_counts.push(quote! {1});
+
+            command_registrations.push(quote! {
+                commands.push(<#variant_ty as clipanion::details::CommandController>::command_spec()?);
+            });
+
+            hydraters.push(quote! {
+                |_, environment, state| {
+                    use clipanion::details::CommandController;
+
+                    let partial
+                        = <#variant_ty>::hydrate_from_state(environment, state)?;
+
+                    Ok(partial.into())
+                }
+            });
+
+            continue;
+        };
+
+        command_counts.push(quote! {
+            <#variant_ty as clipanion::details::CommandProvider>::COMMAND_COUNT
+        });
+
+        command_registrations.push(quote! {
+            {
+                use std::ops::Deref;
+                use std::sync::LazyLock;
+
+                static MOUNTED_COMMANDS: LazyLock<Result<Vec<clipanion::core::CommandSpec>, clipanion::core::BuildError>> = LazyLock::new(|| {
+                    let commands
+                        = <#variant_ty as clipanion::details::CommandProvider>::registered_commands()?;
+
+                    Ok(clipanion::details::mount_commands(&[#(#mount_path),*], commands))
+                });
+
+                commands.extend(MOUNTED_COMMANDS.deref().as_ref().map_err(|e| e.clone())?);
+            }
+        });
+
+        hydraters.push(quote! {
+            |command_index, environment, state| {
+                let partial
+                    = <#variant_ty as clipanion::details::CommandProvider>::hydrate_command(command_index, environment, state)?;
 
-        variant_tys.push(variant_ty.clone());
+                Ok(partial.into())
+            }
+        });
     }
     
     Ok(TokenStream::from(quote! {
@@ -36,63 +128,76 @@ pub fn cli_provider_macro(_args: TokenStream, mut input: DeriveInput) -> Result<
         impl clipanion::details::CommandProvider for #enum_ident {
             type Command = #enum_ident;
 
-            fn command_usage(command_index: usize, opts: clipanion::core::CommandUsageOptions) -> Result<clipanion::core::CommandUsageResult, clipanion::core::BuildError> {
-                use clipanion::details::CommandController;
-
-                const FNS: &[fn(clipanion::core::CommandUsageOptions) -> Result<clipanion::core::CommandUsageResult, clipanion::core::BuildError>] = &[
-                    #(<#variant_tys>::command_usage,)*
-                ];
+            const COMMAND_COUNT: usize = 0 #(+ #command_counts)*;
 
-                FNS[command_index](opts)
+            fn command_usage(command_index: usize, opts: clipanion::core::CommandUsageOptions) -> Result<clipanion::core::CommandUsageResult, clipanion::core::BuildError> {
+                Ok(Self::registered_commands()?[command_index].usage_with(opts))
             }
 
             fn registered_commands() -> Result<Vec<&'static clipanion::core::CommandSpec>, clipanion::core::BuildError> {
-                use clipanion::details::CommandController;
+                let mut commands: Vec<&'static clipanion::core::CommandSpec>
+                    = vec![];
+
+                #(#command_registrations)*
 
-                Ok(vec![
-                    #(<#variant_tys>::command_spec()?,)*
-                ])
+                Ok(commands)
             }
 
-            fn parse_args<'args>(builder: &clipanion::core::CliBuilder<'static>, environment: &'args clipanion::advanced::Environment) -> Result<clipanion::core::SelectionResult<'static, 'args, <#enum_ident as clipanion::details::CliEnums>::PartialEnum>, clipanion::core::Error<'args>> where #enum_ident: clipanion::details::CliEnums {
-                let argv
-                    = environment.argv.iter()
-                        .map(|s| s.as_str())
-                        .collect::<Vec<_>>();
+            fn hydrate_command(command_index: usize, environment: &clipanion::advanced::Environment, state: &clipanion::core::State) -> Result<<#enum_ident as clipanion::details::CliEnums>::PartialEnum, clipanion::core::CommandError> {
+                const COUNTS: &[usize] = &[
+                    #(#command_counts,)*
+                ];
 
-                let mut selector
-                    = builder.run(&argv)?;
+                const FNS: &[fn(usize, &clipanion::advanced::Environment, &clipanion::core::State<'_>) -> Result<<#enum_ident as ::clipanion::details::CliEnums>::PartialEnum, clipanion::core::CommandError>] = &[
+                    #(#hydraters,)*
+                ];
 
-                const FNS: &[fn(&clipanion::advanced::Environment, &clipanion::core::State<'_>) -> Result<<#enum_ident as ::clipanion::details::CliEnums>::PartialEnum, clipanion::core::CommandError>] = &[
-                    #(|environment, state| {
-                        use clipanion::details::CommandController;
+                let mut offset
+                    = 0;
 
-                        let partial
-                            = <#variant_tys>::hydrate_from_state(environment, state)?;
+                for (count, hydrate) in COUNTS.iter().zip(FNS) {
+                    if command_index < offset + count {
+                        return hydrate(command_index - offset, environment, state);
+                    }
 
-                        Ok(partial.into())
-                    },)*
-                ];
+                    offset += count;
+                }
 
-                selector.resolve_state(|state| {
-                    let command
-                        = FNS[state.context_id](environment, state)?;
+                unreachable!("command index {} is out of bounds", command_index)
+            }
 
-                    Ok(command.into())
+            fn parse_args<'args>(builder: &clipanion::core::CliBuilder<'static>, environment: &'args clipanion::advanced::Environment) -> Result<clipanion::core::SelectionResult<'static, 'args, <#enum_ident as clipanion::details::CliEnums>::PartialEnum>, clipanion::core::Error<'args>> where #enum_ident: clipanion::details::CliEnums {
+                clipanion::details::parse_args_with(builder, environment, |state| {
+                    Self::hydrate_command(state.context_id, environment, state)
                 })
             }
 
             fn build_cli() -> Result<clipanion::core::CliBuilder<'static>, clipanion::core::BuildError> {
-                use clipanion::details::CommandController;
+                use std::ops::Deref;
+                use std::sync::LazyLock;
+
+                static CLI_BUILDER: LazyLock<Result<clipanion::core::CliBuilder<'static>, clipanion::core::BuildError>> = LazyLock::new(|| {
+                    let mut builder
+                        = clipanion::core::CliBuilder::new();
+
+                    for command_spec in <#enum_ident as clipanion::details::CommandProvider>::registered_commands()? {
+                        builder.add_command(command_spec);
+                    }
+
+                    builder.set_negative_numbers(#negative_numbers);
+                    builder.set_determinize(#determinize);
+
+                    #precompile
 
-                let mut builder
-                    = clipanion::core::CliBuilder::new();
+                    Ok(builder)
+                });
 
-                #(builder.add_command(<#variant_tys>::command_spec()?);)*
+                let builder
+                    = CLI_BUILDER.deref().clone()?;
 
                 if std::env::var("CLIPANION_DEBUG").is_ok() {
-                    println!("========== CLI State Machine ==========");
-                    println!("{:?}", builder.compile());
+                    eprintln!("========== CLI State Machine ==========");
+                    eprintln!("{:?}", builder.machine());
                 }
 
                 Ok(builder)
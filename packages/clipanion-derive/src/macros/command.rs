@@ -3,7 +3,7 @@ use proc_macro2::Span;
 use quote::quote;
 use syn::{Attribute, DeriveInput, Expr, ExprLit, Fields, Ident, Lit, LitStr, Meta, Path};
 
-use crate::{shared::expect_lit, utils::{to_lit_str, AttributeBag, CliAttributes, OptionBag}};
+use crate::{shared::expect_lit, utils::{expect_lit_strs, take_lifecycle, to_lit_str, AttributeBag, CliAttributes, Constraints, OptionBag}};
 
 fn parse_documentation(value: String) -> proc_macro2::TokenStream {
     let lines
@@ -89,6 +89,9 @@ pub fn command_macro(args: TokenStream, mut input: DeriveInput) -> Result<TokenS
         .map(|lit| lit.value)
         .unwrap_or(false);
 
+    let command_lifecycle
+        = take_lifecycle(&mut command_attribute_bag)?;
+
     let explicit_positionals = command_attribute_bag.take("explicit_positionals")
         .map(expect_lit!(Lit::Bool))
         .transpose()?
@@ -98,6 +101,28 @@ pub fn command_macro(args: TokenStream, mut input: DeriveInput) -> Result<TokenS
     let mut paths_lits
         = command_cli_attributes.take_paths()?;
 
+    let examples
+        = command_cli_attributes.take_examples()?;
+
+    let is_hidden
+        = command_cli_attributes.attributes.remove("hidden").is_some();
+
+    if is_hidden {
+        builder.push(quote! {
+            command_spec.is_hidden = true;
+        });
+    }
+
+    let mut option_groups = vec![];
+
+    for options in command_cli_attributes.take_lit_lists("exclusive")? {
+        option_groups.push(("Exclusive", None, options));
+    }
+
+    for options in command_cli_attributes.take_lit_lists("at_least_one")? {
+        option_groups.push(("AtLeastOne", None, options));
+    }
+
     command_attribute_bag.expect_empty()?;
 
     if is_default {
@@ -128,6 +153,21 @@ pub fn command_macro(args: TokenStream, mut input: DeriveInput) -> Result<TokenS
         });
     }
 
+    for (command, description) in examples {
+        builder.push(quote! {
+            command_spec.examples.push(clipanion::core::Example {
+                command: #command.to_string(),
+                description: #description.to_string(),
+            });
+        });
+    }
+
+    let mut option_names: Vec<String>
+        = vec![];
+
+    let mut has_passthrough
+        = false;
+
     let mut partial_struct_members
         = vec![];
     let mut partial_struct_default_initializers
@@ -180,10 +220,15 @@ pub fn command_macro(args: TokenStream, mut input: DeriveInput) -> Result<TokenS
             }
         }
 
+        let choices = quote! {{
+            use clipanion::details::{NoChoices as _, ValueEnumChoices as _};
+            (&clipanion::details::ChoicesProbe::<#internal_field_type>::new()).choices()
+        }};
+
         let mut cli_attributes
             = CliAttributes::extract(&mut field.attrs)?;
 
-        if !explicit_positionals && !cli_attributes.attributes.contains_key("option") && !cli_attributes.attributes.contains_key("positional") {
+        if !explicit_positionals && !cli_attributes.attributes.contains_key("option") && !cli_attributes.attributes.contains_key("positional") && !cli_attributes.attributes.contains_key("passthrough") {
             cli_attributes.attributes.insert("positional".to_string(), vec![Attribute {
                 pound_token: Default::default(),
                 style: syn::AttrStyle::Outer,
@@ -192,7 +237,53 @@ pub fn command_macro(args: TokenStream, mut input: DeriveInput) -> Result<TokenS
             }]);
         }
 
-        if let Some(mut option_bag) = cli_attributes.take_unique::<OptionBag>("option")? {
+        if let Some(mut passthrough_bag) = cli_attributes.take_unique::<AttributeBag>("passthrough")? {
+            if has_passthrough {
+                return Err(syn::Error::new_spanned(field_ident, "Commands can only define a single passthrough field"));
+            }
+
+            if !is_vec_type {
+                return Err(syn::Error::new_spanned(field_type, "Passthrough fields must be of type Vec<String>"));
+            }
+
+            has_passthrough = true;
+
+            let documentation = passthrough_bag.take("description")
+                .map(expect_lit!(Lit::Str))
+                .transpose()?
+                .map(|lit_str| lit_str.value())
+                .or_else(|| cli_attributes.documentation.clone())
+                .map_or(quote! {None}, parse_documentation);
+
+            let field_name_upper = field.ident.as_ref().unwrap()
+                .to_string()
+                .to_uppercase();
+
+            partial_struct_members.push(quote! {
+                pub #field_ident: Vec<String>,
+            });
+
+            partial_struct_default_initializers.push(quote! {
+                #field_ident: std::default::Default::default(),
+            });
+
+            hydraters.push(quote! {
+                partial.#field_ident.extend(args.iter().map(|arg| arg.value.to_string()));
+            });
+
+            initialization_members.push(quote! {
+                #field_ident: partial.#field_ident,
+            });
+
+            builder.push(quote! {
+                command_spec.components.push(clipanion::core::Component::Passthrough(clipanion::core::PassthroughSpec {
+                    name: #field_name_upper.to_string(),
+                    documentation: #documentation,
+                }));
+            });
+
+            passthrough_bag.expect_empty()?;
+        } else if let Some(mut option_bag) = cli_attributes.take_unique::<OptionBag>("option")? {
             let mut is_bool = false;
             let mut is_tuple = false;
             let mut is_counter = false;
@@ -209,7 +300,7 @@ pub fn command_macro(args: TokenStream, mut input: DeriveInput) -> Result<TokenS
                     extra_len = Some(0);
                 }
 
-                if let Some(_) = option_bag.attributes.take("counter") {
+                if option_bag.attributes.take("counter").is_some() {
                     is_counter = true;
 
                     min_len = 0;
@@ -260,6 +351,29 @@ pub fn command_macro(args: TokenStream, mut input: DeriveInput) -> Result<TokenS
                 .map(to_lit_str)
                 .collect::<Vec<_>>();
 
+            let is_hidden = option_bag.attributes.take("hidden")
+                .map(expect_lit!(Lit::Bool))
+                .transpose()?
+                .is_some_and(|lit| lit.value);
+
+            let allow_hyphen_values = option_bag.attributes.take("allow_hyphen_values")
+                .map(expect_lit!(Lit::Bool))
+                .transpose()?
+                .is_some_and(|lit| lit.value);
+
+            let lifecycle
+                = take_lifecycle(&mut option_bag.attributes)?;
+
+            let constraints
+                = Constraints::take(&mut option_bag.attributes, preferred_name)?;
+
+            if !constraints.is_empty() && (is_bool || is_counter || is_tuple) {
+                return Err(syn::Error::new_spanned(field_ident, "Constraints are only supported on options accepting a single value"));
+            }
+
+            let constraints_spec
+                = constraints.spec();
+
             let min_len_lit = quote! {#min_len};
             let extra_len_lit = match extra_len {
                 Some(extra_len) => quote! {Some(#extra_len)},
@@ -284,8 +398,11 @@ pub fn command_macro(args: TokenStream, mut input: DeriveInput) -> Result<TokenS
                         }).collect::<Result<Vec<_>, _>>()?
                     }
                 } else {
+                    let parse_value
+                        = constraints.parse_value(quote! {s.value}, internal_field_type);
+
                     quote! {args.iter().map(|s| -> Result<_, clipanion::core::CommandError> {
-                        s.value.parse().map_err(clipanion::details::handle_parse_error)
+                        #parse_value
                     }).collect::<Result<Vec<_>, _>>()?}
                 }
             } else if is_bool {
@@ -306,8 +423,11 @@ pub fn command_macro(args: TokenStream, mut input: DeriveInput) -> Result<TokenS
 
                 quote! {Some((#(#tuple_fields),*))}
             } else {
+                let parse_value
+                    = constraints.parse_value(quote! {s.value}, internal_field_type);
+
                 quote! {args.first().map(|s| -> Result<_, clipanion::core::CommandError> {
-                    s.value.parse().map_err(clipanion::details::handle_parse_error)
+                    #parse_value
                 }).transpose()?}
             };
 
@@ -342,6 +462,11 @@ pub fn command_macro(args: TokenStream, mut input: DeriveInput) -> Result<TokenS
             let is_required
                 = default_value.is_none();
 
+            let env_lit = option_bag.attributes.take("env")
+                .map(expect_lit!(Lit::Str))
+                .transpose()?
+                .map_or(quote! {None}, |lit| quote! {Some(#lit.to_string())});
+
             if is_vec_type {
                 partial_struct_members.push(quote! {
                     pub #field_ident: Vec<#internal_field_type>,
@@ -403,10 +528,15 @@ pub fn command_macro(args: TokenStream, mut input: DeriveInput) -> Result<TokenS
                     aliases: vec![#(#aliases_lit.to_string()),*],
                     documentation: #documentation,
                     default_value: #default_value_lit,
-                    is_hidden: false,
+                    env: #env_lit,
+                    choices: #choices,
+                    constraints: #constraints_spec,
+                    lifecycle: #lifecycle,
+                    is_hidden: #is_hidden,
                     is_required: #is_required,
                     allow_binding: false,
                     allow_boolean: #is_option2_type,
+                    allow_hyphen_values: #allow_hyphen_values,
                     min_len: #min_len_lit,
                     extra_len: #extra_len_lit,
                 }));
@@ -445,10 +575,15 @@ pub fn command_macro(args: TokenStream, mut input: DeriveInput) -> Result<TokenS
                             aliases: vec![],
                             documentation: None,
                             default_value: None,
+                            env: None,
+                            choices: vec![],
+                            constraints: vec![],
+                            lifecycle: #lifecycle,
                             is_hidden: true,
                             is_required: false,
                             allow_binding: false,
                             allow_boolean: false,
+                            allow_hyphen_values: false,
                             min_len: 0,
                             extra_len: Some(0),
                         }));
@@ -456,6 +591,20 @@ pub fn command_macro(args: TokenStream, mut input: DeriveInput) -> Result<TokenS
                 }
             }
 
+            option_names.extend(option_bag.path.iter().cloned());
+
+            if let Some(expr) = option_bag.attributes.take("conflicts_with") {
+                for name in expect_lit_strs(expr)? {
+                    option_groups.push(("Exclusive", None, vec![preferred_name_lit.clone(), name]));
+                }
+            }
+
+            for (key, variant) in [("requires", "Requires"), ("required_if", "RequiredIf"), ("required_unless", "RequiredUnless")] {
+                if let Some(expr) = option_bag.attributes.take(key) {
+                    option_groups.push((variant, Some(preferred_name_lit.clone()), expect_lit_strs(expr)?));
+                }
+            }
+
             option_bag.attributes.expect_empty()?;
         } else if let Some(mut positional_bag) = cli_attributes.take_unique::<AttributeBag>("positional")? {
             let documentation = positional_bag.take("description")
@@ -471,10 +620,22 @@ pub fn command_macro(args: TokenStream, mut input: DeriveInput) -> Result<TokenS
                 .map(|lit| lit.value())
                 .unwrap_or_default();
 
+            let allow_hyphen_values = positional_bag.take("allow_hyphen_values")
+                .map(expect_lit!(Lit::Bool))
+                .transpose()?
+                .map(|lit| lit.value())
+                .unwrap_or_default();
+
             let field_name_upper = field.ident.as_ref().unwrap()
                 .to_string()
                 .to_uppercase();
 
+            let constraints
+                = Constraints::take(&mut positional_bag, &field_name_upper)?;
+
+            let constraints_spec
+                = constraints.spec();
+
             if is_vec_type {
                 partial_struct_members.push(quote! {
                     pub #field_ident: Vec<#internal_field_type>,
@@ -484,9 +645,12 @@ pub fn command_macro(args: TokenStream, mut input: DeriveInput) -> Result<TokenS
                     #field_ident: std::default::Default::default(),
                 });
 
+                let parse_value
+                    = constraints.parse_value(quote! {arg.value}, internal_field_type);
+
                 hydraters.push(quote! {
                     let value = args.iter()
-                        .map(|arg| arg.value.parse().map_err(clipanion::details::handle_parse_error))
+                        .map(|arg| #parse_value)
                         .collect::<Result<Vec<_>, _>>()?;
 
                     partial.#field_ident = value;
@@ -500,8 +664,11 @@ pub fn command_macro(args: TokenStream, mut input: DeriveInput) -> Result<TokenS
                     command_spec.components.push(clipanion::core::Component::Positional(clipanion::core::PositionalSpec::Dynamic {
                         name: #field_name_upper.to_string(),
                         documentation: #documentation,
+                        choices: #choices,
+                        constraints: #constraints_spec,
                         min_len: 0,
                         extra_len: None,
+                        allow_hyphen_values: #allow_hyphen_values,
                         is_prefix: #is_prefix,
                         is_proxy: #is_proxy,
                     }));
@@ -515,12 +682,14 @@ pub fn command_macro(args: TokenStream, mut input: DeriveInput) -> Result<TokenS
                     #field_ident: std::default::Default::default(),
                 });
 
+                let parse_value
+                    = constraints.parse_value(quote! {positional.value}, internal_field_type);
+
                 if is_option_type {
                     hydraters.push(quote! {
                         let positional = args.first().unwrap();
 
-                        let value = positional.value.parse()
-                            .map_err(clipanion::details::handle_parse_error)?;
+                        let value = #parse_value?;
 
                         partial.#field_ident = Some(Some(value));
                     });
@@ -532,8 +701,7 @@ pub fn command_macro(args: TokenStream, mut input: DeriveInput) -> Result<TokenS
                     hydraters.push(quote! {
                         let positional = args.first().unwrap();
 
-                        let value = positional.value.parse()
-                            .map_err(clipanion::details::handle_parse_error)?;
+                        let value = #parse_value?;
 
                         partial.#field_ident = Some(value);
                     });
@@ -552,8 +720,11 @@ pub fn command_macro(args: TokenStream, mut input: DeriveInput) -> Result<TokenS
                     command_spec.components.push(clipanion::core::Component::Positional(clipanion::core::PositionalSpec::Dynamic {
                         name: #field_name_upper.to_string(),
                         documentation: #documentation,
+                        choices: #choices,
+                        constraints: #constraints_spec,
                         min_len: #min_len,
                         extra_len: #extra_len,
+                        allow_hyphen_values: #allow_hyphen_values,
                         is_prefix: #is_prefix,
                         is_proxy: false,
                     }));
@@ -564,6 +735,41 @@ pub fn command_macro(args: TokenStream, mut input: DeriveInput) -> Result<TokenS
         }
     }
 
+    for (variant, option, names) in option_groups {
+        if let Some(name) = names.iter().chain(option.iter()).find(|name| !option_names.contains(&name.value())) {
+            return Err(syn::Error::new_spanned(name, format!("Unknown option {}", name.value())));
+        }
+
+        let variant_ident
+            = Ident::new(variant, Span::call_site());
+
+        let group = match (option, variant) {
+            (None, _) => quote! {
+                clipanion::core::OptionGroup::#variant_ident {
+                    options: vec![#(command_spec.option_id(#names).unwrap()),*],
+                }
+            },
+
+            (Some(option), "Requires") => quote! {
+                clipanion::core::OptionGroup::Requires {
+                    option: command_spec.option_id(#option).unwrap(),
+                    requirements: vec![#(command_spec.option_id(#names).unwrap()),*],
+                }
+            },
+
+            (Some(option), _) => quote! {
+                clipanion::core::OptionGroup::#variant_ident {
+                    option: command_spec.option_id(#option).unwrap(),
+                    conditions: vec![#(command_spec.option_id(#names).unwrap()),*],
+                }
+            },
+        };
+
+        builder.push(quote! {
+            command_spec.option_groups.push(#group);
+        });
+    }
+
     if let Fields::Named(fields) = &mut struct_input.fields {
         fields.named.push(syn::parse_quote! {cli_environment: clipanion::advanced::Environment});
         fields.named.push(syn::parse_quote! {cli_path: Vec<String>});
@@ -611,7 +817,7 @@ pub fn command_macro(args: TokenStream, mut input: DeriveInput) -> Result<TokenS
             type Partial = #partial_struct_ident;
 
             fn command_usage(opts: clipanion::core::CommandUsageOptions) -> Result<clipanion::core::CommandUsageResult, clipanion::core::BuildError> {
-                Ok(#struct_name::command_spec()?.usage())
+                Ok(#struct_name::command_spec()?.usage_with(opts))
             }
 
             fn command_spec() -> Result<&'static clipanion::core::CommandSpec, clipanion::core::BuildError> {
@@ -624,6 +830,7 @@ pub fn command_macro(args: TokenStream, mut input: DeriveInput) -> Result<TokenS
 
                     command_spec.category = #command_category;
                     command_spec.documentation = #command_documentation;
+                    command_spec.lifecycle = #command_lifecycle;
 
                     #(#builder)*
 
@@ -645,11 +852,13 @@ pub fn command_macro(args: TokenStream, mut input: DeriveInput) -> Result<TokenS
                 ];
 
                 for (index, args) in &state.option_values {
-                    FNS[*index](&mut partial, args)?;
+                    FNS[index](&mut partial, &args)
+                        .map_err(|err| clipanion::details::locate_hydration_error(environment, &args, err))?;
                 }
 
                 for (index, args) in &state.positional_values {
-                    FNS[*index](&mut partial, args)?;
+                    FNS[index](&mut partial, &args)
+                        .map_err(|err| clipanion::details::locate_hydration_error(environment, &args, err))?;
                 }
 
                 Ok(partial)
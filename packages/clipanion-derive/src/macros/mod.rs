@@ -5,3 +5,4 @@ pub mod cli_provider;
 
 pub mod command;
 pub mod program;
+pub mod value_enum;
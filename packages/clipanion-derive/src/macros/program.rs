@@ -1,10 +1,10 @@
 use proc_macro::TokenStream;
-use quote::quote;
+use quote::{format_ident, quote};
 use syn::{DeriveInput, Expr, ExprLit, Lit};
 
 use crate::{shared::expect_lit, utils::AttributeBag};
 
-pub fn program_macro(args: TokenStream, input: DeriveInput) -> Result<TokenStream, syn::Error> {
+pub fn program_macro(args: TokenStream, mut input: DeriveInput) -> Result<TokenStream, syn::Error> {
     let mut command_attribute_bag
         = syn::parse::<AttributeBag>(args)?;
 
@@ -14,19 +14,85 @@ pub fn program_macro(args: TokenStream, input: DeriveInput) -> Result<TokenStrea
         .map(|lit| lit.value)
         .unwrap_or(false);
 
-    if is_async {
-        Ok(TokenStream::from(quote! {
-            #[clipanion::derive::cli_enum]
-            #[clipanion::derive::cli_exec_async]
-            #[clipanion::derive::cli_provider]
-            #input
-        }))
-    } else {
-        Ok(TokenStream::from(quote! {
-            #[clipanion::derive::cli_enum]
-            #[clipanion::derive::cli_exec_sync]
-            #[clipanion::derive::cli_provider]
-            #input
-        }))
+    let has_completion = command_attribute_bag.take("completion")
+        .map(expect_lit!(Lit::Bool))
+        .transpose()?
+        .map(|lit| lit.value)
+        .unwrap_or(false);
+
+    let negative_numbers = command_attribute_bag.take("negative_numbers")
+        .map(expect_lit!(Lit::Bool))
+        .transpose()?
+        .map(|lit| lit.value)
+        .unwrap_or(false);
+
+    let determinize = command_attribute_bag.take("determinize")
+        .map(expect_lit!(Lit::Bool))
+        .transpose()?
+        .map(|lit| lit.value)
+        .unwrap_or(false);
+
+    let lazy = command_attribute_bag.take("lazy")
+        .map(expect_lit!(Lit::Bool))
+        .transpose()?
+        .map(|lit| lit.value)
+        .unwrap_or(false);
+
+    command_attribute_bag.expect_empty()?;
+
+    let mut extra_items
+        = vec![];
+
+    if has_completion {
+        let syn::Data::Enum(enum_input) = &mut input.data else {
+            return Err(syn::Error::new_spanned(&input.ident, "Only enums are supported"));
+        };
+
+        let enum_ident
+            = &input.ident;
+        let enum_vis
+            = &input.vis;
+
+        let completion_command_ident
+            = format_ident!("{}CompletionCommand", enum_ident);
+
+        enum_input.variants.push(syn::parse_quote! {
+            Completion(#completion_command_ident)
+        });
+
+        let execute_fn = match is_async {
+            true => quote! {async fn execute(&self) -> clipanion::details::CommandResult},
+            false => quote! {fn execute(&self) -> clipanion::details::CommandResult},
+        };
+
+        extra_items.push(quote! {
+            /// Print a completion script for the given shell.
+            #[clipanion::derive::command]
+            #[cli::path("completion")]
+            #enum_vis struct #completion_command_ident {
+                /// The shell to generate the script for (bash, zsh, fish, or powershell).
+                shell: clipanion::completion::Shell,
+            }
+
+            impl #completion_command_ident {
+                #execute_fn {
+                    clipanion::completion::execute_completion_command::<#enum_ident>(&self.cli_environment, self.shell)
+                }
+            }
+        });
     }
+
+    let exec_macro = match is_async {
+        true => quote! {#[clipanion::derive::cli_exec_async]},
+        false => quote! {#[clipanion::derive::cli_exec_sync]},
+    };
+
+    Ok(TokenStream::from(quote! {
+        #(#extra_items)*
+
+        #[clipanion::derive::cli_enum]
+        #exec_macro
+        #[clipanion::derive::cli_provider(negative_numbers = #negative_numbers, determinize = #determinize, lazy = #lazy)]
+        #input
+    }))
 }
@@ -0,0 +1,112 @@
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::{Attribute, DeriveInput, Expr, ExprLit, Fields, Lit};
+
+use crate::{shared::expect_lit, utils::{expect_lit_strs, to_lit_str, AttributeBag}};
+
+fn to_kebab_case(ident: &str) -> String {
+    let mut result
+        = String::new();
+
+    for (index, c) in ident.chars().enumerate() {
+        if c.is_uppercase() && index > 0 {
+            result.push('-');
+        }
+
+        result.extend(c.to_lowercase());
+    }
+
+    result
+}
+
+fn take_value_attributes(attrs: &[Attribute]) -> syn::Result<Vec<AttributeBag>> {
+    attrs.iter()
+        .filter(|attr| attr.path().is_ident("value"))
+        .map(|attr| attr.parse_args::<AttributeBag>())
+        .collect()
+}
+
+pub fn value_enum_macro(input: DeriveInput) -> Result<TokenStream, syn::Error> {
+    let syn::Data::Enum(enum_input) = &input.data else {
+        return Err(syn::Error::new_spanned(&input.ident, "Only enums are supported"));
+    };
+
+    let enum_ident
+        = &input.ident;
+
+    let mut is_case_insensitive
+        = false;
+
+    for mut bag in take_value_attributes(&input.attrs)? {
+        is_case_insensitive |= bag.take("case_insensitive")
+            .map(expect_lit!(Lit::Bool))
+            .transpose()?
+            .is_some_and(|lit| lit.value);
+
+        bag.expect_empty()?;
+    }
+
+    let mut choices
+        = vec![];
+    let mut match_arms
+        = vec![];
+
+    for variant in &enum_input.variants {
+        if !matches!(variant.fields, Fields::Unit) {
+            return Err(syn::Error::new_spanned(variant, "Only unit variants are supported"));
+        }
+
+        let variant_ident
+            = &variant.ident;
+
+        let mut name
+            = to_lit_str(to_kebab_case(&variant_ident.to_string()));
+        let mut aliases
+            = vec![];
+
+        for mut bag in take_value_attributes(&variant.attrs)? {
+            if let Some(lit) = bag.take("name").map(expect_lit!(Lit::Str)).transpose()? {
+                name = lit;
+            }
+
+            if let Some(expr) = bag.take("alias") {
+                aliases.extend(expect_lit_strs(expr)?);
+            }
+
+            bag.expect_empty()?;
+        }
+
+        let matchers = std::iter::once(&name)
+            .chain(aliases.iter())
+            .map(|lit| match is_case_insensitive {
+                true => quote! {value.eq_ignore_ascii_case(#lit)},
+                false => quote! {value == #lit},
+            });
+
+        match_arms.push(quote! {
+            if #(#matchers)||* {
+                return Ok(#enum_ident::#variant_ident);
+            }
+        });
+
+        choices.push(name);
+    }
+
+    Ok(TokenStream::from(quote! {
+        impl clipanion::details::ValueEnum for #enum_ident {
+            fn choices() -> &'static [&'static str] {
+                &[#(#choices),*]
+            }
+        }
+
+        impl ::std::str::FromStr for #enum_ident {
+            type Err = clipanion::core::CommandError;
+
+            fn from_str(value: &str) -> Result<Self, Self::Err> {
+                #(#match_arms)*
+
+                Err(clipanion::core::CommandError::InvalidChoice(value.to_string(), vec![#(#choices.to_string()),*]))
+            }
+        }
+    }))
+}
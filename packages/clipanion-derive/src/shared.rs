@@ -1,5 +1,7 @@
 use quote::format_ident;
 
+use crate::utils::CliAttributes;
+
 macro_rules! expect_lit {
     ($expression:path) => {
         |val| match val {
@@ -14,3 +16,37 @@ pub(crate) use expect_lit;
 pub fn get_partial_enum_ident(enum_ident: &syn::Ident) -> syn::Ident {
     format_ident!("Partial{}", enum_ident)
 }
+
+/**
+ * Return the path a program variant is mounted under, as declared by
+ * `Config(#[cli::mount("config")] ConfigCli)`. The attribute is left in place
+ * so that each of the program macros can read it; `cli_provider` removes it.
+ */
+pub fn get_mount_path(field: &syn::Field) -> syn::Result<Option<Vec<syn::LitStr>>> {
+    let mut attrs
+        = field.attrs.clone();
+
+    let mut mount_paths
+        = CliAttributes::extract(&mut attrs)?.take_lit_lists("mount")?;
+
+    if mount_paths.len() > 1 {
+        return Err(syn::Error::new_spanned(field, "A program can only be mounted once"));
+    }
+
+    match mount_paths.pop() {
+        Some(mount_path) if mount_path.is_empty() => {
+            Err(syn::Error::new_spanned(field, "Mount paths must have at least one segment"))
+        },
+
+        mount_path => {
+            Ok(mount_path)
+        },
+    }
+}
+
+pub fn is_mount_attribute(attr: &syn::Attribute) -> bool {
+    let segments
+        = &attr.path().segments;
+
+    segments.len() == 2 && segments[0].ident == "cli" && segments[1].ident == "mount"
+}
@@ -1,6 +1,8 @@
 use std::collections::HashMap;
 
-use syn::{parse::{Parse, ParseStream}, punctuated::Punctuated, Attribute, Expr, ExprLit, Ident, Lit, LitBool, LitStr, Meta, Token};
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{parse::{Parse, ParseStream}, punctuated::Punctuated, Attribute, Expr, ExprArray, ExprLit, ExprRange, Ident, Lit, LitBool, LitStr, Meta, RangeLimits, Token, Type};
 
 pub fn to_lit_str<T: AsRef<str>>(str: T) -> LitStr {
     LitStr::new(str.as_ref(), proc_macro2::Span::call_site())
@@ -78,6 +80,173 @@ impl Parse for AttributeBag {
     }
 }
 
+/**
+ * Extract the `deprecated`, `experimental`, and `since` attributes into a
+ * `Lifecycle` expression.
+ */
+pub fn take_lifecycle(bag: &mut AttributeBag) -> syn::Result<TokenStream> {
+    let deprecated = bag.take("deprecated")
+        .map(expect_lit!(Lit::Str))
+        .transpose()?
+        .map_or(quote! {None}, |lit| quote! {Some(#lit.to_string())});
+
+    let is_experimental = bag.take("experimental")
+        .map(expect_lit!(Lit::Bool))
+        .transpose()?
+        .is_some_and(|lit| lit.value);
+
+    let since = bag.take("since")
+        .map(expect_lit!(Lit::Str))
+        .transpose()?
+        .map_or(quote! {None}, |lit| quote! {Some(#lit.to_string())});
+
+    Ok(quote! {
+        clipanion::core::Lifecycle {
+            deprecated: #deprecated,
+            is_experimental: #is_experimental,
+            since: #since,
+        }
+    })
+}
+
+/**
+ * Accept either a single string literal or an array of them.
+ */
+pub fn expect_lit_strs(expr: Expr) -> syn::Result<Vec<LitStr>> {
+    match expr {
+        Expr::Array(ExprArray {elems, ..}) => {
+            elems.into_iter()
+                .map(expect_lit!(Lit::Str))
+                .collect()
+        },
+
+        expr => {
+            Ok(vec![expect_lit!(Lit::Str)(expr)?])
+        },
+    }
+}
+
+fn parse_len_bound(expr: Option<Box<Expr>>) -> syn::Result<Option<usize>> {
+    expr.map(|expr| expect_lit!(Lit::Int)(*expr)?.base10_parse::<usize>())
+        .transpose()
+}
+
+/**
+ * The `min`, `max`, `len`, and `pattern` attributes of an option or
+ * positional argument. Lengths and patterns are checked against the raw
+ * arguments, bounds against the parsed values.
+ */
+#[derive(Default)]
+pub struct Constraints {
+    pub specs: Vec<TokenStream>,
+    pub raw_checks: Vec<TokenStream>,
+    pub value_checks: Vec<TokenStream>,
+}
+
+impl Constraints {
+    pub fn take(bag: &mut AttributeBag, field_name: &str) -> syn::Result<Self> {
+        let mut constraints
+            = Constraints::default();
+
+        if let Some(expr) = bag.take("min") {
+            constraints.specs.push(quote! {clipanion::core::Constraint::Min {value: (#expr).to_string()}});
+            constraints.value_checks.push(quote! {clipanion::details::check_min(#field_name, &value, #expr)?;});
+        }
+
+        if let Some(expr) = bag.take("max") {
+            constraints.specs.push(quote! {clipanion::core::Constraint::Max {value: (#expr).to_string()}});
+            constraints.value_checks.push(quote! {clipanion::details::check_max(#field_name, &value, #expr)?;});
+        }
+
+        if let Some(expr) = bag.take("len") {
+            let (min, max) = match expr {
+                Expr::Range(ExprRange {start, end, limits, ..}) => {
+                    let min = parse_len_bound(start)?.unwrap_or(0);
+                    let max = parse_len_bound(end)?;
+
+                    match (limits, max) {
+                        (RangeLimits::HalfOpen(_), Some(max)) => (min, Some(max.saturating_sub(1))),
+                        (_, max) => (min, max),
+                    }
+                },
+
+                expr => {
+                    let len = expect_lit!(Lit::Int)(expr)?
+                        .base10_parse::<usize>()?;
+
+                    (len, Some(len))
+                },
+            };
+
+            let max_lit = match max {
+                Some(max) => quote! {Some(#max)},
+                None => quote! {None},
+            };
+
+            constraints.specs.push(quote! {clipanion::core::Constraint::Len {min: #min, max: #max_lit}});
+            constraints.raw_checks.push(quote! {clipanion::details::check_len(#field_name, raw, #min, #max_lit)?;});
+        }
+
+        if let Some(expr) = bag.take("pattern") {
+            let lit
+                = expect_lit!(Lit::Str)(expr)?;
+
+            if let Err(err) = regex::Regex::new(&lit.value()) {
+                return Err(syn::Error::new_spanned(lit, format!("Invalid pattern: {}", err)));
+            }
+
+            constraints.specs.push(quote! {clipanion::core::Constraint::Pattern {pattern: #lit.to_string()}});
+            constraints.raw_checks.push(quote! {{
+                static PATTERN: std::sync::LazyLock<clipanion::details::Regex>
+                    = std::sync::LazyLock::new(|| clipanion::details::Regex::new(#lit).unwrap());
+
+                clipanion::details::check_pattern(#field_name, raw, &PATTERN)?;
+            }});
+        }
+
+        Ok(constraints)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.specs.is_empty()
+    }
+
+    pub fn spec(&self) -> TokenStream {
+        let specs
+            = &self.specs;
+
+        quote! {vec![#(#specs),*]}
+    }
+
+    /**
+     * Generate an expression parsing the given argument into a
+     * `Result<T, CommandError>`, applying the constraints along the way.
+     */
+    pub fn parse_value(&self, raw: TokenStream, ty: &Type) -> TokenStream {
+        if self.is_empty() {
+            return quote! {#raw.parse().map_err(clipanion::details::handle_parse_error)};
+        }
+
+        let raw_checks
+            = &self.raw_checks;
+        let value_checks
+            = &self.value_checks;
+
+        quote! {
+            (|| -> Result<#ty, clipanion::core::CommandError> {
+                let raw: &str = #raw;
+                #(#raw_checks)*
+
+                let value: #ty = raw.parse()
+                    .map_err(clipanion::details::handle_parse_error)?;
+
+                #(#value_checks)*
+                Ok(value)
+            })()
+        }
+    }
+}
+
 #[derive(Clone, Default)]
 pub struct OptionBag {
     pub path: Vec<String>,
@@ -147,7 +316,7 @@ impl CliAttributes {
                     pound_token: Default::default(),
                     style: syn::AttrStyle::Outer,
                     bracket_token: Default::default(),
-                    meta: meta,
+                    meta,
                 };
 
                 attribute.parse_args::<T>()
@@ -224,9 +393,8 @@ impl CliAttributes {
                 }
 
                 let attr = &values[0];
-                let res = Self::parse_args(attr).map(Some);
 
-                res
+                Self::parse_args(attr).map(Some)
             },
 
             None => Ok(None),
@@ -234,7 +402,15 @@ impl CliAttributes {
     }
 
     pub fn take_paths(&mut self) -> syn::Result<Vec<Vec<LitStr>>> {
-        let path_attributes = self.attributes.remove("path")
+        self.take_lit_lists("path")
+    }
+
+    /**
+     * Return the string lists of all the attributes with the given name, for
+     * attributes such as `#[cli::path("config", "get")]`.
+     */
+    pub fn take_lit_lists(&mut self, key: &str) -> syn::Result<Vec<Vec<LitStr>>> {
+        let path_attributes = self.attributes.remove(key)
             .unwrap_or_default();
 
         let punctuated_paths = path_attributes.into_iter()
@@ -247,4 +423,28 @@ impl CliAttributes {
 
         Ok(path_lits)
     }
+
+    pub fn take_examples(&mut self) -> syn::Result<Vec<(LitStr, LitStr)>> {
+        let example_attributes = self.attributes.remove("example")
+            .unwrap_or_default();
+
+        example_attributes.into_iter().map(|attr| {
+            let mut bag
+                = attr.parse_args::<AttributeBag>()?;
+
+            let command = bag.take("command")
+                .map(expect_lit!(Lit::Str))
+                .transpose()?
+                .ok_or_else(|| syn::Error::new_spanned(&attr, "Examples must have a `command` string"))?;
+
+            let description = bag.take("description")
+                .map(expect_lit!(Lit::Str))
+                .transpose()?
+                .ok_or_else(|| syn::Error::new_spanned(&attr, "Examples must have a `description` string"))?;
+
+            bag.expect_empty()?;
+
+            Ok((command, description))
+        }).collect()
+    }
 }
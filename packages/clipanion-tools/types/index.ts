@@ -1,17 +1,67 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
 
-export type CommandSpec = { primaryPath: Array<string>, aliases: Array<Array<string>>, category: string | null, documentation: Documentation | null, examples: Array<Example>, components: Array<Component>, requiredOptions: Array<number>, };
+/**
+ * The location of an argument that was read from a file (for example a
+ * response file passed as `@path`) rather than from the command line.
+ */
+export type ArgSource = { path: string, line: number, };
 
-export type Component = { "type": "positional" } & PositionalSpec | { "type": "option" } & OptionSpec;
+export type CommandSpec = { primaryPath: Array<string>, aliases: Array<Array<string>>, category: string | null, documentation: Documentation | null, examples: Array<Example>, components: Array<Component>, requiredOptions: Array<number>, optionGroups: Array<OptionGroup>, lifecycle: Lifecycle, isHidden: boolean, };
+
+export type Completion = { "type": "keyword", value: string, } | { "type": "option", value: string, commandId: number, componentId: number, } | { "type": "choice", value: string, commandId: number, componentId: number, } | { "type": "value", commandId: number, componentId: number, };
+
+export type Component = { "type": "positional" } & PositionalSpec | { "type": "option" } & OptionSpec | { "type": "passthrough" } & PassthroughSpec;
+
+/**
+ * A check applied to the values of an option or positional argument. Bounds
+ * are kept as written in the command definition so they can be displayed
+ * as-is; lengths are counted in characters.
+ */
+export type Constraint = { "type": "min", value: string, } | { "type": "max", value: string, } | { "type": "len", min: number, max: number | null, } | { "type": "pattern", pattern: string, };
 
 export type Documentation = { description: string, details: string | null, };
 
+/**
+ * Why a candidate state was discarded while selecting the command to run.
+ */
+export type DropReason = { "type": "unsuccessful" } | { "type": "invalidOptions", error: string, } | { "type": "hydrationFailed", error: string, } | { "type": "lessGreedy", keptStateId: number, } | { "type": "unusedPositionals", unusedCount: number, minUnusedCount: number, };
+
 export type Example = { command: string, description: string, };
 
-export type OptionSpec = { primaryName: string, aliases: Array<string>, documentation: Documentation | null, defaultValue: string | null, minLen: number, extraLen: number | null, allowBinding: boolean, allowBoolean: boolean, isHidden: boolean, isRequired: boolean, };
+/**
+ * Where a command or option stands in its release cycle. Deprecated items
+ * still work but print a warning, while experimental ones are refused unless
+ * the user opts into them.
+ */
+export type Lifecycle = { deprecated: string | null, isExperimental: boolean, since: string | null, };
+
+/**
+ * A relation between the options of a command, checked once the command line
+ * has been parsed. Options are referenced by their component id.
+ */
+export type OptionGroup = { "type": "exclusive", options: Array<number>, } | { "type": "atLeastOne", options: Array<number>, } | { "type": "requires", option: number, requirements: Array<number>, } | { "type": "requiredIf", option: number, conditions: Array<number>, } | { "type": "requiredUnless", option: number, conditions: Array<number>, };
 
-export type PositionalSpec = { "positionalType": "keyword", expected: string, } | { "positionalType": "dynamic", name: string, documentation: Documentation | null, min_len: number, extra_len: number | null, is_prefix: boolean, is_proxy: boolean, };
+export type OptionSpec = { primaryName: string, aliases: Array<string>, documentation: Documentation | null, defaultValue: string | null, env: string | null, choices: Array<string>, constraints: Array<Constraint>, lifecycle: Lifecycle, minLen: number, extraLen: number | null, allowBinding: boolean, allowBoolean: boolean, allowHyphenValues: boolean, isHidden: boolean, isRequired: boolean, };
+
+export type PassthroughSpec = { name: string, documentation: Documentation | null, };
+
+export type PositionalSpec = { "positionalType": "keyword", expected: string, } | { "positionalType": "dynamic", name: string, documentation: Documentation | null, choices: Array<string>, constraints: Array<Constraint>, min_len: number, extra_len: number | null, allow_hyphen_values: boolean, is_prefix: boolean, is_proxy: boolean, };
+
+/**
+ * The selection steps that can discard candidates.
+ */
+export type PruneStep = "requiredOptions" | "unsuccessfulNodes" | "hydrationResults" | "greediness" | "unusedPositionals";
 
 export type Token = { "type": "binary", argIndex: number, slice: { start: number, end: number, }, } | { "type": "syntax", argIndex: number, slice: { start: number, end: number, }, } | { "type": "keyword", argIndex: number, slice: { start: number, end: number, }, } | { "type": "option", argIndex: number, slice: { start: number, end: number, }, componentId: number, } | { "type": "positional", argIndex: number, slice: { start: number, end: number, }, componentId: number, } | { "type": "assign", argIndex: number, slice: { start: number, end: number, }, componentId: number, } | { "type": "value", argIndex: number, slice: { start: number, end: number, }, componentId: number, };
 
-export type TokenSet = { commandId: number, tokens: Array<Token>, };
+export type TokenSet = { commandId: number, tokens: Array<Token>, sources: Array<ArgSource | null>, };
+
+/**
+ * A step of the parsing process. The runner reports the transitions it takes
+ * for each argument, then the selector reports the states it got from the
+ * runner and how it narrowed them down to a single one.
+ *
+ * Nodes refer to the compiled machine, contexts to the command ids, and
+ * states to their position in the list the selector received.
+ */
+export type TraceEvent = { "type": "startOfInput" } | { "type": "argument", index: number, value: string, } | { "type": "endOfInput" } | { "type": "transition", contextId: number, from: number, to: number, reducer: string | null, } | { "type": "noTransition", contextId: number, from: number, } | { "type": "trim", contextId: number, nodeId: number, keywordCount: number, maxKeywordCount: number, } | { "type": "candidate", stateId: number, contextId: number, isSuccessful: boolean, } | { "type": "drop", stateId: number, contextId: number, reason: DropReason, } | { "type": "prune", step: PruneStep, remaining: Array<number>, } | { "type": "selected", stateId: number, contextId: number, };
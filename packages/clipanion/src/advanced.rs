@@ -1,8 +1,23 @@
-use std::{collections::HashMap, future::Future};
+use std::{collections::{BTreeSet, HashMap}, future::Future, sync::Arc};
 
-use clipanion_core::{BuiltinCommand, CliBuilder, Info, SelectionResult};
+use clipanion_core::{autocorrect, ArgSource, BuiltinCommand, CliBuilder, CommandError, CommandSpec, Info, SelectionResult, State};
 
-use crate::{details::{CliEnums, CommandExecutor, CommandExecutorAsync, CommandProvider}, format::{write_color, write_fading_title_line, Formatter}};
+#[cfg(feature = "serde")]
+use crate::explain::render_explanation_json;
+
+use crate::{config::ConfigTable, details::{deprecation_warnings, parse_args_with, parse_args_with_trace, CliEnums, CommandExecutor, CommandExecutorAsync, CommandProvider, CommandResult}, dynamic::{DynCommand, DynamicCommands}, explain::render_explanation, format::{format_lifecycle_tags, write_color, write_fading_title_line, Formatter}, man::{render_command_page, render_index_page}, response_files::{self, ResponseFileError}};
+
+/**
+ * What to do when the arguments don't match any command, but a typo fix has
+ * been found that would make them valid (git's `help.autocorrect`).
+ */
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Autocorrect {
+    #[default]
+    Disabled,
+    Prompt,
+    Delay(std::time::Duration),
+}
 
 /**
  * Used to define the properties of the CLI. In general you can ignore this and
@@ -13,6 +28,14 @@ use crate::{details::{CliEnums, CommandExecutor, CommandExecutorAsync, CommandPr
  pub struct Environment {
     pub info: Info,
     pub argv: Vec<String>,
+    pub env_vars: HashMap<String, String>,
+    pub env_prefix: Option<String>,
+    pub config: ConfigTable,
+    pub autocorrect: Autocorrect,
+    pub experimental: bool,
+    pub response_files: Option<usize>,
+    pub arg_sources: Vec<Option<ArgSource>>,
+    pub dynamic_commands: DynamicCommands,
  }
 
 impl Environment {
@@ -40,13 +63,140 @@ impl Environment {
         self.argv = argv;
         self
     }
+
+    pub fn with_env_vars(mut self, env_vars: HashMap<String, String>) -> Self {
+        self.env_vars = env_vars;
+        self
+    }
+
+    /**
+     * Options without an explicit `env` attribute will also be read from the
+     * environment, using variables named after their long name (for example
+     * `--dry-run` becomes `<PREFIX>_DRY_RUN`).
+     */
+    pub fn with_env_prefix(mut self, env_prefix: String) -> Self {
+        self.env_prefix = Some(env_prefix);
+        self
+    }
+
+    /**
+     * Option values to use when they aren't set on the command line nor in
+     * the environment; see `ConfigSources` to load them from the usual
+     * configuration files.
+     */
+    pub fn with_config(mut self, config: ConfigTable) -> Self {
+        self.config = config;
+        self
+    }
+
+    pub fn with_autocorrect(mut self, autocorrect: Autocorrect) -> Self {
+        self.autocorrect = autocorrect;
+        self
+    }
+
+    /**
+     * Allow the use of experimental commands and options. Users can also opt
+     * in by setting the variable returned by `experimental_env_var` to `1`.
+     */
+    pub fn with_experimental(mut self, experimental: bool) -> Self {
+        self.experimental = experimental;
+        self
+    }
+
+    /**
+     * The environment variable enabling experimental features; derived from
+     * the program prefix when there's one (`<PREFIX>_EXPERIMENTAL`), and
+     * `CLIPANION_EXPERIMENTAL` otherwise.
+     */
+    pub fn experimental_env_var(&self) -> String {
+        match &self.env_prefix {
+            Some(env_prefix) => format!("{}_EXPERIMENTAL", env_prefix),
+            None => "CLIPANION_EXPERIMENTAL".to_string(),
+        }
+    }
+
+    /**
+     * Replace the `@path` arguments by the content of the referenced files
+     * before parsing them; see `expand_response_files` for the syntax.
+     */
+    pub fn with_response_files(mut self, enabled: bool) -> Self {
+        self.response_files = enabled.then_some(response_files::DEFAULT_MAX_DEPTH);
+        self
+    }
+
+    pub fn with_response_files_max_depth(mut self, max_depth: usize) -> Self {
+        self.response_files = Some(max_depth);
+        self
+    }
+
+    /**
+     * Expand the response files referenced in the arguments, if enabled; the
+     * returned environment keeps track of where each argument came from.
+     *
+     * Completion requests are left untouched: the `@path` being completed is
+     * usually incomplete, and expanding the others would shift the cursor.
+     */
+    pub fn expand_response_files(&self) -> Result<Environment, ResponseFileError> {
+        let Some(max_depth) = self.response_files else {
+            return Ok(self.clone());
+        };
+
+        if self.argv.first().is_some_and(|arg| arg.starts_with("--clipanion-complete")) {
+            return Ok(self.clone());
+        }
+
+        let (argv, arg_sources)
+            = response_files::expand_response_files(&self.argv, max_depth)?;
+
+        let mut env
+            = self.clone().with_argv(argv);
+
+        env.response_files = None;
+        env.arg_sources = arg_sources;
+
+        Ok(env)
+    }
+
+    /**
+     * The file and line the given argument was read from, if it came from a
+     * response file.
+     */
+    pub fn arg_source(&self, index: usize) -> Option<&ArgSource> {
+        self.arg_sources.get(index)?.as_ref()
+    }
+
+    /**
+     * Register a command discovered at runtime; it can be called alongside
+     * the commands of the program. Note that the state machine then has to
+     * be compiled on each run, even if the program was precompiled.
+     */
+    pub fn with_dynamic_command(mut self, spec: impl Into<Arc<CommandSpec>>, command: Box<dyn DynCommand>) -> Self {
+        self.dynamic_commands.register(spec, command);
+        self
+    }
+
+    pub fn is_experimental_enabled(&self) -> bool {
+        self.experimental || self.env_vars.get(&self.experimental_env_var()).is_some_and(|value| {
+            matches!(value.to_lowercase().as_str(), "1" | "true" | "yes" | "on")
+        })
+    }
+}
+
+/**
+ * The environment variables of the process; the ones whose name or value
+ * isn't valid UTF-8 can't be used as option values, so they're skipped.
+ */
+fn env_vars_from_os() -> HashMap<String, String> {
+    std::env::vars_os()
+        .filter_map(|(name, value)| Some((name.into_string().ok()?, value.into_string().ok()?)))
+        .collect()
 }
 
 impl Default for Environment {
     fn default() -> Self {
         let binary_name = std::env::args()
             .next().unwrap()
-            .split('/').last().unwrap()
+            .split('/').next_back().unwrap()
             .to_string();
 
         let argv
@@ -56,6 +206,14 @@ impl Default for Environment {
 
         Self {
             argv,
+            env_vars: env_vars_from_os(),
+            env_prefix: None,
+            config: ConfigTable::default(),
+            autocorrect: Autocorrect::Disabled,
+            experimental: false,
+            response_files: None,
+            arg_sources: vec![],
+            dynamic_commands: DynamicCommands::default(),
             info: Info {
                 program_name: "my-program".to_string(),
                 binary_name,
@@ -67,7 +225,71 @@ impl Default for Environment {
     }
 }
 
-fn report_error<'cmds, 'args, S: CommandProvider>(env: &Environment, err: clipanion_core::Error<'cmds>) -> std::process::ExitCode {
+/**
+ * If autocorrection is enabled and the arguments contain an unambiguous typo,
+ * tell the user about it and return the fixed environment once they agreed
+ * to it (or once the delay expired).
+ */
+fn autocorrect_env(builder: &CliBuilder<'_>, env: &Environment, err: &clipanion_core::Error) -> Option<Environment> {
+    if env.autocorrect == Autocorrect::Disabled {
+        return None;
+    }
+
+    if !matches!(err, clipanion_core::Error::NotFound(_) | clipanion_core::Error::CommandError(_, CommandError::UnknownOption(..))) {
+        return None;
+    }
+
+    let argv = env.argv.iter()
+        .map(|arg| arg.as_str())
+        .collect::<Vec<_>>();
+
+    let suggestions
+        = builder.suggest(&argv);
+
+    let (index, replacement)
+        = autocorrect(&suggestions, &argv)?;
+
+    eprintln!("WARNING: You called `{}`, which does not exist.", argv[index]);
+
+    match env.autocorrect {
+        Autocorrect::Disabled => {
+            return None;
+        },
+
+        Autocorrect::Prompt => {
+            eprint!("Run `{}` instead? [y/N] ", replacement);
+
+            let mut answer
+                = String::new();
+
+            std::io::stdin().read_line(&mut answer).ok()?;
+
+            if !matches!(answer.trim(), "y" | "Y" | "yes") {
+                return None;
+            }
+        },
+
+        Autocorrect::Delay(delay) => {
+            eprintln!("Continuing in {:.1} seconds, assuming that you meant `{}`.", delay.as_secs_f32(), replacement);
+            std::thread::sleep(delay);
+        },
+    }
+
+    let mut argv
+        = env.argv.clone();
+
+    argv[index] = replacement;
+
+    Some(env.clone().with_argv(argv).with_autocorrect(Autocorrect::Disabled))
+}
+
+fn report_deprecations(env: &Environment, command_spec: &CommandSpec, state: &State<'_>) {
+    for warning in deprecation_warnings(&env.info, command_spec, state) {
+        eprintln!("WARNING: {}", warning);
+    }
+}
+
+fn report_error<'cmds, S: CommandProvider>(env: &Environment, err: clipanion_core::Error<'cmds>) -> std::process::ExitCode {
     match err {
         clipanion_core::Error::CommandError(command_spec, command_error) => {
             println!("{}", Formatter::<S>::format_error(&env.info, "Error", &command_error.to_string(), vec![command_spec]));
@@ -95,8 +317,69 @@ fn report_error<'cmds, 'args, S: CommandProvider>(env: &Environment, err: clipan
     }
 }
 
-fn handle_builtin<'cmds, 'args, S: CliEnums + CommandProvider>(builder: &CliBuilder<'static>, env: &'args Environment, builtin: BuiltinCommand<'cmds, 'args>) -> Result<std::process::ExitCode, clipanion_core::Error<'cmds>> {
+fn report_command_result<S: CommandProvider>(env: &Environment, command_spec: &CommandSpec, command_result: CommandResult) -> std::process::ExitCode {
+    if let Some(error_message) = &command_result.error_message {
+        return report_error::<S>(env, clipanion_core::Error::CommandError(command_spec, error_message.to_string().into()));
+    }
+
+    command_result.exit_code
+}
+
+fn report_response_file_error<S: CommandProvider>(env: &Environment, err: ResponseFileError) -> std::process::ExitCode {
+    println!("{}", Formatter::<S>::format_error(&env.info, "Error", &err.to_string(), vec![]));
+    std::process::ExitCode::FAILURE
+}
+
+/**
+ * The command selected by the parser: either one of the program's own, or one
+ * registered at runtime (identified by its index in the dynamic commands).
+ */
+enum Selected<T> {
+    Program(T),
+    Dynamic(usize),
+}
+
+fn build_cli<S: CommandProvider>(env: &Environment) -> Result<CliBuilder<'_>, clipanion_core::BuildError> {
+    let mut builder: CliBuilder<'_>
+        = S::build_cli()?;
+
+    // Adding commands drops the machine precompiled by the program, so we
+    // only pay for its compilation when there's something to add
+    if !env.dynamic_commands.is_empty() {
+        env.dynamic_commands.add_to(&mut builder);
+    }
+
+    Ok(builder)
+}
+
+fn hydrate_selected<'args, S: CliEnums + CommandProvider>(env: &'args Environment, state: &State<'args>) -> Result<Selected<S::PartialEnum>, CommandError> {
+    match state.context_id.checked_sub(S::COMMAND_COUNT) {
+        Some(index) => Ok(Selected::Dynamic(index)),
+        None => S::hydrate_command(state.context_id, env, state).map(Selected::Program),
+    }
+}
+
+fn parse_args<'cmds, 'args, S: CliEnums + CommandProvider>(builder: &CliBuilder<'cmds>, env: &'args Environment) -> Result<SelectionResult<'cmds, 'args, Selected<S::PartialEnum>>, clipanion_core::Error<'cmds>> {
+    parse_args_with(builder, env, |state| hydrate_selected::<S>(env, state))
+}
+
+fn handle_builtin<'cmds, 'args, S: CliEnums + CommandProvider>(builder: &CliBuilder<'cmds>, env: &'args Environment, builtin: BuiltinCommand<'cmds, 'args>) -> Result<std::process::ExitCode, clipanion_core::Error<'cmds>> {
     match builtin {
+        BuiltinCommand::Complete(command_line, cursor) => {
+            let completions
+                = builder.complete(&command_line, cursor);
+
+            let words = completions.iter()
+                .filter_map(|completion| completion.word())
+                .collect::<BTreeSet<_>>();
+
+            for word in words {
+                println!("{}", word);
+            }
+
+            Ok(std::process::ExitCode::SUCCESS)
+        },
+
         BuiltinCommand::Describe => {
             #[cfg(not(feature = "serde"))] {
                 println!("Using this command requires the 'serde' feature to be enabled.");
@@ -105,8 +388,9 @@ fn handle_builtin<'cmds, 'args, S: CliEnums + CommandProvider>(builder: &CliBuil
             }
 
             #[cfg(feature = "serde")] {
-                let commands
-                    = S::registered_commands()?;
+                let commands = builder.commands().iter()
+                    .map(|command| command.with_env_prefix(env.env_prefix.as_deref()))
+                    .collect::<Vec<_>>();
 
                 let commands_json
                     = serde_json::to_string(&commands)
@@ -118,6 +402,60 @@ fn handle_builtin<'cmds, 'args, S: CliEnums + CommandProvider>(builder: &CliBuil
             }
         },
 
+        BuiltinCommand::Explain(command_line, is_json) => {
+            let explained_env = env.clone()
+                .with_argv(command_line.iter().map(|arg| arg.to_string()).collect());
+
+            let mut trace
+                = vec![];
+
+            let result
+                = parse_args_with_trace(builder, &explained_env, Some(&mut trace), |state| hydrate_selected::<S>(&explained_env, state));
+
+            if is_json {
+                #[cfg(not(feature = "serde"))] {
+                    println!("Using this command requires the 'serde' feature to be enabled.");
+
+                    return Err(clipanion_core::Error::InternalError);
+                }
+
+                #[cfg(feature = "serde")] {
+                    let explanation_json
+                        = render_explanation_json(builder.commands(), &command_line, &trace, &result)
+                            .map_err(|_| clipanion_core::Error::InternalError)?;
+
+                    println!("{}", explanation_json);
+
+                    return Ok(std::process::ExitCode::SUCCESS);
+                }
+            }
+
+            print!("{}", render_explanation(&env.info, builder.commands(), &command_line, &trace, &result));
+
+            Ok(std::process::ExitCode::SUCCESS)
+        },
+
+        BuiltinCommand::Manual(path) => {
+            let commands
+                = builder.commands().to_vec();
+
+            let page = match path.is_empty() {
+                true => render_index_page(&env.info, &commands),
+
+                false => {
+                    let command = commands.iter()
+                        .find(|command| std::iter::once(&command.primary_path).chain(command.aliases.iter()).any(|command_path| command_path.iter().eq(path.iter())))
+                        .ok_or(clipanion_core::Error::NotFound(vec![]))?;
+
+                    render_command_page(&env.info, command)
+                },
+            };
+
+            print!("{}", page);
+
+            Ok(std::process::ExitCode::SUCCESS)
+        },
+
         BuiltinCommand::Tokenize(command_line) => {
             #[cfg(not(feature = "tokens"))] {
                 println!("Using this command requires the 'tokens' feature to be enabled.");
@@ -131,9 +469,15 @@ fn handle_builtin<'cmds, 'args, S: CliEnums + CommandProvider>(builder: &CliBuil
                 let mut states
                     = builder.run_partial(&command_line);
 
+                // The tokenized command line starts after the builtin flag
+                let sources = (0..command_line.len())
+                    .map(|index| env.arg_source(index + 1).cloned())
+                    .collect();
+
                 let result = states.pop().map(|state| TokenSet {
                     command_id: state.context_id,
-                    tokens: state.tokens.clone(),
+                    tokens: state.tokens.to_vec(),
+                    sources,
                 });
 
                 let tokens_json
@@ -151,6 +495,11 @@ fn handle_builtin<'cmds, 'args, S: CliEnums + CommandProvider>(builder: &CliBuil
             Ok(std::process::ExitCode::SUCCESS)
         },
 
+        BuiltinCommand::Help(commands) if commands.len() == 1 => {
+            print!("{}", Formatter::<S>::format_command_help(&env.info, &commands[0].with_env_prefix(env.env_prefix.as_deref())));
+            Ok(std::process::ExitCode::SUCCESS)
+        },
+
         BuiltinCommand::Help(commands) => {
             let mut output_string
                 = String::new();
@@ -158,7 +507,7 @@ fn handle_builtin<'cmds, 'args, S: CliEnums + CommandProvider>(builder: &CliBuil
             write_fading_title_line(&mut output_string, &format!("{} - {}", env.info.program_name, env.info.version), (255, 255, 255), 80, 50);
 
             let commands = match commands.is_empty() {
-                true => S::registered_commands().unwrap(),
+                true => builder.commands().to_vec(),
                 false => commands,
             };
 
@@ -177,7 +526,7 @@ fn handle_builtin<'cmds, 'args, S: CliEnums + CommandProvider>(builder: &CliBuil
                 = HashMap::<_, Vec<_>>::new();
 
             for command in &commands {
-                if command.documentation.is_some() {
+                if !command.is_hidden {
                     let category = command.category
                         .as_ref()
                         .map(|category| category.as_ref());
@@ -203,21 +552,30 @@ fn handle_builtin<'cmds, 'args, S: CliEnums + CommandProvider>(builder: &CliBuil
                 write_fading_title_line(&mut output_string, category, (128, 128, 128), 80, 50);
 
                 let mut commands_and_paths
-                    = commands.into_iter()
+                    = commands.iter()
                         .map(|command| (&command.primary_path, command))
                         .collect::<Vec<_>>();
 
                 commands_and_paths.sort_by(|a, b| {
-                    a.0.cmp(&b.0)
+                    a.0.cmp(b.0)
                 });
 
                 for command in commands {
-                    if let Some(documentation) = &command.documentation {
-                        output_string.push_str("\n  \x1b[1m");
-                        output_string.push_str(&command.usage().oneliner(&env.info));
-                        output_string.push_str("\x1b[0m\n      ");
+                    output_string.push_str("\n  \x1b[1m");
+                    output_string.push_str(&command.usage().oneliner(&env.info));
+                    output_string.push_str("\x1b[0m\n");
+
+                    let description = command.documentation.iter()
+                        .map(|documentation| documentation.description.clone())
+                        .chain(Some(format_lifecycle_tags(&command.lifecycle)))
+                        .filter(|part| !part.is_empty())
+                        .collect::<Vec<_>>()
+                        .join(" ");
+
+                    if !description.is_empty() {
+                        output_string.push_str("      ");
                         write_color(&mut output_string, (128, 128, 128));
-                        output_string.push_str(&documentation.description);
+                        output_string.push_str(&description);
                         output_string.push_str("\x1b[0m\n");
                     }
                 }
@@ -237,11 +595,16 @@ pub trait Cli {
 
 impl<S> Cli for S where S: CliEnums + CommandProvider, S::Enum: CommandExecutor {
     fn run(env: Environment) -> std::process::ExitCode {
-        let builder = S::build_cli()
+        let env = match env.expand_response_files() {
+            Ok(env) => env,
+            Err(err) => return report_response_file_error::<S>(&env, err),
+        };
+
+        let builder = build_cli::<S>(&env)
             .unwrap();
 
         let parse_result
-            = S::parse_args(&builder, &env);
+            = parse_args::<S>(&builder, &env);
 
         match parse_result {
             Ok(SelectionResult::Builtin(builtin)) => {
@@ -249,7 +612,17 @@ impl<S> Cli for S where S: CliEnums + CommandProvider, S::Enum: CommandExecutor
                     .unwrap_or_else(|err| report_error::<S>(&env, err))
             },
 
-            Ok(SelectionResult::Command(command_spec, _, partial_command)) => {
+            Ok(SelectionResult::Command(command_spec, state, selected)) => {
+                report_deprecations(&env, command_spec, &state);
+
+                let partial_command = match selected {
+                    Selected::Program(partial_command)
+                        => partial_command,
+
+                    Selected::Dynamic(index)
+                        => return report_command_result::<S>(&env, command_spec, env.dynamic_commands.execute(index, &env, command_spec, &state)),
+                };
+
                 let full_command = match <S as CliEnums>::Enum::try_from(partial_command) {
                     Ok(full_command)
                         => full_command,
@@ -261,14 +634,14 @@ impl<S> Cli for S where S: CliEnums + CommandProvider, S::Enum: CommandExecutor
                 let command_result
                     = full_command.execute(&env);
 
-                if let Some(error_message) = &command_result.error_message {
-                    return report_error::<S>(&env, clipanion_core::Error::CommandError(command_spec, error_message.to_string().into()));
-                }
-
-                command_result.exit_code
+                report_command_result::<S>(&env, command_spec, command_result)
             },
 
             Err(err) => {
+                if let Some(env) = autocorrect_env(&builder, &env, &err) {
+                    return <Self as Cli>::run(env);
+                }
+
                 report_error::<S>(&env, err)
             },
         }
@@ -286,11 +659,16 @@ pub trait CliAsync {
 
 impl<S> CliAsync for S where S: CliEnums + CommandProvider, S::Enum: CommandExecutorAsync {
     async fn run(env: Environment) -> std::process::ExitCode {
-        let builder = S::build_cli()
+        let env = match env.expand_response_files() {
+            Ok(env) => env,
+            Err(err) => return report_response_file_error::<S>(&env, err),
+        };
+
+        let builder = build_cli::<S>(&env)
             .unwrap();
 
         let parse_result
-            = S::parse_args(&builder, &env);
+            = parse_args::<S>(&builder, &env);
 
         match parse_result {
             Ok(SelectionResult::Builtin(builtin)) => {
@@ -298,7 +676,17 @@ impl<S> CliAsync for S where S: CliEnums + CommandProvider, S::Enum: CommandExec
                     .unwrap_or_else(|err| report_error::<S>(&env, err))
             },
 
-            Ok(SelectionResult::Command(command_spec, _, partial_command)) => {
+            Ok(SelectionResult::Command(command_spec, state, selected)) => {
+                report_deprecations(&env, command_spec, &state);
+
+                let partial_command = match selected {
+                    Selected::Program(partial_command)
+                        => partial_command,
+
+                    Selected::Dynamic(index)
+                        => return report_command_result::<S>(&env, command_spec, env.dynamic_commands.execute(index, &env, command_spec, &state)),
+                };
+
                 let full_command = match <S as CliEnums>::Enum::try_from(partial_command) {
                     Ok(full_command)
                         => full_command,
@@ -310,14 +698,14 @@ impl<S> CliAsync for S where S: CliEnums + CommandProvider, S::Enum: CommandExec
                 let command_result
                     = full_command.execute(&env).await;
 
-                if let Some(error_message) = &command_result.error_message {
-                    return report_error::<S>(&env, clipanion_core::Error::CommandError(command_spec, error_message.to_string().into()));
-                }
-
-                command_result.exit_code
+                report_command_result::<S>(&env, command_spec, command_result)
             },
 
             Err(err) => {
+                if let Some(env) = autocorrect_env(&builder, &env, &err) {
+                    return Box::pin(<Self as CliAsync>::run(env)).await;
+                }
+
                 report_error::<S>(&env, err)
             },
         }
@@ -0,0 +1,528 @@
+use std::{collections::{BTreeMap, BTreeSet}, fmt::Write, str::FromStr};
+
+use clipanion_core::{BuildError, CommandSpec, Component, OptionSpec, PositionalSpec};
+
+use crate::{advanced::Environment, details::{CommandProvider, CommandResult}};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+    PowerShell,
+}
+
+impl Shell {
+    pub const ALL: [Shell; 4] = [Shell::Bash, Shell::Zsh, Shell::Fish, Shell::PowerShell];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Shell::Bash => "bash",
+            Shell::Zsh => "zsh",
+            Shell::Fish => "fish",
+            Shell::PowerShell => "powershell",
+        }
+    }
+}
+
+impl std::fmt::Display for Shell {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for Shell {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "bash" => Ok(Shell::Bash),
+            "zsh" => Ok(Shell::Zsh),
+            "fish" => Ok(Shell::Fish),
+            "powershell" | "pwsh" => Ok(Shell::PowerShell),
+            _ => Err(format!("Unsupported shell: {} (expected one of bash, zsh, fish, powershell)", s)),
+        }
+    }
+}
+
+/**
+ * Everything the shell needs to know about a given command path prefix: the
+ * keywords that can follow it, and the options and positional choices of the
+ * commands it selects.
+ */
+#[derive(Default)]
+struct PathNode<'cmds> {
+    keywords: BTreeMap<String, Option<&'cmds str>>,
+    options: BTreeMap<&'cmds str, &'cmds OptionSpec>,
+    positional_choices: BTreeSet<&'cmds str>,
+}
+
+impl<'cmds> PathNode<'cmds> {
+    fn option_names(&self) -> Vec<&'cmds str> {
+        self.options.values()
+            .flat_map(|option| option.all_names())
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect()
+    }
+
+    fn value_option_names(&self) -> Vec<&'cmds str> {
+        self.options.values()
+            .filter(|option| takes_value(option))
+            .flat_map(|option| option.all_names())
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect()
+    }
+
+    fn positional_choices(&self) -> Vec<&'cmds str> {
+        self.positional_choices.iter()
+            .copied()
+            .collect()
+    }
+
+    /**
+     * The names of the options restricted to a fixed set of values, along
+     * with these values.
+     */
+    fn option_choices(&self) -> Vec<(Vec<&'cmds str>, &'cmds [String])> {
+        self.options.values()
+            .filter(|option| takes_value(option) && !option.choices.is_empty())
+            .map(|option| (option.all_names().collect(), option.choices.as_slice()))
+            .collect()
+    }
+}
+
+/**
+ * Options that may be used without value (booleans, counters, or options
+ * whose values are all optional) never prevent keywords from being offered.
+ */
+fn takes_value(option: &OptionSpec) -> bool {
+    option.min_len > 0
+}
+
+fn build_path_tree<'cmds>(commands: &[&'cmds CommandSpec]) -> BTreeMap<Vec<&'cmds str>, PathNode<'cmds>> {
+    let mut tree
+        = BTreeMap::<Vec<&'cmds str>, PathNode<'cmds>>::new();
+
+    tree.entry(vec![]).or_default();
+
+    for command in commands.iter().filter(|command| !command.is_hidden) {
+        let description = command.documentation.as_ref()
+            .map(|documentation| documentation.description.as_str());
+
+        let paths
+            = std::iter::once(&command.primary_path)
+                .chain(command.aliases.iter());
+
+        for path in paths {
+            let path = path.iter()
+                .map(|segment| segment.as_str())
+                .collect::<Vec<_>>();
+
+            for (i, segment) in path.iter().enumerate() {
+                let keyword_description
+                    = if i == path.len() - 1 {description} else {None};
+
+                tree.entry(path[..i].to_vec()).or_default()
+                    .keywords.entry(segment.to_string())
+                    .and_modify(|existing| *existing = existing.or(keyword_description))
+                    .or_insert(keyword_description);
+            }
+
+            let node
+                = tree.entry(path).or_default();
+
+            for component in &command.components {
+                match component {
+                    Component::Option(option) if !option.is_hidden => {
+                        node.options.insert(option.primary_name.as_str(), option);
+                    },
+
+                    Component::Positional(PositionalSpec::Dynamic {choices, ..}) => {
+                        node.positional_choices.extend(choices.iter().map(|choice| choice.as_str()));
+                    },
+
+                    _ => {},
+                }
+            }
+        }
+    }
+
+    tree
+}
+
+fn sanitize_identifier(binary_name: &str) -> String {
+    binary_name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() {c} else {'_'})
+        .collect()
+}
+
+fn quote_posix(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+fn quote_fish(value: &str) -> String {
+    format!("'{}'", value.replace('\\', "\\\\").replace('\'', "\\'"))
+}
+
+fn quote_powershell(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+fn join_quoted(values: impl IntoIterator<Item = impl AsRef<str>>, quote: fn(&str) -> String, separator: &str) -> String {
+    values.into_iter()
+        .map(|value| quote(value.as_ref()))
+        .collect::<Vec<_>>()
+        .join(separator)
+}
+
+fn generate_bash(binary_name: &str, tree: &BTreeMap<Vec<&str>, PathNode>) -> String {
+    let function_name
+        = format!("_{}", sanitize_identifier(binary_name));
+
+    let prefixes = tree.keys()
+        .filter(|path| !path.is_empty())
+        .map(|path| path.join(" "))
+        .collect::<Vec<_>>();
+
+    let mut out = String::new();
+
+    writeln!(out, "{}() {{", function_name).unwrap();
+    writeln!(out, "    local cur=\"${{COMP_WORDS[COMP_CWORD]}}\"").unwrap();
+    writeln!(out, "    local prev=\"${{COMP_WORDS[COMP_CWORD-1]}}\"").unwrap();
+    writeln!(out, "    local cmd_path=\"\" candidate i").unwrap();
+    writeln!(out, "    local keywords=\"\" options=\"\" value_options=\"\" positionals=\"\" value_choices=\"\"").unwrap();
+
+    if !prefixes.is_empty() {
+        writeln!(out).unwrap();
+        writeln!(out, "    for ((i = 1; i < COMP_CWORD; i++)); do").unwrap();
+        writeln!(out, "        candidate=\"${{cmd_path:+$cmd_path }}${{COMP_WORDS[i]}}\"").unwrap();
+        writeln!(out, "        case \"$candidate\" in").unwrap();
+        writeln!(out, "            {}) cmd_path=\"$candidate\" ;;", join_quoted(&prefixes, quote_posix, "|")).unwrap();
+        writeln!(out, "        esac").unwrap();
+        writeln!(out, "    done").unwrap();
+    }
+
+    writeln!(out).unwrap();
+    writeln!(out, "    case \"$cmd_path\" in").unwrap();
+
+    for (path, node) in tree {
+        writeln!(out, "        {})", quote_posix(&path.join(" "))).unwrap();
+        writeln!(out, "            keywords={}", quote_posix(&node.keywords.keys().cloned().collect::<Vec<_>>().join(" "))).unwrap();
+        writeln!(out, "            options={}", quote_posix(&node.option_names().join(" "))).unwrap();
+        writeln!(out, "            value_options={}", quote_posix(&node.value_option_names().join(" "))).unwrap();
+        writeln!(out, "            positionals={}", quote_posix(&node.positional_choices().join(" "))).unwrap();
+
+        let option_choices
+            = node.option_choices();
+
+        if !option_choices.is_empty() {
+            writeln!(out, "            case \"$prev\" in").unwrap();
+
+            for (names, choices) in option_choices {
+                writeln!(out, "                {}) value_choices={} ;;", join_quoted(names, quote_posix, "|"), quote_posix(&choices.join(" "))).unwrap();
+            }
+
+            writeln!(out, "            esac").unwrap();
+        }
+
+        writeln!(out, "            ;;").unwrap();
+    }
+
+    writeln!(out, "    esac").unwrap();
+    writeln!(out).unwrap();
+    writeln!(out, "    if [[ -n \"$value_choices\" ]]; then").unwrap();
+    writeln!(out, "        COMPREPLY=($(compgen -W \"$value_choices\" -- \"$cur\"))").unwrap();
+    writeln!(out, "        return").unwrap();
+    writeln!(out, "    fi").unwrap();
+    writeln!(out).unwrap();
+    writeln!(out, "    if [[ \" $value_options \" == *\" $prev \"* ]]; then").unwrap();
+    writeln!(out, "        COMPREPLY=($(compgen -f -- \"$cur\"))").unwrap();
+    writeln!(out, "        return").unwrap();
+    writeln!(out, "    fi").unwrap();
+    writeln!(out).unwrap();
+    writeln!(out, "    COMPREPLY=($(compgen -W \"$keywords $positionals $options\" -- \"$cur\"))").unwrap();
+    writeln!(out, "}}").unwrap();
+    writeln!(out).unwrap();
+    writeln!(out, "complete -F {} {}", function_name, quote_posix(binary_name)).unwrap();
+
+    out
+}
+
+fn generate_zsh(binary_name: &str, tree: &BTreeMap<Vec<&str>, PathNode>) -> String {
+    let function_name
+        = format!("_{}", sanitize_identifier(binary_name));
+
+    let prefixes = tree.keys()
+        .filter(|path| !path.is_empty())
+        .map(|path| path.join(" "))
+        .collect::<Vec<_>>();
+
+    let mut out = String::new();
+
+    writeln!(out, "#compdef {}", binary_name).unwrap();
+    writeln!(out).unwrap();
+    writeln!(out, "{}() {{", function_name).unwrap();
+    writeln!(out, "    local cmd_path=\"\" candidate i").unwrap();
+    writeln!(out, "    local -a keywords options value_options positionals value_choices").unwrap();
+
+    if !prefixes.is_empty() {
+        writeln!(out).unwrap();
+        writeln!(out, "    for ((i = 2; i < CURRENT; i++)); do").unwrap();
+        writeln!(out, "        candidate=\"${{cmd_path:+$cmd_path }}${{words[i]}}\"").unwrap();
+        writeln!(out, "        case \"$candidate\" in").unwrap();
+        writeln!(out, "            ({}) cmd_path=\"$candidate\" ;;", join_quoted(&prefixes, quote_posix, "|")).unwrap();
+        writeln!(out, "        esac").unwrap();
+        writeln!(out, "    done").unwrap();
+    }
+
+    writeln!(out).unwrap();
+    writeln!(out, "    case \"$cmd_path\" in").unwrap();
+
+    for (path, node) in tree {
+        writeln!(out, "        ({})", quote_posix(&path.join(" "))).unwrap();
+        writeln!(out, "            keywords=({})", join_quoted(node.keywords.keys(), quote_posix, " ")).unwrap();
+        writeln!(out, "            options=({})", join_quoted(node.option_names(), quote_posix, " ")).unwrap();
+        writeln!(out, "            value_options=({})", join_quoted(node.value_option_names(), quote_posix, " ")).unwrap();
+        writeln!(out, "            positionals=({})", join_quoted(node.positional_choices(), quote_posix, " ")).unwrap();
+
+        let option_choices
+            = node.option_choices();
+
+        if !option_choices.is_empty() {
+            writeln!(out, "            case \"${{words[CURRENT-1]}}\" in").unwrap();
+
+            for (names, choices) in option_choices {
+                writeln!(out, "                ({}) value_choices=({}) ;;", join_quoted(names, quote_posix, "|"), join_quoted(choices, quote_posix, " ")).unwrap();
+            }
+
+            writeln!(out, "            esac").unwrap();
+        }
+
+        writeln!(out, "            ;;").unwrap();
+    }
+
+    writeln!(out, "    esac").unwrap();
+    writeln!(out).unwrap();
+    writeln!(out, "    if (( ${{#value_choices}} )); then").unwrap();
+    writeln!(out, "        compadd -- \"${{value_choices[@]}}\"").unwrap();
+    writeln!(out, "        return").unwrap();
+    writeln!(out, "    fi").unwrap();
+    writeln!(out).unwrap();
+    writeln!(out, "    if (( ${{value_options[(Ie)${{words[CURRENT-1]}}]}} )); then").unwrap();
+    writeln!(out, "        _files").unwrap();
+    writeln!(out, "        return").unwrap();
+    writeln!(out, "    fi").unwrap();
+    writeln!(out).unwrap();
+    writeln!(out, "    compadd -- \"${{keywords[@]}}\" \"${{positionals[@]}}\" \"${{options[@]}}\"").unwrap();
+    writeln!(out, "}}").unwrap();
+    writeln!(out).unwrap();
+    writeln!(out, "if [ \"$funcstack[1]\" = \"{}\" ]; then", function_name).unwrap();
+    writeln!(out, "    {} \"$@\"", function_name).unwrap();
+    writeln!(out, "else").unwrap();
+    writeln!(out, "    compdef {} {}", function_name, quote_posix(binary_name)).unwrap();
+    writeln!(out, "fi").unwrap();
+
+    out
+}
+
+fn generate_fish(binary_name: &str, tree: &BTreeMap<Vec<&str>, PathNode>) -> String {
+    let function_name
+        = format!("__{}", sanitize_identifier(binary_name));
+
+    let prefixes = tree.keys()
+        .filter(|path| !path.is_empty())
+        .map(|path| path.join(" "))
+        .collect::<Vec<_>>();
+
+    let binary_name_lit
+        = quote_fish(binary_name);
+
+    let mut out = String::new();
+
+    writeln!(out, "function {}_path", function_name).unwrap();
+    writeln!(out, "    set -l tokens (commandline -opc)").unwrap();
+    writeln!(out, "    set -l cmd_path").unwrap();
+
+    if !prefixes.is_empty() {
+        writeln!(out, "    for token in $tokens[2..-1]").unwrap();
+        writeln!(out, "        switch (string join ' ' $cmd_path $token)").unwrap();
+        writeln!(out, "            case {}", join_quoted(&prefixes, quote_fish, " ")).unwrap();
+        writeln!(out, "                set cmd_path $cmd_path $token").unwrap();
+        writeln!(out, "        end").unwrap();
+        writeln!(out, "    end").unwrap();
+    }
+
+    writeln!(out, "    string join ' ' $cmd_path").unwrap();
+    writeln!(out, "end").unwrap();
+    writeln!(out).unwrap();
+    writeln!(out, "function {}_path_is", function_name).unwrap();
+    writeln!(out, "    set -l current ({}_path)", function_name).unwrap();
+    writeln!(out, "    test \"$current\" = \"$argv[1]\"").unwrap();
+    writeln!(out, "end").unwrap();
+    writeln!(out).unwrap();
+    writeln!(out, "complete -c {} -f", binary_name_lit).unwrap();
+
+    for (path, node) in tree {
+        let condition
+            = quote_fish(&format!("{}_path_is {}", function_name, quote_fish(&path.join(" "))));
+
+        for (keyword, description) in &node.keywords {
+            write!(out, "complete -c {} -n {} -a {}", binary_name_lit, condition, quote_fish(keyword)).unwrap();
+
+            if let Some(description) = description {
+                write!(out, " -d {}", quote_fish(description)).unwrap();
+            }
+
+            writeln!(out).unwrap();
+        }
+
+        if !node.positional_choices.is_empty() {
+            writeln!(out, "complete -c {} -n {} -a {}", binary_name_lit, condition, quote_fish(&node.positional_choices().join(" "))).unwrap();
+        }
+
+        for option in node.options.values() {
+            write!(out, "complete -c {} -n {}", binary_name_lit, condition).unwrap();
+
+            for name in option.all_names() {
+                if let Some(long_name) = name.strip_prefix("--") {
+                    write!(out, " -l {}", quote_fish(long_name)).unwrap();
+                } else if let Some(short_name) = name.strip_prefix('-').filter(|name| name.chars().count() == 1) {
+                    write!(out, " -s {}", quote_fish(short_name)).unwrap();
+                } else {
+                    write!(out, " -o {}", quote_fish(name.trim_start_matches('-'))).unwrap();
+                }
+            }
+
+            if takes_value(option) && !option.choices.is_empty() {
+                write!(out, " -x -a {}", quote_fish(&option.choices.join(" "))).unwrap();
+            } else if takes_value(option) {
+                write!(out, " -r -F").unwrap();
+            }
+
+            if let Some(documentation) = &option.documentation {
+                write!(out, " -d {}", quote_fish(&documentation.description)).unwrap();
+            }
+
+            writeln!(out).unwrap();
+        }
+    }
+
+    out
+}
+
+fn generate_powershell(binary_name: &str, tree: &BTreeMap<Vec<&str>, PathNode>) -> String {
+    let prefixes = tree.keys()
+        .filter(|path| !path.is_empty())
+        .map(|path| path.join(" "))
+        .collect::<Vec<_>>();
+
+    let mut out = String::new();
+
+    writeln!(out, "Register-ArgumentCompleter -Native -CommandName {} -ScriptBlock {{", quote_powershell(binary_name)).unwrap();
+    writeln!(out, "    param($wordToComplete, $commandAst, $cursorPosition)").unwrap();
+    writeln!(out).unwrap();
+    writeln!(out, "    $words = @($commandAst.CommandElements | Where-Object {{ $_.Extent.EndOffset -lt $cursorPosition }} | ForEach-Object {{ $_.ToString() }})").unwrap();
+    writeln!(out, "    $cmdPath = ''").unwrap();
+    writeln!(out, "    $prefixes = @({})", join_quoted(&prefixes, quote_powershell, ", ")).unwrap();
+    writeln!(out).unwrap();
+    writeln!(out, "    foreach ($word in ($words | Select-Object -Skip 1)) {{").unwrap();
+    writeln!(out, "        $candidate = if ($cmdPath) {{ \"$cmdPath $word\" }} else {{ $word }}").unwrap();
+    writeln!(out, "        if ($prefixes -contains $candidate) {{ $cmdPath = $candidate }}").unwrap();
+    writeln!(out, "    }}").unwrap();
+    writeln!(out).unwrap();
+    writeln!(out, "    $keywords = @()").unwrap();
+    writeln!(out, "    $options = @()").unwrap();
+    writeln!(out, "    $valueOptions = @()").unwrap();
+    writeln!(out, "    $positionals = @()").unwrap();
+    writeln!(out, "    $valueChoices = @()").unwrap();
+    writeln!(out).unwrap();
+    writeln!(out, "    switch ($cmdPath) {{").unwrap();
+
+    for (path, node) in tree {
+        writeln!(out, "        {} {{", quote_powershell(&path.join(" "))).unwrap();
+        writeln!(out, "            $keywords = @({})", join_quoted(node.keywords.keys(), quote_powershell, ", ")).unwrap();
+        writeln!(out, "            $options = @({})", join_quoted(node.option_names(), quote_powershell, ", ")).unwrap();
+        writeln!(out, "            $valueOptions = @({})", join_quoted(node.value_option_names(), quote_powershell, ", ")).unwrap();
+        writeln!(out, "            $positionals = @({})", join_quoted(node.positional_choices(), quote_powershell, ", ")).unwrap();
+
+        let option_choices
+            = node.option_choices();
+
+        if !option_choices.is_empty() {
+            writeln!(out, "            switch -CaseSensitive ($words[-1]) {{").unwrap();
+
+            for (names, choices) in option_choices {
+                for name in names {
+                    writeln!(out, "                {} {{ $valueChoices = @({}) }}", quote_powershell(name), join_quoted(choices, quote_powershell, ", ")).unwrap();
+                }
+            }
+
+            writeln!(out, "            }}").unwrap();
+        }
+
+        writeln!(out, "        }}").unwrap();
+    }
+
+    writeln!(out, "    }}").unwrap();
+    writeln!(out).unwrap();
+    writeln!(out, "    if ($valueChoices.Count -gt 0) {{").unwrap();
+    writeln!(out, "        $valueChoices | Where-Object {{ $_ -like \"$wordToComplete*\" }} | ForEach-Object {{").unwrap();
+    writeln!(out, "            [System.Management.Automation.CompletionResult]::new($_, $_, 'ParameterValue', $_)").unwrap();
+    writeln!(out, "        }}").unwrap();
+    writeln!(out, "        return").unwrap();
+    writeln!(out, "    }}").unwrap();
+    writeln!(out).unwrap();
+    writeln!(out, "    if ($words.Count -gt 0 -and $valueOptions -contains $words[-1]) {{").unwrap();
+    writeln!(out, "        return").unwrap();
+    writeln!(out, "    }}").unwrap();
+    writeln!(out).unwrap();
+    writeln!(out, "    @($keywords + $positionals + $options) | Where-Object {{ $_ -like \"$wordToComplete*\" }} | ForEach-Object {{").unwrap();
+    writeln!(out, "        [System.Management.Automation.CompletionResult]::new($_, $_, 'ParameterValue', $_)").unwrap();
+    writeln!(out, "    }}").unwrap();
+    writeln!(out, "}}").unwrap();
+
+    out
+}
+
+/**
+ * Generate a static completion script for the given shell. The script only
+ * depends on the command specs, so it can be generated at build time and
+ * shipped alongside the binary.
+ */
+pub fn generate_completion_script(shell: Shell, binary_name: &str, commands: &[&CommandSpec]) -> String {
+    let tree
+        = build_path_tree(commands);
+
+    match shell {
+        Shell::Bash => generate_bash(binary_name, &tree),
+        Shell::Zsh => generate_zsh(binary_name, &tree),
+        Shell::Fish => generate_fish(binary_name, &tree),
+        Shell::PowerShell => generate_powershell(binary_name, &tree),
+    }
+}
+
+pub fn completion_script_for<S: CommandProvider>(shell: Shell, binary_name: &str) -> Result<String, BuildError> {
+    let commands
+        = S::registered_commands()?;
+
+    Ok(generate_completion_script(shell, binary_name, &commands))
+}
+
+/**
+ * Implementation of the `completion <shell>` command registered by
+ * `#[cli::program(completion)]`.
+ */
+pub fn execute_completion_command<S: CommandProvider>(environment: &Environment, shell: Shell) -> CommandResult {
+    match completion_script_for::<S>(shell, &environment.info.binary_name) {
+        Ok(script) => {
+            print!("{}", script);
+            ().into()
+        },
+
+        Err(err) => {
+            clipanion_core::CommandError::Custom(err.to_string()).into()
+        },
+    }
+}
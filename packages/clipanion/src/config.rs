@@ -0,0 +1,286 @@
+use std::collections::BTreeMap;
+#[cfg(feature = "config")]
+use std::path::{Path, PathBuf};
+
+use clipanion_core::{CommandSpec, OptionSpec};
+
+/**
+ * Option values read from configuration files. Top-level keys apply to every
+ * command accepting an option of that name, while nested tables are matched
+ * against the command paths:
+ *
+ * ```toml
+ * verbose = true
+ *
+ * [config.get]
+ * json = true
+ * ```
+ *
+ * Keys are the long option names without their leading dashes; dashes and
+ * underscores are interchangeable. Arrays are used for options accepting
+ * multiple values.
+ */
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ConfigTable {
+    pub values: BTreeMap<String, Vec<String>>,
+    pub tables: BTreeMap<String, ConfigTable>,
+}
+
+impl ConfigTable {
+    /**
+     * Merge the other table into this one; the other table's values take
+     * precedence.
+     */
+    pub fn merge(&mut self, other: ConfigTable) {
+        self.values.extend(other.values);
+
+        for (key, table) in other.tables {
+            self.tables.entry(key)
+                .or_default()
+                .merge(table);
+        }
+    }
+
+    fn get(&self, option: &OptionSpec) -> Option<&Vec<String>> {
+        option.all_names()
+            .filter_map(|name| name.strip_prefix("--"))
+            .find_map(|name| self.values.get(name).or_else(|| self.values.get(&name.replace('-', "_"))))
+    }
+
+    /**
+     * Find the value of the given option, favoring the tables matching the
+     * longest prefix of the command path.
+     */
+    pub fn lookup(&self, command_spec: &CommandSpec, option: &OptionSpec) -> Option<&Vec<String>> {
+        let mut table
+            = self;
+        let mut result
+            = self.get(option);
+
+        for segment in &command_spec.primary_path {
+            let Some(next_table) = table.tables.get(segment) else {
+                break;
+            };
+
+            table = next_table;
+            result = table.get(option).or(result);
+        }
+
+        result
+    }
+}
+
+#[cfg(feature = "config")]
+#[derive(thiserror::Error, Debug)]
+pub enum ConfigError {
+    #[error("Failed to read {0}: {1}")]
+    Io(PathBuf, std::io::Error),
+
+    #[error("Failed to parse {0}: {1}")]
+    Toml(PathBuf, toml::de::Error),
+
+    #[error("Failed to parse {0}: {1}")]
+    Json(PathBuf, serde_json::Error),
+
+    #[error("Expected {0} to contain a table")]
+    NotATable(PathBuf),
+}
+
+#[cfg(feature = "config")]
+fn scalar_to_string(value: &serde_json::Value) -> Option<String> {
+    match value {
+        serde_json::Value::String(value) => Some(value.clone()),
+        serde_json::Value::Bool(value) => Some(value.to_string()),
+        serde_json::Value::Number(value) => Some(value.to_string()),
+        _ => None,
+    }
+}
+
+#[cfg(feature = "config")]
+fn table_from_json(object: &serde_json::Map<String, serde_json::Value>) -> ConfigTable {
+    let mut table
+        = ConfigTable::default();
+
+    for (key, value) in object {
+        match value {
+            serde_json::Value::Object(object) => {
+                table.tables.insert(key.clone(), table_from_json(object));
+            },
+
+            serde_json::Value::Array(values) => {
+                table.values.insert(key.clone(), values.iter().filter_map(scalar_to_string).collect());
+            },
+
+            value => {
+                table.values.extend(scalar_to_string(value).map(|value| (key.clone(), vec![value])));
+            },
+        }
+    }
+
+    table
+}
+
+#[cfg(feature = "config")]
+fn toml_scalar_to_string(value: &toml::Value) -> Option<String> {
+    match value {
+        toml::Value::String(value) => Some(value.clone()),
+        toml::Value::Integer(value) => Some(value.to_string()),
+        toml::Value::Float(value) => Some(value.to_string()),
+        toml::Value::Boolean(value) => Some(value.to_string()),
+        toml::Value::Datetime(value) => Some(value.to_string()),
+        _ => None,
+    }
+}
+
+#[cfg(feature = "config")]
+fn table_from_toml(input: &toml::Table) -> ConfigTable {
+    let mut table
+        = ConfigTable::default();
+
+    for (key, value) in input {
+        match value {
+            toml::Value::Table(input) => {
+                table.tables.insert(key.clone(), table_from_toml(input));
+            },
+
+            toml::Value::Array(values) => {
+                table.values.insert(key.clone(), values.iter().filter_map(toml_scalar_to_string).collect());
+            },
+
+            value => {
+                table.values.extend(toml_scalar_to_string(value).map(|value| (key.clone(), vec![value])));
+            },
+        }
+    }
+
+    table
+}
+
+/**
+ * Parse a configuration file; the format is selected from the extension,
+ * with anything but `.json` being parsed as TOML.
+ */
+#[cfg(feature = "config")]
+pub fn parse_config_file(path: &Path, content: &str) -> Result<ConfigTable, ConfigError> {
+    match path.extension().and_then(|extension| extension.to_str()) {
+        Some("json") => {
+            let value: serde_json::Value = serde_json::from_str(content)
+                .map_err(|err| ConfigError::Json(path.to_path_buf(), err))?;
+
+            let serde_json::Value::Object(object) = value else {
+                return Err(ConfigError::NotATable(path.to_path_buf()));
+            };
+
+            Ok(table_from_json(&object))
+        },
+
+        _ => {
+            let table = content.parse::<toml::Table>()
+                .map_err(|err| ConfigError::Toml(path.to_path_buf(), err))?;
+
+            Ok(table_from_toml(&table))
+        },
+    }
+}
+
+/**
+ * The locations configuration files are read from, from lowest to highest
+ * precedence:
+ *
+ * - `$XDG_CONFIG_HOME/<name>/config.{toml,json}` (`~/.config` by default)
+ * - `.<name>.{toml,json}` in every directory from the root down to the
+ *   current directory
+ *
+ * Every path is resolved relative to `root`, which is `/` unless overridden
+ * (typically to point at a temporary directory in tests).
+ */
+#[cfg(feature = "config")]
+#[derive(Clone, Debug)]
+pub struct ConfigSources {
+    pub name: String,
+    pub root: PathBuf,
+    pub home: Option<PathBuf>,
+    pub xdg_config_home: Option<PathBuf>,
+    pub cwd: PathBuf,
+}
+
+#[cfg(feature = "config")]
+impl ConfigSources {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            root: PathBuf::from("/"),
+            home: std::env::var_os("HOME").map(PathBuf::from),
+            xdg_config_home: std::env::var_os("XDG_CONFIG_HOME").map(PathBuf::from),
+            cwd: std::env::current_dir().unwrap_or_default(),
+        }
+    }
+
+    pub fn with_root(mut self, root: PathBuf) -> Self {
+        self.root = root;
+        self
+    }
+
+    pub fn with_home(mut self, home: Option<PathBuf>) -> Self {
+        self.home = home;
+        self
+    }
+
+    pub fn with_xdg_config_home(mut self, xdg_config_home: Option<PathBuf>) -> Self {
+        self.xdg_config_home = xdg_config_home;
+        self
+    }
+
+    pub fn with_cwd(mut self, cwd: PathBuf) -> Self {
+        self.cwd = cwd;
+        self
+    }
+
+    fn resolve(&self, path: &Path) -> PathBuf {
+        self.root.join(path.strip_prefix("/").unwrap_or(path))
+    }
+
+    pub fn paths(&self) -> Vec<PathBuf> {
+        let mut paths
+            = vec![];
+
+        let config_home = self.xdg_config_home.clone()
+            .or_else(|| self.home.as_ref().map(|home| home.join(".config")));
+
+        if let Some(config_home) = config_home {
+            paths.push(config_home.join(&self.name).join("config.toml"));
+            paths.push(config_home.join(&self.name).join("config.json"));
+        }
+
+        let mut directories
+            = self.cwd.ancestors().collect::<Vec<_>>();
+
+        directories.reverse();
+
+        for directory in directories {
+            paths.push(directory.join(format!(".{}.toml", self.name)));
+            paths.push(directory.join(format!(".{}.json", self.name)));
+        }
+
+        paths.into_iter()
+            .map(|path| self.resolve(&path))
+            .collect()
+    }
+
+    pub fn load(&self) -> Result<ConfigTable, ConfigError> {
+        let mut config
+            = ConfigTable::default();
+
+        for path in self.paths() {
+            let content = match std::fs::read_to_string(&path) {
+                Ok(content) => content,
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(err) => return Err(ConfigError::Io(path, err)),
+            };
+
+            config.merge(parse_config_file(&path, &content)?);
+        }
+
+        Ok(config)
+    }
+}
@@ -1,13 +1,18 @@
-use std::{convert::Infallible, fmt::Display, future::Future};
+use std::{collections::BTreeSet, convert::Infallible, fmt::Display, future::Future, marker::PhantomData, str::FromStr};
 
-use clipanion_core::{CommandError, CommandSpec, SelectionResult};
+use clipanion_core::{CliBuilder, CommandError, CommandSpec, Constraint, Info, OptionSpec, SelectionResult, State, StderrTraceSink, TraceSink, UserArg};
+use num_traits::{NumCast, ToPrimitive};
 
 use crate::advanced::Environment;
 
 pub fn handle_parse_error<E: Display + 'static>(err: E) -> CommandError {
-    match std::any::TypeId::of::<E>() == std::any::TypeId::of::<Infallible>() {
-        true => unreachable!("Infallible error occurred"),
-        false => CommandError::Custom(err.to_string()),
+    if std::any::TypeId::of::<E>() == std::any::TypeId::of::<Infallible>() {
+        unreachable!("Infallible error occurred");
+    }
+
+    match (&err as &dyn std::any::Any).downcast_ref::<CommandError>() {
+        Some(command_error) => command_error.clone(),
+        None => CommandError::Custom(err.to_string()),
     }
 }
 
@@ -118,13 +123,101 @@ pub trait CliEnums {
 pub trait CommandProvider {
     type Command;
 
+    /**
+     * Number of commands returned by `registered_commands`, including the
+     * ones coming from mounted programs.
+     */
+    const COMMAND_COUNT: usize;
+
     fn command_usage(command_index: usize, opts: clipanion_core::CommandUsageOptions) -> Result<clipanion_core::CommandUsageResult, clipanion_core::BuildError>;
 
     fn registered_commands() -> Result<Vec<&'static CommandSpec>, clipanion_core::BuildError>;
+    fn hydrate_command(command_index: usize, environment: &Environment, state: &State) -> Result<Self::PartialEnum, CommandError> where Self: Sized + CliEnums;
     fn parse_args<'args>(builder: &clipanion_core::CliBuilder<'static>, environment: &'args Environment) -> Result<SelectionResult<'static, 'args, Self::PartialEnum>, clipanion_core::Error<'args>> where Self: Sized + CliEnums;
     fn build_cli() -> Result<clipanion_core::CliBuilder<'static>, clipanion_core::BuildError>;
 }
 
+/**
+ * Run the parser over the environment arguments, fill the missing options
+ * from the environment variables and configuration, then hydrate the
+ * selected command with the provided function. Setting `CLIPANION_DEBUG`
+ * prints the parse trace to stderr.
+ */
+pub fn parse_args_with<'cmds, 'args, T, F>(builder: &CliBuilder<'cmds>, environment: &'args Environment, hydrate: F) -> Result<SelectionResult<'cmds, 'args, T>, clipanion_core::Error<'cmds>> where F: Fn(&State<'args>) -> Result<T, CommandError> {
+    match environment.env_vars.contains_key("CLIPANION_DEBUG") {
+        true => parse_args_with_trace(builder, environment, Some(&mut StderrTraceSink), hydrate),
+        false => parse_args_with_trace(builder, environment, None, hydrate),
+    }
+}
+
+/**
+ * Same as `parse_args_with`, but report the steps of the parse to the given
+ * sink, if any.
+ */
+pub fn parse_args_with_trace<'cmds, 'args, T, F>(builder: &CliBuilder<'cmds>, environment: &'args Environment, mut trace: Option<&mut dyn TraceSink>, hydrate: F) -> Result<SelectionResult<'cmds, 'args, T>, clipanion_core::Error<'cmds>> where F: Fn(&State<'args>) -> Result<T, CommandError> {
+    let argv
+        = environment.argv.iter()
+            .map(|s| s.as_str())
+            .collect::<Vec<_>>();
+
+    let mut selector = match trace.as_deref_mut() {
+        Some(trace) => builder.run_with_trace(&argv, trace)?,
+        None => builder.run(&argv)?,
+    };
+
+    selector.apply_env_vars(&environment.env_vars, environment.env_prefix.as_deref());
+
+    selector.apply_fallbacks(|command_spec, option| {
+        environment.config.lookup(command_spec, option)
+            .map(|values| values.iter().map(|value| value.as_str()).collect())
+    });
+
+    let result = match trace {
+        Some(trace) => selector.resolve_state_with_trace(hydrate, trace),
+        None => selector.resolve_state(hydrate),
+    };
+
+    let result = result.map_err(|err| match err {
+        clipanion_core::Error::CommandError(command_spec, err @ CommandError::UnknownOption(..)) => {
+            let index = builder.suggest(&argv).into_iter()
+                .find(|suggestion| suggestion.is_option() && std::ptr::eq(suggestion.command_spec, command_spec))
+                .map(|suggestion| suggestion.index);
+
+            clipanion_core::Error::CommandError(command_spec, locate_error(environment, index, err))
+        },
+
+        err => err,
+    })?;
+
+    if let SelectionResult::Command(command_spec, state, _) = &result {
+        check_experimental(environment, command_spec, state)
+            .map_err(|err| clipanion_core::Error::CommandError(command_spec, err))?;
+    }
+
+    Ok(result)
+}
+
+/**
+ * Clone the commands of a program mounted by `#[cli::mount]`, prefixing
+ * their paths (and aliases) with the mount path.
+ */
+pub fn mount_commands(mount_path: &[&str], commands: Vec<&CommandSpec>) -> Vec<CommandSpec> {
+    let prefix_path = |path: &Vec<String>| {
+        mount_path.iter()
+            .map(|segment| segment.to_string())
+            .chain(path.iter().cloned())
+            .collect()
+    };
+
+    commands.into_iter()
+        .map(|command| CommandSpec {
+            primary_path: prefix_path(&command.primary_path),
+            aliases: command.aliases.iter().map(prefix_path).collect(),
+            ..command.clone()
+        })
+        .collect()
+}
+
 pub trait CommandExecutor {
     fn execute(self, env: &Environment) -> crate::details::CommandResult;
 }
@@ -132,3 +225,192 @@ pub trait CommandExecutor {
 pub trait CommandExecutorAsync {
     fn execute(self, env: &Environment) -> impl Future<Output = crate::details::CommandResult>;
 }
+
+#[doc(hidden)]
+pub use regex::Regex;
+
+/**
+ * The options set in the given state, each listed once even when repeated;
+ * those filled from the environment or the configuration files are only
+ * included when `with_fallbacks` is set.
+ */
+fn used_options<'a>(command_spec: &'a CommandSpec, state: &'a State<'_>, with_fallbacks: bool) -> impl Iterator<Item = &'a OptionSpec> {
+    let mut seen_ids
+        = BTreeSet::new();
+
+    state.option_values.iter()
+        .map(|(component_id, _)| component_id)
+        .filter(move |component_id| with_fallbacks || !state.fallback_ids.contains(component_id))
+        .filter(move |component_id| seen_ids.insert(*component_id))
+        .filter_map(|component_id| command_spec.components[component_id].is_option())
+}
+
+/**
+ * Refuse the experimental command and options used in the given state unless
+ * the environment opted into them; options filled from the environment or the
+ * configuration files count as used too.
+ */
+pub fn check_experimental(environment: &Environment, command_spec: &CommandSpec, state: &State<'_>) -> Result<(), CommandError> {
+    if environment.is_experimental_enabled() {
+        return Ok(());
+    }
+
+    if command_spec.lifecycle.is_experimental {
+        let name
+            = format!("{} {}", environment.info.binary_name, command_spec.primary_path.join(" "));
+
+        return Err(CommandError::Experimental(format!("`{}`", name.trim_end()), environment.experimental_env_var()));
+    }
+
+    if let Some(option) = used_options(command_spec, state, true).find(|option| option.lifecycle.is_experimental) {
+        return Err(CommandError::Experimental(format!("`{}`", option.primary_name), environment.experimental_env_var()));
+    }
+
+    Ok(())
+}
+
+/**
+ * List the warnings to print for the deprecated command and options used in
+ * the given state.
+ */
+pub fn deprecation_warnings(info: &Info, command_spec: &CommandSpec, state: &State<'_>) -> Vec<String> {
+    let command_name
+        = format!("{} {}", info.binary_name, command_spec.primary_path.join(" "));
+
+    let command_warning = command_spec.lifecycle.deprecated.as_ref()
+        .map(|message| (command_name.trim_end().to_string(), message));
+
+    let option_warnings = used_options(command_spec, state, false)
+        .filter_map(|option| option.lifecycle.deprecated.as_ref().map(|message| (option.primary_name.clone(), message)));
+
+    command_warning.into_iter()
+        .chain(option_warnings)
+        .map(|(name, message)| match message.is_empty() {
+            true => format!("`{}` is deprecated.", name),
+            false => format!("`{}` is deprecated; {}", name, message),
+        })
+        .collect()
+}
+
+/**
+ * Wrap the error into `CommandError::FromSource` when the argument at fault
+ * was read from a response file.
+ */
+fn locate_error(environment: &Environment, index: Option<usize>, err: CommandError) -> CommandError {
+    match index.and_then(|index| environment.arg_source(index)) {
+        Some(source) => CommandError::FromSource(source.clone(), Box::new(err)),
+        None => err,
+    }
+}
+
+/**
+ * Same as `locate_error`, for an error returned while hydrating a component
+ * from the given arguments. When it received more than one, the error must
+ * mention the faulty value for us to know which one to blame.
+ */
+pub fn locate_hydration_error(environment: &Environment, args: &[UserArg<'_>], err: CommandError) -> CommandError {
+    let value = match &err {
+        CommandError::InvalidChoice(value, _) | CommandError::ConstraintViolation(_, value, _) => Some(value.as_str()),
+        _ => None,
+    };
+
+    let arg = args.iter()
+        .find(|arg| Some(arg.value) == value)
+        .or_else(|| args.first().filter(|_| args.len() == 1));
+
+    locate_error(environment, arg.map(|arg| arg.index), err)
+}
+
+fn is_in_bound<T, B>(value: &T, bound: B, ordering: std::cmp::Ordering) -> bool where T: PartialOrd + NumCast + ToPrimitive, B: ToPrimitive + Copy {
+    // Bounds that can't be represented exactly in the value type (a negative
+    // minimum on an unsigned field, a fractional bound on an integer field)
+    // are compared as floats instead.
+    let exact_bound = T::from(bound)
+        .filter(|exact_bound| exact_bound.to_f64() == bound.to_f64());
+
+    let comparison = match exact_bound {
+        Some(exact_bound) => value.partial_cmp(&exact_bound),
+        None => value.to_f64().zip(bound.to_f64()).and_then(|(value, bound)| value.partial_cmp(&bound)),
+    };
+
+    comparison.is_some_and(|comparison| comparison == ordering || comparison == std::cmp::Ordering::Equal)
+}
+
+pub fn check_min<T, B>(field: &str, value: &T, bound: B) -> Result<(), CommandError> where T: PartialOrd + NumCast + ToPrimitive + Display, B: ToPrimitive + Copy + Display {
+    match is_in_bound(value, bound, std::cmp::Ordering::Greater) {
+        true => Ok(()),
+        false => Err(CommandError::ConstraintViolation(field.to_string(), value.to_string(), Constraint::Min {value: bound.to_string()})),
+    }
+}
+
+pub fn check_max<T, B>(field: &str, value: &T, bound: B) -> Result<(), CommandError> where T: PartialOrd + NumCast + ToPrimitive + Display, B: ToPrimitive + Copy + Display {
+    match is_in_bound(value, bound, std::cmp::Ordering::Less) {
+        true => Ok(()),
+        false => Err(CommandError::ConstraintViolation(field.to_string(), value.to_string(), Constraint::Max {value: bound.to_string()})),
+    }
+}
+
+pub fn check_len(field: &str, value: &str, min: usize, max: Option<usize>) -> Result<(), CommandError> {
+    let len
+        = value.chars().count();
+
+    match len >= min && max.is_none_or(|max| len <= max) {
+        true => Ok(()),
+        false => Err(CommandError::ConstraintViolation(field.to_string(), value.to_string(), Constraint::Len {min, max})),
+    }
+}
+
+pub fn check_pattern(field: &str, value: &str, pattern: &Regex) -> Result<(), CommandError> {
+    match pattern.is_match(value) {
+        true => Ok(()),
+        false => Err(CommandError::ConstraintViolation(field.to_string(), value.to_string(), Constraint::Pattern {pattern: pattern.as_str().to_string()})),
+    }
+}
+
+/**
+ * Implemented by `#[derive(ValueEnum)]` for enums whose variants map to a
+ * fixed set of words on the command line.
+ */
+pub trait ValueEnum: FromStr {
+    fn choices() -> &'static [&'static str];
+}
+
+/**
+ * Internal helpers used by the `#[command]` attribute to find out whether a
+ * field type implements `ValueEnum` without requiring it to. The method
+ * resolution picks `ValueEnumChoices` when the bound is satisfied, and falls
+ * back to `NoChoices` (one autoref away) otherwise.
+ */
+#[doc(hidden)]
+pub struct ChoicesProbe<T>(PhantomData<T>);
+
+impl<T> ChoicesProbe<T> {
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> Self {
+        Self(PhantomData)
+    }
+}
+
+#[doc(hidden)]
+pub trait ValueEnumChoices {
+    fn choices(&self) -> Vec<String>;
+}
+
+impl<T: ValueEnum> ValueEnumChoices for ChoicesProbe<T> {
+    fn choices(&self) -> Vec<String> {
+        T::choices().iter()
+            .map(|choice| choice.to_string())
+            .collect()
+    }
+}
+
+#[doc(hidden)]
+pub trait NoChoices {
+    fn choices(&self) -> Vec<String>;
+}
+
+impl<T> NoChoices for &ChoicesProbe<T> {
+    fn choices(&self) -> Vec<String> {
+        vec![]
+    }
+}
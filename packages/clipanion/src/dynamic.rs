@@ -0,0 +1,150 @@
+use std::{fmt::Display, str::FromStr, sync::Arc};
+
+use clipanion_core::{CliBuilder, CommandError, CommandSpec, Component, PositionalSpec, State};
+
+use crate::{advanced::Environment, details::{handle_parse_error, CommandResult}};
+
+/**
+ * A command whose spec is only known at runtime, for example one provided by
+ * a plugin. Rather than being hydrated into a struct, it reads the values it
+ * needs from the `DynArgs` by component name.
+ */
+pub trait DynCommand: Send + Sync {
+    fn execute(&self, env: &Environment, args: &DynArgs<'_, '_>) -> CommandResult;
+}
+
+/**
+ * The commands registered at runtime; they are added to the parser after the
+ * ones of the program, and can be used alongside them.
+ */
+#[derive(Clone, Default)]
+pub struct DynamicCommands {
+    commands: Vec<(Arc<CommandSpec>, Arc<dyn DynCommand>)>,
+}
+
+impl std::fmt::Debug for DynamicCommands {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list()
+            .entries(self.commands.iter().map(|(spec, _)| spec.primary_path.join(" ")))
+            .finish()
+    }
+}
+
+impl DynamicCommands {
+    pub fn register(&mut self, spec: impl Into<Arc<CommandSpec>>, command: Box<dyn DynCommand>) -> &mut Self {
+        self.commands.push((spec.into(), Arc::from(command)));
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.commands.len()
+    }
+
+    /**
+     * Add the command specs to the builder; their command ids follow the ones
+     * of the commands already registered. This discards the machine the
+     * builder may have precompiled.
+     */
+    pub fn add_to<'cmds>(&'cmds self, builder: &mut CliBuilder<'cmds>) {
+        for (spec, _) in &self.commands {
+            builder.add_command(spec);
+        }
+    }
+
+    pub fn execute(&self, index: usize, env: &Environment, command_spec: &CommandSpec, state: &State<'_>) -> CommandResult {
+        let (_, command)
+            = &self.commands[index];
+
+        command.execute(env, &DynArgs::new(command_spec, state))
+    }
+}
+
+/**
+ * Give access to the values of a parsed command line by component name;
+ * options can be referred to by any of their names (`--name` or `-n`), and
+ * positionals and passthroughs by their name.
+ */
+pub struct DynArgs<'a, 'args> {
+    command_spec: &'a CommandSpec,
+    state: &'a State<'args>,
+}
+
+impl<'a, 'args> DynArgs<'a, 'args> {
+    pub fn new(command_spec: &'a CommandSpec, state: &'a State<'args>) -> Self {
+        Self {
+            command_spec,
+            state,
+        }
+    }
+
+    pub fn command_spec(&self) -> &CommandSpec {
+        self.command_spec
+    }
+
+    pub fn state(&self) -> &State<'args> {
+        self.state
+    }
+
+    fn component_id(&self, name: &str) -> Option<usize> {
+        self.command_spec.components.iter().position(|component| match component {
+            Component::Option(option) => option.all_names().any(|option_name| option_name == name),
+            Component::Positional(PositionalSpec::Dynamic {name: positional_name, ..}) => positional_name == name,
+            Component::Positional(PositionalSpec::Keyword {..}) => false,
+            Component::Passthrough(passthrough) => passthrough.name == name,
+        })
+    }
+
+    /**
+     * Iterate over each time the component was set, with the values it
+     * received each time.
+     */
+    pub fn occurrences(&self, name: &str) -> impl Iterator<Item = Vec<&'args str>> + '_ {
+        let component_id
+            = self.component_id(name);
+
+        self.state.option_values.iter()
+            .chain(self.state.positional_values.iter())
+            .filter(move |(id, _)| Some(*id) == component_id)
+            .map(|(_, args)| args.iter().map(|arg| arg.value).collect())
+    }
+
+    pub fn is_set(&self, name: &str) -> bool {
+        self.occurrences(name).next().is_some()
+    }
+
+    /**
+     * Return how many times the component was set; typically used for
+     * counters such as `-vvv`.
+     */
+    pub fn count(&self, name: &str) -> usize {
+        self.occurrences(name).count()
+    }
+
+    /**
+     * Return all the values the component received, in order.
+     */
+    pub fn values(&self, name: &str) -> Vec<&'args str> {
+        self.occurrences(name)
+            .flatten()
+            .collect()
+    }
+
+    /**
+     * Return the first value of the last time the component was set.
+     */
+    pub fn value(&self, name: &str) -> Option<&'args str> {
+        self.occurrences(name)
+            .last()
+            .and_then(|values| values.first().copied())
+    }
+
+    pub fn parse<T>(&self, name: &str) -> Result<Option<T>, CommandError> where T: FromStr, T::Err: Display + 'static {
+        self.value(name)
+            .map(|value| value.parse::<T>().map_err(handle_parse_error))
+            .transpose()
+    }
+}
@@ -0,0 +1,191 @@
+use std::fmt::Write;
+
+use clipanion_core::{BuiltinCommand, CommandSpec, Error, Info, SelectionResult, TraceEvent};
+use colored::{ColoredString, Colorize};
+
+use crate::format::write_section_title;
+
+fn paint(info: &Info, text: String, style: fn(ColoredString) -> ColoredString) -> String {
+    match info.colorized {
+        true => style(text.normal()).to_string(),
+        false => text,
+    }
+}
+
+fn command_label(commands: &[&CommandSpec], context_id: usize) -> String {
+    match commands.get(context_id) {
+        Some(command) if !command.primary_path.is_empty() => format!("#{} {}", context_id, command.primary_path.join(" ")),
+        Some(_) => format!("#{} (default)", context_id),
+        None => format!("#{}", context_id),
+    }
+}
+
+fn builtin_name(builtin: &BuiltinCommand<'_, '_>) -> &'static str {
+    match builtin {
+        BuiltinCommand::Complete(..) => "completion",
+        BuiltinCommand::Describe => "describe",
+        BuiltinCommand::Explain(..) => "explain",
+        BuiltinCommand::Manual(_) => "manual",
+        BuiltinCommand::Tokenize(_) => "tokenize",
+        BuiltinCommand::Version => "version",
+        BuiltinCommand::Help(_) => "help",
+    }
+}
+
+/**
+ * The ids of the commands an error refers to.
+ */
+fn error_command_ids(commands: &[&CommandSpec], err: &Error<'_>) -> Vec<usize> {
+    let command_specs = match err {
+        Error::AmbiguousSyntax(command_specs) | Error::NotFound(command_specs) => command_specs.clone(),
+        Error::CommandError(command_spec, _) => vec![*command_spec],
+        Error::BuildError(_) | Error::InternalError => vec![],
+    };
+
+    command_specs.into_iter()
+        .filter_map(|command_spec| commands.iter().position(|other| std::ptr::eq(*other, command_spec)))
+        .collect()
+}
+
+/**
+ * Describe how the parser resolved a command line from the trace of its run:
+ * the transitions taken for each argument, then how the selector narrowed
+ * the resulting states down to the one that got selected.
+ */
+pub fn render_explanation<T>(info: &Info, commands: &[&CommandSpec], argv: &[&str], trace: &[TraceEvent], result: &Result<SelectionResult<'_, '_, T>, Error<'_>>) -> String {
+    let mut output
+        = String::new();
+
+    write_section_title(&mut output, info, "Command line");
+
+    output.push('\n');
+    writeln!(output, "  {}", paint(info, format!("$ {} {}", info.binary_name, argv.join(" ")), |text| text.bold())).unwrap();
+    output.push('\n');
+
+    write_section_title(&mut output, info, "Parsing");
+    output.push('\n');
+
+    for event in trace {
+        match event {
+            TraceEvent::StartOfInput => {
+                writeln!(output, "  {}", paint(info, "<start of input>".to_string(), |text| text.bright_black())).unwrap();
+            },
+
+            TraceEvent::Argument {index, value} => {
+                writeln!(output, "  {} {}", paint(info, value.clone(), |text| text.bold()), paint(info, format!("(argument {})", index), |text| text.bright_black())).unwrap();
+            },
+
+            TraceEvent::EndOfInput => {
+                writeln!(output, "  {}", paint(info, "<end of input>".to_string(), |text| text.bright_black())).unwrap();
+            },
+
+            TraceEvent::Transition {context_id, from, to, reducer} => {
+                let reducer = reducer.as_ref()
+                    .map_or_else(String::new, |reducer| format!(" ({})", reducer));
+
+                writeln!(output, "    {}", paint(info, format!("{}: {} → {}{}", command_label(commands, *context_id), from, to, reducer), |text| text.bright_black())).unwrap();
+            },
+
+            TraceEvent::NoTransition {context_id, from} => {
+                writeln!(output, "    {}", paint(info, format!("✗ {}: no transition from node {}", command_label(commands, *context_id), from), |text| text.red())).unwrap();
+            },
+
+            TraceEvent::Trim {context_id, keyword_count, max_keyword_count, ..} => {
+                writeln!(output, "    {}", paint(info, format!("✗ {}: trimmed, matched {} keywords against {}", command_label(commands, *context_id), keyword_count, max_keyword_count), |text| text.yellow())).unwrap();
+            },
+
+            _ => {},
+        }
+    }
+
+    output.push('\n');
+    write_section_title(&mut output, info, "Selection");
+    output.push('\n');
+
+    for event in trace {
+        match event {
+            TraceEvent::Candidate {state_id, context_id, is_successful} => {
+                let status = match is_successful {
+                    true => "reached the end of the command line",
+                    false => "stopped on an error",
+                };
+
+                writeln!(output, "  state {}: {} {}", state_id, command_label(commands, *context_id), paint(info, format!("({})", status), |text| text.bright_black())).unwrap();
+            },
+
+            TraceEvent::Drop {state_id, context_id, reason} => {
+                writeln!(output, "  {}", paint(info, format!("✗ state {} ({}): {}", state_id, command_label(commands, *context_id), reason), |text| text.red())).unwrap();
+            },
+
+            TraceEvent::Prune {step, remaining} => {
+                let remaining = remaining.iter()
+                    .map(|state_id| state_id.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ");
+
+                writeln!(output, "  {}", paint(info, format!("→ after pruning by {}: [{}]", step, remaining), |text| text.bright_black())).unwrap();
+            },
+
+            TraceEvent::Selected {state_id, context_id} => {
+                writeln!(output, "  {}", paint(info, format!("✓ state {} ({})", state_id, command_label(commands, *context_id)), |text| text.green())).unwrap();
+            },
+
+            _ => {},
+        }
+    }
+
+    output.push('\n');
+    write_section_title(&mut output, info, "Result");
+    output.push('\n');
+
+    match result {
+        Ok(SelectionResult::Command(_, state, _)) => {
+            writeln!(output, "  {}", paint(info, format!("Runs {}", command_label(commands, state.context_id)), |text| text.green())).unwrap();
+        },
+
+        Ok(SelectionResult::Builtin(builtin)) => {
+            writeln!(output, "  {}", paint(info, format!("Runs the {} builtin", builtin_name(builtin)), |text| text.green())).unwrap();
+        },
+
+        Err(err) => {
+            writeln!(output, "  {}", paint(info, format!("Error: {}", err), |text| text.red())).unwrap();
+
+            for context_id in error_command_ids(commands, err) {
+                writeln!(output, "    {}", command_label(commands, context_id)).unwrap();
+            }
+        },
+    }
+
+    output
+}
+
+/**
+ * Same as `render_explanation`, but as a JSON object holding the arguments,
+ * the trace events, and the result of the parse.
+ */
+#[cfg(feature = "serde")]
+pub fn render_explanation_json<T>(commands: &[&CommandSpec], argv: &[&str], trace: &[TraceEvent], result: &Result<SelectionResult<'_, '_, T>, Error<'_>>) -> Result<String, serde_json::Error> {
+    let result = match result {
+        Ok(SelectionResult::Command(_, state, _)) => serde_json::json!({
+            "type": "command",
+            "commandId": state.context_id,
+        }),
+
+        Ok(SelectionResult::Builtin(builtin)) => serde_json::json!({
+            "type": "builtin",
+            "name": builtin_name(builtin),
+        }),
+
+        Err(err) => serde_json::json!({
+            "type": "error",
+            "message": err.to_string(),
+            "commandIds": error_command_ids(commands, err),
+        }),
+    };
+
+    serde_json::to_string(&serde_json::json!({
+        "argv": argv,
+        "events": trace,
+        "result": result,
+    }))
+}
@@ -1,6 +1,6 @@
 use std::{fmt::Display, marker::PhantomData};
 
-use clipanion_core::{CommandSpec, Info};
+use clipanion_core::{CommandSpec, CommandUsageOptions, Info, Lifecycle};
 use colored::Colorize;
 
 use crate::details::CommandProvider;
@@ -50,7 +50,7 @@ pub fn write_fading_title_line<T: Write>(output: &mut T, title: &str, main_color
     write_color(output, main_color);
     output.write_str(left).unwrap();
     write_color(output, (255, 255, 255));
-    output.write_str(&title).unwrap();
+    output.write_str(title).unwrap();
     write_color(output, main_color);
     output.write_char(' ').unwrap();
 
@@ -73,6 +73,52 @@ pub fn write_fading_title_line<T: Write>(output: &mut T, title: &str, main_color
     output.write_str("\x1b[0m\n").unwrap(); // Reset
 }
 
+pub(crate) fn write_section_title(output: &mut String, info: &Info, title: &str) {
+    match info.colorized {
+        true => write_fading_title_line(output, title, (128, 128, 128), 80, 50),
+        false => writeln!(output, "━━━ {} ━━━", title).unwrap(),
+    }
+}
+
+fn write_table(output: &mut String, info: &Info, rows: &[(String, String)]) {
+    let width = rows.iter()
+        .map(|(definition, _)| definition.chars().count())
+        .max()
+        .unwrap_or(0);
+
+    for (definition, description) in rows {
+        let padding
+            = " ".repeat(width - definition.chars().count());
+
+        match info.colorized {
+            true => writeln!(output, "  {}{}  {}", definition.bold(), padding, description.bright_black()).unwrap(),
+            false => writeln!(output, "  {}{}  {}", definition, padding, description).unwrap(),
+        }
+    }
+}
+
+/**
+ * Render the lifecycle of a command or option as a list of tags, such as
+ * `[deprecated: use --foo instead] [since: 1.4]`.
+ */
+pub fn format_lifecycle_tags(lifecycle: &Lifecycle) -> String {
+    let deprecated = lifecycle.deprecated.as_ref().map(|message| match message.is_empty() {
+        true => "[deprecated]".to_string(),
+        false => format!("[deprecated: {}]", message),
+    });
+
+    let experimental = lifecycle.is_experimental
+        .then(|| "[experimental]".to_string());
+
+    let since = lifecycle.since.as_ref()
+        .map(|since| format!("[since: {}]", since));
+
+    [deprecated, experimental, since].into_iter()
+        .flatten()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
 pub struct Formatter<S> {
     phantom: PhantomData<S>,
 }
@@ -122,5 +168,125 @@ impl<S: CommandProvider> Formatter<S> {
                 => Self::format_error(info, "Usage Error", &"The specified command was not found. Did you mean one of those commands?", suggested_specs.iter().cloned()),
         }
     }
-}
 
+    /**
+     * Render the full help page of a single command, as printed when running
+     * `<command> --help`. The variables derived from the program prefix are
+     * only listed once `CommandSpec::with_env_prefix` has been applied.
+     */
+    pub fn format_command_help(info: &Info, command_spec: &CommandSpec) -> String {
+        let usage = command_spec.usage_with(CommandUsageOptions {
+            detailed: true,
+            inline_options: false,
+        });
+
+        let mut result
+            = String::new();
+
+        write_section_title(&mut result, info, "Usage");
+
+        result.push('\n');
+        writeln!(result, "  {}", usage.oneliner(info)).unwrap();
+
+        for alias_line in &usage.alias_lines {
+            let alias_line
+                = format!("› {} {}", info.binary_name, alias_line);
+
+            match info.colorized {
+                true => writeln!(result, "  {}", alias_line.bright_white()).unwrap(),
+                false => writeln!(result, "  {}", alias_line).unwrap(),
+            }
+        }
+
+        let lifecycle_tags
+            = format_lifecycle_tags(&command_spec.lifecycle);
+
+        if !lifecycle_tags.is_empty() {
+            result.push('\n');
+            writeln!(result, "  {}", lifecycle_tags).unwrap();
+        }
+
+        if let Some(documentation) = &command_spec.documentation {
+            result.push('\n');
+            writeln!(result, "  {}", documentation.description).unwrap();
+
+            if let Some(details) = &documentation.details {
+                for paragraph in details.split("\n\n") {
+                    result.push('\n');
+
+                    for line in paragraph.trim().lines() {
+                        writeln!(result, "  {}", line).unwrap();
+                    }
+                }
+            }
+        }
+
+        if !usage.positionals.is_empty() {
+            let rows = usage.positionals.iter()
+                .map(|positional| {
+                    let mut description = match positional.required {
+                        true => format!("{} (required)", positional.description),
+                        false => positional.description.clone(),
+                    };
+
+                    for constraint in &positional.constraints {
+                        description = format!("{} [{}]", description, constraint);
+                    }
+
+                    (positional.definition.clone(), description.trim().to_string())
+                })
+                .collect::<Vec<_>>();
+
+            result.push('\n');
+            write_section_title(&mut result, info, "Arguments");
+            result.push('\n');
+            write_table(&mut result, info, &rows);
+        }
+
+        if !usage.options.is_empty() {
+            let rows = usage.options.iter()
+                .map(|option| {
+                    let mut description = match (&option.default_value, option.required) {
+                        (_, true) => format!("{} (required)", option.description),
+                        (Some(default_value), false) => format!("{} [default: {}]", option.description, default_value),
+                        (None, false) => option.description.clone(),
+                    };
+
+                    if let Some(env) = &option.env {
+                        description = format!("{} [env: {}]", description, env);
+                    }
+
+                    for constraint in &option.constraints {
+                        description = format!("{} [{}]", description, constraint);
+                    }
+
+                    description = format!("{} {}", description, format_lifecycle_tags(&option.lifecycle));
+
+                    (option.definition.clone(), description.trim().to_string())
+                })
+                .collect::<Vec<_>>();
+
+            result.push('\n');
+            write_section_title(&mut result, info, "Options");
+            result.push('\n');
+            write_table(&mut result, info, &rows);
+        }
+
+        if !command_spec.examples.is_empty() {
+            result.push('\n');
+            write_section_title(&mut result, info, "Examples");
+
+            for example in &command_spec.examples {
+                result.push('\n');
+                writeln!(result, "  {}", example.description).unwrap();
+
+                match info.colorized {
+                    true => writeln!(result, "    {}", format!("$ {}", example.command).bright_white()).unwrap(),
+                    false => writeln!(result, "    $ {}", example.command).unwrap(),
+                }
+            }
+        }
+
+        result
+    }
+}
@@ -9,11 +9,20 @@ pub mod cli {
 }
 
 pub mod advanced;
+pub mod completion;
+pub mod config;
 pub mod format;
 pub mod details;
+pub mod dynamic;
+pub mod explain;
+pub mod man;
+pub mod markdown;
 pub mod prelude;
+pub mod response_files;
 
 pub use advanced::Environment;
+pub use clipanion_derive::ValueEnum;
+pub use details::ValueEnum;
 
 pub use clipanion_core::{
     BuiltinCommand,
@@ -31,7 +40,7 @@ macro_rules! test_cli_success {
             let cli = $cli_name::build_cli().unwrap();
             let env = $crate::advanced::Environment::default().with_argv(ARGS.iter().map(|s| s.to_string()).collect());
 
-            println!("cli: {:?}", cli.compile());
+            println!("cli: {:?}", cli.machine());
 
             let result = $cli_name::parse_args(&cli, &env);
             let f: fn($command_name) -> () = $fn;
@@ -69,7 +78,7 @@ macro_rules! test_cli_failure {
             let env = $crate::advanced::Environment::default()
                 .with_argv(ARGS.iter().map(|s| s.to_string()).collect());
 
-            println!("cli: {:?}", cli.compile());
+            println!("cli: {:?}", cli.machine());
 
             let result = $cli_name::parse_args(&cli, &env);
             let f: fn($crate::core::Error<'_>) -> () = $fn;
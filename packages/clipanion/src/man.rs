@@ -0,0 +1,273 @@
+use std::{collections::BTreeMap, fmt::Write, path::Path};
+
+use clipanion_core::{BuildError, CommandSpec, Component, Info, PositionalSpec};
+
+use crate::details::CommandProvider;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ManPage {
+    pub name: String,
+    pub content: String,
+}
+
+impl ManPage {
+    pub fn file_name(&self) -> String {
+        format!("{}.1", self.name)
+    }
+}
+
+fn escape(value: &str) -> String {
+    let escaped = value
+        .replace('\\', "\\e")
+        .replace('-', "\\-");
+
+    escaped.lines()
+        .map(|line| match line.starts_with('.') || line.starts_with('\'') {
+            true => format!("\\&{}", line),
+            false => line.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn write_paragraphs(out: &mut String, text: &str) {
+    for paragraph in text.split("\n\n") {
+        writeln!(out, ".PP").unwrap();
+        writeln!(out, "{}", escape(paragraph.trim())).unwrap();
+    }
+}
+
+fn write_header(out: &mut String, info: &Info, name: &str, manual: &str) {
+    writeln!(out, ".TH \"{}\" \"1\" \"\" \"{} {}\" \"{}\"", escape(&name.to_uppercase()), escape(&info.program_name), escape(&info.version), escape(manual)).unwrap();
+}
+
+/**
+ * The name of the page documenting the given command. Default commands share
+ * their path with the index page, so they fall back on one of their aliases.
+ */
+pub fn command_page_name(info: &Info, command: &CommandSpec) -> String {
+    let path = std::iter::once(&command.primary_path)
+        .chain(command.aliases.iter())
+        .find(|path| !path.is_empty());
+
+    match path {
+        Some(path) => format!("{}-{}", info.binary_name, path.join("-")),
+        None => format!("{}-default", info.binary_name),
+    }
+}
+
+pub fn render_command_page(info: &Info, command: &CommandSpec) -> String {
+    let name
+        = command_page_name(info, command);
+
+    let manual = command.category.as_deref()
+        .map(|category| category.to_string())
+        .unwrap_or_else(|| format!("{} Manual", info.program_name));
+
+    let mut out = String::new();
+
+    write_header(&mut out, info, &name, &manual);
+
+    writeln!(out, ".SH NAME").unwrap();
+
+    match &command.documentation {
+        Some(documentation) => writeln!(out, "{} \\- {}", escape(&name), escape(&documentation.description)).unwrap(),
+        None => writeln!(out, "{}", escape(&name)).unwrap(),
+    }
+
+    writeln!(out, ".SH SYNOPSIS").unwrap();
+    writeln!(out, ".B {}", escape(&info.binary_name)).unwrap();
+    writeln!(out, "{}", escape(&command.to_string())).unwrap();
+
+    for alias in &command.aliases {
+        let alias_spec = CommandSpec {
+            primary_path: alias.clone(),
+            aliases: vec![],
+            ..command.clone()
+        };
+
+        writeln!(out, ".br").unwrap();
+        writeln!(out, ".B {}", escape(&info.binary_name)).unwrap();
+        writeln!(out, "{}", escape(&alias_spec.to_string())).unwrap();
+    }
+
+    if let Some(details) = command.documentation.as_ref().and_then(|documentation| documentation.details.as_ref()) {
+        writeln!(out, ".SH DESCRIPTION").unwrap();
+        write_paragraphs(&mut out, details);
+    }
+
+    let positionals = command.components.iter()
+        .filter_map(|component| match component {
+            Component::Positional(PositionalSpec::Dynamic {name, documentation: Some(documentation), ..}) => Some((name, documentation)),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
+    if !positionals.is_empty() {
+        writeln!(out, ".SH ARGUMENTS").unwrap();
+
+        for (name, documentation) in positionals {
+            writeln!(out, ".TP").unwrap();
+            writeln!(out, "\\fI{}\\fR", escape(name)).unwrap();
+            writeln!(out, "{}", escape(&documentation.description)).unwrap();
+        }
+    }
+
+    let options = command.components.iter()
+        .filter_map(|component| component.is_option())
+        .filter(|option| !option.is_hidden)
+        .collect::<Vec<_>>();
+
+    if !options.is_empty() {
+        writeln!(out, ".SH OPTIONS").unwrap();
+
+        for option in options {
+            let names = option.all_names()
+                .map(|name| format!("\\fB{}\\fR", escape(name)))
+                .collect::<Vec<_>>()
+                .join(", ");
+
+            writeln!(out, ".TP").unwrap();
+
+            if option.min_len > 0 || option.extra_len != Some(0) {
+                writeln!(out, "{} \\fIarg\\fR", names).unwrap();
+            } else {
+                writeln!(out, "{}", names).unwrap();
+            }
+
+            if let Some(documentation) = &option.documentation {
+                writeln!(out, "{}", escape(&documentation.description)).unwrap();
+            }
+
+            if option.is_required {
+                writeln!(out, ".br").unwrap();
+                writeln!(out, "Required.").unwrap();
+            } else if let Some(default_value) = &option.default_value {
+                writeln!(out, ".br").unwrap();
+                writeln!(out, "Default: {}", escape(default_value)).unwrap();
+            }
+        }
+    }
+
+    if !command.examples.is_empty() {
+        writeln!(out, ".SH EXAMPLES").unwrap();
+
+        for example in &command.examples {
+            writeln!(out, ".PP").unwrap();
+            writeln!(out, "{}", escape(&example.description)).unwrap();
+            writeln!(out, ".PP").unwrap();
+            writeln!(out, ".RS 4").unwrap();
+            writeln!(out, ".nf").unwrap();
+            writeln!(out, "{}", escape(&example.command)).unwrap();
+            writeln!(out, ".fi").unwrap();
+            writeln!(out, ".RE").unwrap();
+        }
+    }
+
+    writeln!(out, ".SH SEE ALSO").unwrap();
+    writeln!(out, "\\fB{}\\fR(1)", escape(&info.binary_name)).unwrap();
+
+    out
+}
+
+pub fn render_index_page(info: &Info, commands: &[&CommandSpec]) -> String {
+    let mut out = String::new();
+
+    write_header(&mut out, info, &info.binary_name, &format!("{} Manual", info.program_name));
+
+    writeln!(out, ".SH NAME").unwrap();
+    writeln!(out, "{} \\- {}", escape(&info.binary_name), escape(&info.about)).unwrap();
+
+    writeln!(out, ".SH SYNOPSIS").unwrap();
+
+    let default_commands = commands.iter()
+        .filter(|command| command.is_default() && !command.is_hidden)
+        .collect::<Vec<_>>();
+
+    for command in &default_commands {
+        writeln!(out, ".B {}", escape(&info.binary_name)).unwrap();
+        writeln!(out, "{}", escape(&command.to_string())).unwrap();
+        writeln!(out, ".br").unwrap();
+    }
+
+    writeln!(out, ".B {}", escape(&info.binary_name)).unwrap();
+    writeln!(out, "\\fIcommand\\fR [\\fIargs\\fR...]").unwrap();
+
+    let mut commands_by_category
+        = BTreeMap::<Option<&str>, Vec<&CommandSpec>>::new();
+
+    for command in commands.iter().filter(|command| !command.is_hidden) {
+        commands_by_category.entry(command.category.as_deref())
+            .or_default()
+            .push(command);
+    }
+
+    writeln!(out, ".SH COMMANDS").unwrap();
+
+    for (category, mut commands) in commands_by_category {
+        commands.sort_by(|a, b| a.primary_path.cmp(&b.primary_path));
+
+        writeln!(out, ".SS {}", escape(category.unwrap_or("General commands"))).unwrap();
+
+        for command in commands {
+            writeln!(out, ".TP").unwrap();
+            writeln!(out, "\\fB{} {}\\fR", escape(&info.binary_name), escape(&command.to_string())).unwrap();
+
+            if let Some(documentation) = &command.documentation {
+                writeln!(out, "{}", escape(&documentation.description)).unwrap();
+            }
+
+            writeln!(out, "See \\fB{}\\fR(1).", escape(&command_page_name(info, command))).unwrap();
+        }
+    }
+
+    writeln!(out, ".SH VERSION").unwrap();
+    writeln!(out, "{}", escape(&info.version)).unwrap();
+
+    out
+}
+
+/**
+ * Render the index page followed by one page per command.
+ */
+pub fn render_man_pages(info: &Info, commands: &[&CommandSpec]) -> Vec<ManPage> {
+    let index = ManPage {
+        name: info.binary_name.clone(),
+        content: render_index_page(info, commands),
+    };
+
+    let command_pages = commands.iter().filter(|command| !command.is_hidden).map(|command| ManPage {
+        name: command_page_name(info, command),
+        content: render_command_page(info, command),
+    });
+
+    std::iter::once(index)
+        .chain(command_pages)
+        .collect()
+}
+
+pub fn man_pages_for<S: CommandProvider>(info: &Info) -> Result<Vec<ManPage>, BuildError> {
+    let commands
+        = S::registered_commands()?;
+
+    Ok(render_man_pages(info, &commands))
+}
+
+/**
+ * Write all the man pages of the program into the given directory. Build
+ * scripts can't call it since they can't depend on the crate they build;
+ * call it from a separate binary instead (for example an `xtask` crate, or a
+ * `gen-man` bin target of the program itself) when packaging a release.
+ */
+pub fn write_man_pages<S: CommandProvider>(info: &Info, directory: &Path) -> std::io::Result<()> {
+    let pages = man_pages_for::<S>(info)
+        .map_err(std::io::Error::other)?;
+
+    std::fs::create_dir_all(directory)?;
+
+    for page in pages {
+        std::fs::write(directory.join(page.file_name()), page.content)?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,166 @@
+use std::{collections::BTreeMap, fmt::Write};
+
+use clipanion_core::{BuildError, CommandSpec, Component, Info, PositionalSpec};
+
+use crate::details::CommandProvider;
+
+fn escape_cell(value: &str) -> String {
+    value
+        .replace('|', "\\|")
+        .replace('\n', " ")
+}
+
+fn write_command(out: &mut String, info: &Info, command: &CommandSpec) {
+    let title = std::iter::once(info.binary_name.as_str())
+        .chain(command.primary_path.iter().map(|segment| segment.as_str()))
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    writeln!(out, "### `{}`", title).unwrap();
+    writeln!(out).unwrap();
+    writeln!(out, "```").unwrap();
+    writeln!(out, "{} {}", info.binary_name, command).unwrap();
+    writeln!(out, "```").unwrap();
+    writeln!(out).unwrap();
+
+    if let Some(documentation) = &command.documentation {
+        writeln!(out, "{}", documentation.description).unwrap();
+        writeln!(out).unwrap();
+
+        if let Some(details) = &documentation.details {
+            writeln!(out, "{}", details).unwrap();
+            writeln!(out).unwrap();
+        }
+    }
+
+    if !command.aliases.is_empty() {
+        let aliases = command.aliases.iter()
+            .map(|alias| format!("`{}`", std::iter::once(info.binary_name.as_str()).chain(alias.iter().map(|segment| segment.as_str())).collect::<Vec<_>>().join(" ")))
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        writeln!(out, "Aliases: {}", aliases).unwrap();
+        writeln!(out).unwrap();
+    }
+
+    let positionals = command.components.iter()
+        .filter_map(|component| match component {
+            Component::Positional(PositionalSpec::Dynamic {name, documentation, ..}) => Some((name, documentation)),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
+    if !positionals.is_empty() {
+        writeln!(out, "#### Arguments").unwrap();
+        writeln!(out).unwrap();
+        writeln!(out, "| Argument | Description |").unwrap();
+        writeln!(out, "| --- | --- |").unwrap();
+
+        for (name, documentation) in positionals {
+            let description = documentation.as_ref()
+                .map(|documentation| escape_cell(&documentation.description))
+                .unwrap_or_default();
+
+            writeln!(out, "| `{}` | {} |", escape_cell(name), description).unwrap();
+        }
+
+        writeln!(out).unwrap();
+    }
+
+    let options = command.components.iter()
+        .filter_map(|component| component.is_option())
+        .filter(|option| !option.is_hidden)
+        .collect::<Vec<_>>();
+
+    if !options.is_empty() {
+        writeln!(out, "#### Options").unwrap();
+        writeln!(out).unwrap();
+        writeln!(out, "| Option | Description | Default |").unwrap();
+        writeln!(out, "| --- | --- | --- |").unwrap();
+
+        for option in options {
+            let names = option.all_names()
+                .map(|name| format!("`{}`", escape_cell(name)))
+                .collect::<Vec<_>>()
+                .join(", ");
+
+            let description = option.documentation.as_ref()
+                .map(|documentation| escape_cell(&documentation.description))
+                .unwrap_or_default();
+
+            let default_value = match (&option.default_value, option.is_required) {
+                (_, true) => "*required*".to_string(),
+                (Some(default_value), false) => format!("`{}`", escape_cell(default_value)),
+                (None, false) => String::new(),
+            };
+
+            writeln!(out, "| {} | {} | {} |", names, description, default_value).unwrap();
+        }
+
+        writeln!(out).unwrap();
+    }
+
+    if !command.examples.is_empty() {
+        writeln!(out, "#### Examples").unwrap();
+        writeln!(out).unwrap();
+
+        for example in &command.examples {
+            writeln!(out, "{}", example.description).unwrap();
+            writeln!(out).unwrap();
+            writeln!(out, "```").unwrap();
+            writeln!(out, "{}", example.command).unwrap();
+            writeln!(out, "```").unwrap();
+            writeln!(out).unwrap();
+        }
+    }
+}
+
+/**
+ * Render a Markdown reference of the given commands, grouped by category.
+ * Commands are sorted by path so that the output only changes when the
+ * commands themselves do.
+ */
+pub fn render_markdown(info: &Info, commands: &[&CommandSpec]) -> String {
+    let mut out = String::new();
+
+    writeln!(out, "# {}", info.program_name).unwrap();
+    writeln!(out).unwrap();
+
+    if !info.about.is_empty() {
+        writeln!(out, "{}", info.about).unwrap();
+        writeln!(out).unwrap();
+    }
+
+    let mut commands_by_category
+        = BTreeMap::<Option<&str>, Vec<&CommandSpec>>::new();
+
+    for command in commands.iter().filter(|command| !command.is_hidden) {
+        commands_by_category.entry(command.category.as_deref())
+            .or_default()
+            .push(command);
+    }
+
+    for (category, mut commands) in commands_by_category {
+        commands.sort_by(|a, b| a.primary_path.cmp(&b.primary_path));
+
+        writeln!(out, "## {}", category.unwrap_or("General commands")).unwrap();
+        writeln!(out).unwrap();
+
+        for command in commands {
+            write_command(&mut out, info, command);
+        }
+    }
+
+    while out.ends_with("\n\n") {
+        out.pop();
+    }
+
+    out
+}
+
+pub fn markdown_for<S: CommandProvider>(info: &Info) -> Result<String, BuildError> {
+    let commands
+        = S::registered_commands()?;
+
+    Ok(render_markdown(info, &commands))
+}
@@ -0,0 +1,225 @@
+use std::path::{Path, PathBuf};
+
+use clipanion_core::ArgSource;
+
+/**
+ * How many response files can be nested inside each other before we give up;
+ * a response file is allowed to reference other response files.
+ */
+pub const DEFAULT_MAX_DEPTH: usize = 16;
+
+#[derive(thiserror::Error, Debug)]
+pub enum ResponseFileError {
+    #[error("Failed to read {0}: {1}")]
+    Io(PathBuf, std::io::Error),
+
+    #[error("Unterminated quote in {0}:{1}")]
+    UnterminatedQuote(PathBuf, usize),
+
+    #[error("Response file {0} includes itself")]
+    Cycle(PathBuf),
+
+    #[error("Response file {0} is nested too deeply (the limit is {1})")]
+    TooDeep(PathBuf, usize),
+}
+
+/**
+ * Split the content of a response file into arguments, alongside the line
+ * they start on. Arguments are separated by whitespace (including newlines),
+ * and follow the usual shell quoting rules:
+ *
+ * - single quotes preserve everything until the next single quote
+ * - double quotes preserve everything except `\"` and `\\`
+ * - outside of quotes, a backslash escapes the character that follows it
+ * - lines starting with `#` are comments
+ */
+pub fn parse_response_file(path: &Path, content: &str) -> Result<Vec<(String, usize)>, ResponseFileError> {
+    let mut args
+        = vec![];
+
+    let mut current: Option<(String, usize)>
+        = None;
+
+    let mut line
+        = 1;
+
+    let mut chars
+        = content.chars().peekable();
+
+    let mut at_line_start
+        = true;
+
+    while let Some(c) = chars.next() {
+        if at_line_start && current.is_none() && c == '#' {
+            for c in chars.by_ref() {
+                if c == '\n' {
+                    line += 1;
+                    break;
+                }
+            }
+
+            continue;
+        }
+
+        match c {
+            '\n' | ' ' | '\t' | '\r' => {
+                args.extend(current.take());
+
+                if c == '\n' {
+                    line += 1;
+                    at_line_start = true;
+                }
+
+                continue;
+            },
+
+            '\'' | '"' => {
+                let start_line
+                    = line;
+
+                let (value, _) = current
+                    .get_or_insert_with(|| (String::new(), line));
+
+                loop {
+                    match chars.next() {
+                        None => {
+                            return Err(ResponseFileError::UnterminatedQuote(path.to_path_buf(), start_line));
+                        },
+
+                        Some(quoted) if quoted == c => {
+                            break;
+                        },
+
+                        Some('\\') if c == '"' && matches!(chars.peek(), Some('"' | '\\')) => {
+                            value.push(chars.next().unwrap());
+                        },
+
+                        Some(quoted) => {
+                            if quoted == '\n' {
+                                line += 1;
+                            }
+
+                            value.push(quoted);
+                        },
+                    }
+                }
+            },
+
+            '\\' => {
+                let (value, _) = current
+                    .get_or_insert_with(|| (String::new(), line));
+
+                if let Some(escaped) = chars.next() {
+                    if escaped == '\n' {
+                        line += 1;
+                    }
+
+                    value.push(escaped);
+                }
+            },
+
+            c => {
+                current.get_or_insert_with(|| (String::new(), line)).0.push(c);
+            },
+        }
+
+        at_line_start = false;
+    }
+
+    args.extend(current);
+
+    Ok(args)
+}
+
+/**
+ * Replace the `@path` arguments by the content of the files they reference,
+ * returning the expanded arguments alongside their location (`None` for the
+ * ones that didn't come from a file).
+ *
+ * Paths referenced from within a response file are resolved relative to the
+ * directory of that file. A leading `@@` stands for a literal `@`, and
+ * arguments after `--` are kept as-is.
+ */
+pub fn expand_response_files(argv: &[String], max_depth: usize) -> Result<(Vec<String>, Vec<Option<ArgSource>>), ResponseFileError> {
+    let mut expansion
+        = Expansion {max_depth, ..Default::default()};
+
+    for arg in argv {
+        expansion.push(arg, None, None)?;
+    }
+
+    Ok((expansion.args, expansion.sources))
+}
+
+#[derive(Default)]
+struct Expansion {
+    max_depth: usize,
+    stack: Vec<PathBuf>,
+    args: Vec<String>,
+    sources: Vec<Option<ArgSource>>,
+    post_double_dash: bool,
+}
+
+impl Expansion {
+    fn push(&mut self, arg: &str, base: Option<&Path>, source: Option<ArgSource>) -> Result<(), ResponseFileError> {
+        if self.post_double_dash {
+            self.args.push(arg.to_string());
+            self.sources.push(source);
+            return Ok(());
+        }
+
+        if arg == "--" {
+            self.post_double_dash = true;
+        }
+
+        if let Some(literal) = arg.strip_prefix("@@") {
+            self.args.push(format!("@{}", literal));
+            self.sources.push(source);
+            return Ok(());
+        }
+
+        match arg.strip_prefix('@').filter(|path| !path.is_empty()) {
+            Some(path) => self.include(&base.map_or_else(|| PathBuf::from(path), |base| base.join(path))),
+
+            None => {
+                self.args.push(arg.to_string());
+                self.sources.push(source);
+                Ok(())
+            },
+        }
+    }
+
+    fn include(&mut self, path: &Path) -> Result<(), ResponseFileError> {
+        let canonical_path = path.canonicalize()
+            .map_err(|err| ResponseFileError::Io(path.to_path_buf(), err))?;
+
+        if self.stack.contains(&canonical_path) {
+            return Err(ResponseFileError::Cycle(path.to_path_buf()));
+        }
+
+        if self.stack.len() >= self.max_depth {
+            return Err(ResponseFileError::TooDeep(path.to_path_buf(), self.max_depth));
+        }
+
+        let content = std::fs::read_to_string(path)
+            .map_err(|err| ResponseFileError::Io(path.to_path_buf(), err))?;
+
+        let base
+            = path.parent().map(Path::to_path_buf);
+
+        self.stack.push(canonical_path);
+
+        for (arg, line) in parse_response_file(path, &content)? {
+            let source = ArgSource {
+                path: path.display().to_string(),
+                line,
+            };
+
+            self.push(&arg, base.as_deref(), Some(source))?;
+        }
+
+        self.stack.pop();
+
+        Ok(())
+    }
+}
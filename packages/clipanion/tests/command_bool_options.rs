@@ -23,13 +23,13 @@ enum MyCli {
 }
 
 test_cli_success!(it_works, MyCli, MyCommand, &["-DP"], |command| {
-    assert_eq!(command.development, true);
-    assert_eq!(command.production, true);
-    assert_eq!(command.test, false);
+    assert!(command.development);
+    assert!(command.production);
+    assert!(!command.test);
 });
 
 test_cli_success!(it_works_with_no_args, MyCli, MyCommand, &["-PTD"], |command| {
-    assert_eq!(command.development, true);
-    assert_eq!(command.production, true);
-    assert_eq!(command.test, true);
+    assert!(command.development);
+    assert!(command.production);
+    assert!(command.test);
 });
@@ -0,0 +1,69 @@
+use clipanion::{completion::{generate_completion_script, Shell}, details::CommandProvider, prelude::*, test_cli_success};
+
+#[cli::command]
+#[cli::path("commit")]
+#[cli::path("ci")]
+struct CommitCommand {
+    #[cli::option("-a,--all", default = false)]
+    all: bool,
+
+    #[cli::option("-m,--message")]
+    message: Option<String>,
+}
+
+impl CommitCommand {
+    fn execute(&self) {
+    }
+}
+
+#[cli::command]
+#[cli::path("config", "get")]
+struct ConfigGetCommand {
+    name: String,
+}
+
+impl ConfigGetCommand {
+    fn execute(&self) {
+    }
+}
+
+#[cli::program(completion)]
+enum MyCli {
+    Commit(CommitCommand),
+    ConfigGet(ConfigGetCommand),
+}
+
+test_cli_success!(it_registers_the_completion_command, MyCli, MyCliCompletionCommand, &["completion", "zsh"], |command| {
+    assert_eq!(command.shell, Shell::Zsh);
+});
+
+#[test]
+fn it_generates_bash_scripts() {
+    let commands = MyCli::registered_commands().unwrap();
+    let script = generate_completion_script(Shell::Bash, "my-cli", &commands);
+
+    assert!(script.contains("'ci'|'commit'|'completion'|'config'|'config get'"));
+    assert!(script.contains("keywords='ci commit completion config'"));
+    assert!(script.contains("options='--all --message -a -m'"));
+    assert!(script.contains("value_options='--message -m'"));
+    assert!(script.contains("complete -F _my_cli 'my-cli'"));
+}
+
+#[test]
+fn it_generates_fish_scripts() {
+    let commands = MyCli::registered_commands().unwrap();
+    let script = generate_completion_script(Shell::Fish, "my-cli", &commands);
+
+    assert!(script.contains("complete -c 'my-cli' -n '__my_cli_path_is \\'config\\'' -a 'get'"));
+    assert!(script.contains("complete -c 'my-cli' -n '__my_cli_path_is \\'commit\\'' -l 'message' -s 'm' -r -F"));
+}
+
+#[test]
+fn it_generates_a_script_for_every_shell() {
+    let commands = MyCli::registered_commands().unwrap();
+
+    for shell in Shell::ALL {
+        assert!(generate_completion_script(shell, "my-cli", &commands).contains("my-cli"));
+        assert_eq!(shell.to_string().parse::<Shell>(), Ok(shell));
+    }
+}
@@ -0,0 +1,154 @@
+#![cfg(feature = "config")]
+
+use std::{collections::HashMap, path::{Path, PathBuf}};
+
+use clipanion::{advanced::Environment, config::{ConfigSources, ConfigTable}, core::SelectionResult, details::CliEnums, prelude::*, Error};
+
+#[cli::command]
+#[cli::path("config", "get")]
+struct ConfigGetCommand {
+    #[cli::option("--json", default = false)]
+    json: bool,
+
+    #[cli::option("--format", default = "text".to_string())]
+    format: String,
+
+    #[cli::option("--retries", env = "MYTOOL_RETRIES", default = 3)]
+    retries: usize,
+
+    #[cli::option("--exclude", default = vec![])]
+    exclude: Vec<String>,
+
+    name: String,
+}
+
+impl ConfigGetCommand {
+    fn execute(&self) {
+    }
+}
+
+#[cli::program]
+enum MyCli {
+    ConfigGet(ConfigGetCommand),
+}
+
+struct TempRoot(PathBuf);
+
+impl TempRoot {
+    fn new(name: &str) -> Self {
+        let path = std::env::temp_dir()
+            .join(format!("clipanion-config-{}-{}", name, std::process::id()));
+
+        let _ = std::fs::remove_dir_all(&path);
+        std::fs::create_dir_all(&path).unwrap();
+
+        Self(path)
+    }
+
+    fn write(&self, path: &str, content: &str) {
+        let path
+            = self.0.join(path);
+
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, content).unwrap();
+    }
+
+    fn sources(&self) -> ConfigSources {
+        ConfigSources::new("mytool")
+            .with_root(self.0.clone())
+            .with_home(Some(PathBuf::from("/home/user")))
+            .with_xdg_config_home(None)
+            .with_cwd(PathBuf::from("/work/project/src"))
+    }
+
+    fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempRoot {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(self.path());
+    }
+}
+
+fn parse(args: &[&str], env_vars: &[(&str, &str)], config: ConfigTable) -> ConfigGetCommand {
+    let env = Environment::default()
+        .with_argv(args.iter().map(|arg| arg.to_string()).collect())
+        .with_env_vars(env_vars.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect::<HashMap<_, _>>())
+        .with_config(config);
+
+    let cli
+        = MyCli::build_cli().unwrap();
+
+    let result: Result<_, Error> = MyCli::parse_args(&cli, &env);
+
+    match result.unwrap() {
+        SelectionResult::Command(_, _, command) => {
+            let MyCli::ConfigGet(command) = <MyCli as CliEnums>::Enum::try_from(command).unwrap();
+            command
+        },
+
+        SelectionResult::Builtin(builtin) => {
+            panic!("expected command, got builtin: {:?}", builtin);
+        },
+    }
+}
+
+#[test]
+fn it_layers_user_and_project_files() {
+    let root = TempRoot::new("layers");
+
+    root.write("home/user/.config/mytool/config.toml", "json = true\nretries = 1\n\n[config.get]\nformat = \"yaml\"\n");
+    root.write("work/.mytool.json", r#"{"config": {"get": {"format": "csv", "exclude": ["a", "b"]}}}"#);
+    root.write("work/project/.mytool.toml", "retries = 2\n");
+
+    let config
+        = root.sources().load().unwrap();
+
+    let command
+        = parse(&["config", "get", "name"], &[], config);
+
+    assert!(command.json);
+    assert_eq!(command.format, "csv");
+    assert_eq!(command.retries, 2);
+    assert_eq!(command.exclude, vec!["a".to_string(), "b".to_string()]);
+}
+
+#[test]
+fn it_favors_the_command_line_and_the_environment() {
+    let root = TempRoot::new("precedence");
+
+    root.write("work/project/.mytool.toml", "json = true\nformat = \"yaml\"\nretries = 2\n");
+
+    let config
+        = root.sources().load().unwrap();
+
+    let command
+        = parse(&["config", "get", "--no-json", "--format", "csv", "name"], &[("MYTOOL_RETRIES", "5")], config);
+
+    assert!(!command.json);
+    assert_eq!(command.format, "csv");
+    assert_eq!(command.retries, 5);
+}
+
+#[test]
+fn it_falls_back_to_the_default_values() {
+    let root = TempRoot::new("defaults");
+
+    let command
+        = parse(&["config", "get", "name"], &[], root.sources().load().unwrap());
+
+    assert!(!command.json);
+    assert_eq!(command.format, "text");
+    assert_eq!(command.retries, 3);
+}
+
+#[test]
+fn it_reports_malformed_files() {
+    let root = TempRoot::new("malformed");
+
+    root.write("work/.mytool.toml", "json = \n");
+
+    assert!(root.sources().load().is_err());
+}
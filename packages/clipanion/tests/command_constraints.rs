@@ -0,0 +1,80 @@
+use clipanion::{core::{CommandError, Constraint, Error}, details::CommandProvider, format::Formatter, prelude::*, test_cli_failure, test_cli_success};
+
+mod common;
+
+#[cli::command]
+#[cli::path("serve")]
+struct ServeCommand {
+    /// The port to listen on.
+    #[cli::option("--port", default = 8080, min = 1, max = 65535)]
+    port: u16,
+
+    #[cli::option("--ratio", min = 0.5)]
+    ratio: Option<f64>,
+
+    #[cli::option("--tag", default = vec![], len = 1..=8)]
+    tags: Vec<String>,
+
+    /// The name of the site.
+    #[cli::positional(pattern = "^[a-z-]+$", len = 3..)]
+    name: String,
+}
+
+impl ServeCommand {
+    fn execute(&self) {
+    }
+}
+
+#[cli::program]
+enum MyCli {
+    Serve(ServeCommand),
+}
+
+test_cli_success!(it_accepts_values_within_the_constraints, MyCli, ServeCommand, &["serve", "--port", "65535", "--ratio", "0.5", "--tag", "12345678", "my-site"], |command| {
+    assert_eq!(command.port, 65535);
+    assert_eq!(command.ratio, Some(0.5));
+    assert_eq!(command.tags, vec!["12345678".to_string()]);
+    assert_eq!(command.name, "my-site");
+});
+
+test_cli_failure!(it_enforces_the_minimum, MyCli, &["serve", "--port", "0", "my-site"], |err| {
+    assert_eq!(err, Error::CommandError(ServeCommand::command_spec().unwrap(), CommandError::ConstraintViolation("--port".to_string(), "0".to_string(), Constraint::Min {value: "1".to_string()})));
+    assert_eq!(err.to_string(), "Invalid value `0` for --port; expected at least 1");
+});
+
+test_cli_failure!(it_enforces_float_minimums, MyCli, &["serve", "--ratio", "0.25", "my-site"], |err| {
+    assert_eq!(err.to_string(), "Invalid value `0.25` for --ratio; expected at least 0.5");
+});
+
+test_cli_failure!(it_enforces_lengths_on_each_value, MyCli, &["serve", "--tag", "a", "--tag", "123456789", "my-site"], |err| {
+    assert_eq!(err.to_string(), "Invalid value `123456789` for --tag; expected between 1 and 8 characters");
+});
+
+test_cli_failure!(it_enforces_patterns, MyCli, &["serve", "My_Site"], |err| {
+    assert_eq!(err, Error::CommandError(ServeCommand::command_spec().unwrap(), CommandError::ConstraintViolation("NAME".to_string(), "My_Site".to_string(), Constraint::Pattern {pattern: "^[a-z-]+$".to_string()})));
+});
+
+test_cli_failure!(it_checks_lengths_before_patterns, MyCli, &["serve", "ab"], |err| {
+    assert_eq!(err.to_string(), "Invalid value `ab` for NAME; expected at least 3 characters");
+});
+
+#[test]
+fn it_records_the_constraints_in_the_spec() {
+    let command_spec
+        = ServeCommand::command_spec().unwrap();
+
+    assert_eq!(command_spec.components[0].constraints(), &[Constraint::Min {value: "1".to_string()}, Constraint::Max {value: "65535".to_string()}]);
+    assert_eq!(command_spec.components[4].constraints(), &[Constraint::Len {min: 1, max: Some(8)}]);
+}
+
+#[test]
+fn it_lists_the_constraints_in_the_help() {
+    let info
+        = common::info();
+
+    let help
+        = Formatter::<MyCli>::format_command_help(&info, ServeCommand::command_spec().unwrap());
+
+    assert!(help.contains("The port to listen on. [default: 8080] [at least 1] [at most 65535]"), "{}", help);
+    assert!(help.contains("The name of the site. (required) [at least 3 characters] [a value matching `^[a-z-]+$`]"), "{}", help);
+}
@@ -17,9 +17,9 @@ enum MyCli {
 }
 
 test_cli_success!(it_works, MyCli, MyCommand, &["--my-option"], |command| {
-    assert_eq!(command.value, true);
+    assert!(command.value);
 });
 
 test_cli_success!(it_works_with_no_args, MyCli, MyCommand, &[], |command| {
-    assert_eq!(command.value, false);
+    assert!(!command.value);
 });
@@ -0,0 +1,61 @@
+use clipanion::{details::CommandProvider, prelude::*, test_cli_failure, test_cli_success};
+
+#[cli::command]
+#[cli::path("run")]
+struct RunCommand {
+    #[cli::option("-v,--verbose", default = false)]
+    verbose: bool,
+
+    #[cli::option("--env")]
+    env: Vec<String>,
+
+    script: String,
+    args: Vec<String>,
+}
+
+impl RunCommand {
+    fn execute(&self) {
+    }
+}
+
+#[cli::command]
+#[cli::path("run", "all")]
+struct RunAllCommand {
+    #[cli::option("--parallel", default = false)]
+    parallel: bool,
+}
+
+impl RunAllCommand {
+    fn execute(&self) {
+    }
+}
+
+#[cli::program(determinize)]
+enum MyCli {
+    Run(RunCommand),
+    RunAll(RunAllCommand),
+}
+
+#[test]
+fn it_determinizes_the_machine() {
+    let cli
+        = MyCli::build_cli().unwrap();
+
+    let machine
+        = cli.machine();
+
+    assert!(machine.nodes.iter().skip(1).all(|node| node.shortcuts.is_empty()));
+}
+
+test_cli_success!(it_parses_with_the_determinized_machine, MyCli, RunCommand, &["run", "-v", "--env", "a=1", "build", "--env", "b=2", "x", "y"], |command| {
+    assert!(command.verbose);
+    assert_eq!(command.env, vec!["a=1", "b=2"]);
+    assert_eq!(command.script, "build");
+    assert_eq!(command.args, vec!["x", "y"]);
+});
+
+test_cli_success!(it_still_favors_keywords, MyCli, RunAllCommand, &["run", "all", "--parallel"], |command| {
+    assert!(command.parallel);
+});
+
+test_cli_failure!(it_still_reports_missing_positionals, MyCli, &["run", "-v"], |_| {});
@@ -0,0 +1,138 @@
+use std::{process::ExitCode, sync::{Arc, Mutex}};
+
+use clipanion::{advanced::{Cli, Environment}, core::{CommandSpec, Component, OptionSpec, PositionalSpec}, details::CommandResult, dynamic::{DynArgs, DynCommand}, prelude::*};
+
+#[cli::command]
+#[cli::path("build")]
+struct BuildCommand {
+}
+
+impl BuildCommand {
+    fn execute(&self) -> ExitCode {
+        ExitCode::from(10)
+    }
+}
+
+#[cli::program]
+enum MyCli {
+    Build(BuildCommand),
+}
+
+struct GreetCommand {
+    output: Arc<Mutex<Vec<String>>>,
+}
+
+impl DynCommand for GreetCommand {
+    fn execute(&self, _env: &Environment, args: &DynArgs<'_, '_>) -> CommandResult {
+        let repeat = match args.parse::<usize>("--repeat") {
+            Ok(repeat) => repeat.unwrap_or(1),
+            Err(err) => return err.into(),
+        };
+
+        let greeting
+            = args.value("-g").unwrap_or("Hello");
+
+        let mut output
+            = self.output.lock().unwrap();
+
+        for _ in 0..repeat {
+            for target in args.values("target") {
+                output.push(format!("{} {}{}", greeting, target, if args.is_set("--loud") { "!" } else { "" }));
+            }
+        }
+
+        ExitCode::from(20).into()
+    }
+}
+
+fn greet_spec() -> CommandSpec {
+    let mut target
+        = PositionalSpec::rest();
+
+    if let PositionalSpec::Dynamic {name, ..} = &mut target {
+        *name = "target".to_string();
+    }
+
+    CommandSpec {
+        primary_path: vec!["plugin".to_string(), "greet".to_string()],
+        components: vec![
+            Component::Option(OptionSpec::boolean("--loud")),
+            Component::Option(OptionSpec {is_required: false, ..OptionSpec::parametrized("--greeting,-g")}),
+            Component::Option(OptionSpec {is_required: false, ..OptionSpec::parametrized("--repeat")}),
+            Component::Positional(target),
+        ],
+        ..Default::default()
+    }
+}
+
+fn run(args: &[&str]) -> (ExitCode, Vec<String>) {
+    let output
+        = Arc::new(Mutex::new(vec![]));
+
+    let command
+        = GreetCommand {output: output.clone()};
+
+    let env = Environment::default()
+        .with_argv(args.iter().map(|arg| arg.to_string()).collect())
+        .with_dynamic_command(greet_spec(), Box::new(command));
+
+    let exit_code
+        = <MyCli as Cli>::run(env);
+
+    let output
+        = output.lock().unwrap().clone();
+
+    (exit_code, output)
+}
+
+#[test]
+fn it_runs_dynamic_commands() {
+    let (exit_code, output)
+        = run(&["plugin", "greet", "--loud", "-g", "Hi", "Alice", "Bob"]);
+
+    assert_eq!(exit_code, ExitCode::from(20));
+    assert_eq!(output, vec!["Hi Alice!", "Hi Bob!"]);
+}
+
+#[test]
+fn it_parses_dynamic_values() {
+    let (_, output)
+        = run(&["plugin", "greet", "--repeat", "2", "Alice"]);
+
+    assert_eq!(output, vec!["Hello Alice", "Hello Alice"]);
+}
+
+#[test]
+fn it_reports_invalid_dynamic_values() {
+    let (exit_code, output)
+        = run(&["plugin", "greet", "--repeat", "twice", "Alice"]);
+
+    assert_eq!(exit_code, ExitCode::FAILURE);
+    assert!(output.is_empty());
+}
+
+#[test]
+fn it_keeps_running_derived_commands() {
+    let (exit_code, output)
+        = run(&["build"]);
+
+    assert_eq!(exit_code, ExitCode::from(10));
+    assert!(output.is_empty());
+}
+
+#[test]
+fn it_accepts_shared_specs() {
+    let spec
+        = Arc::new(greet_spec());
+
+    let output
+        = Arc::new(Mutex::new(vec![]));
+
+    let env = Environment::default()
+        .with_argv(vec!["plugin".to_string(), "greet".to_string(), "Carol".to_string()])
+        .with_dynamic_command(spec.clone(), Box::new(GreetCommand {output: output.clone()}));
+
+    assert_eq!(<MyCli as Cli>::run(env), ExitCode::from(20));
+    assert_eq!(*output.lock().unwrap(), vec!["Hello Carol"]);
+    assert_eq!(Arc::strong_count(&spec), 1);
+}
@@ -0,0 +1,118 @@
+use std::collections::HashMap;
+
+use clipanion::{advanced::Environment, core::{CommandError, SelectionResult}, details::{CliEnums, CommandProvider}, format::Formatter, prelude::*, Error};
+
+mod common;
+
+#[cli::command]
+#[cli::path("deploy")]
+struct DeployCommand {
+    /// The token used to authenticate.
+    #[cli::option("--token", env = "MYTOOL_TOKEN")]
+    token: String,
+
+    /// The number of retries.
+    #[cli::option("--retries", default = 3)]
+    retries: usize,
+
+    #[cli::option("--dry-run", default = false)]
+    dry_run: bool,
+}
+
+impl DeployCommand {
+    fn execute(&self) {
+    }
+}
+
+#[cli::program]
+enum MyCli {
+    Deploy(DeployCommand),
+}
+
+fn env(args: &[&str], env_vars: &[(&str, &str)]) -> Environment {
+    Environment::default()
+        .with_argv(args.iter().map(|arg| arg.to_string()).collect())
+        .with_env_vars(env_vars.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect::<HashMap<_, _>>())
+}
+
+fn parse(env: &Environment) -> Result<DeployCommand, Error<'_>> {
+    let cli
+        = MyCli::build_cli().unwrap();
+
+    match MyCli::parse_args(&cli, env)? {
+        SelectionResult::Command(_, _, command) => {
+            let MyCli::Deploy(command) = <MyCli as CliEnums>::Enum::try_from(command).unwrap();
+            Ok(command)
+        },
+
+        SelectionResult::Builtin(builtin) => {
+            panic!("expected command, got builtin: {:?}", builtin);
+        },
+    }
+}
+
+#[test]
+fn it_reads_options_from_the_environment() {
+    let command = parse(&env(&["deploy"], &[("MYTOOL_TOKEN", "secret")])).unwrap();
+
+    assert_eq!(command.token, "secret");
+    assert_eq!(command.retries, 3);
+}
+
+#[test]
+fn it_favors_the_command_line_over_the_environment() {
+    let command = parse(&env(&["deploy", "--token", "cli"], &[("MYTOOL_TOKEN", "secret")])).unwrap();
+
+    assert_eq!(command.token, "cli");
+}
+
+#[test]
+fn it_still_requires_missing_options() {
+    let env = env(&["deploy"], &[]);
+
+    assert_eq!(parse(&env).err(), Some(Error::CommandError(DeployCommand::command_spec().unwrap(), CommandError::MissingOptionArguments(vec!["--token".to_string()]))));
+}
+
+#[test]
+fn it_derives_variable_names_from_the_program_prefix() {
+    let env = env(&["deploy"], &[("MYTOOL_TOKEN", "secret"), ("MYTOOL_RETRIES", "5"), ("MYTOOL_DRY_RUN", "1")])
+        .with_env_prefix("MYTOOL".to_string());
+
+    let command = parse(&env).unwrap();
+
+    assert_eq!(command.retries, 5);
+    assert!(command.dry_run);
+}
+
+#[test]
+fn it_hydrates_environment_values_with_from_str() {
+    let env = env(&["deploy"], &[("MYTOOL_TOKEN", "secret"), ("MYTOOL_RETRIES", "many")])
+        .with_env_prefix("MYTOOL".to_string());
+
+    assert!(matches!(parse(&env), Err(Error::CommandError(_, _))));
+}
+
+#[cfg(unix)]
+#[test]
+fn it_skips_the_variables_that_arent_utf8() {
+    use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+
+    std::env::set_var("CLIPANION_TEST_NOT_UTF8", OsStr::from_bytes(b"\xff\xfe"));
+
+    let env
+        = Environment::default();
+
+    assert!(!env.env_vars.contains_key("CLIPANION_TEST_NOT_UTF8"));
+}
+
+#[test]
+fn it_shows_the_variables_in_the_help() {
+    let info
+        = common::info();
+
+    let commands = MyCli::registered_commands().unwrap();
+    let help = Formatter::<MyCli>::format_command_help(&info, &commands[0].with_env_prefix(Some("MYTOOL")));
+
+    assert!(help.contains("  --token <arg>    The token used to authenticate. (required) [env: MYTOOL_TOKEN]\n"));
+    assert!(help.contains("  --retries <arg>  The number of retries. [default: 3] [env: MYTOOL_RETRIES]\n"));
+}
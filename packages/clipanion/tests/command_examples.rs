@@ -0,0 +1,55 @@
+use clipanion::{core::Example, details::CommandProvider, format::Formatter, prelude::*};
+
+mod common;
+
+/// Add file contents to the index.
+#[cli::command]
+#[cli::path("add")]
+#[cli::example(command = "my-cli add .", description = "Add all files to the index.")]
+#[cli::example(command = "my-cli add src/main.rs", description = "Add a single file to the index.")]
+struct AddCommand {
+    paths: Vec<String>,
+}
+
+impl AddCommand {
+    fn execute(&self) {
+    }
+}
+
+#[cli::program]
+enum MyCli {
+    Add(AddCommand),
+}
+
+#[test]
+fn it_records_the_examples_in_order() {
+    let command_spec = AddCommand::command_spec().unwrap();
+
+    assert_eq!(command_spec.examples, vec![
+        Example {
+            command: "my-cli add .".to_string(),
+            description: "Add all files to the index.".to_string(),
+        },
+        Example {
+            command: "my-cli add src/main.rs".to_string(),
+            description: "Add a single file to the index.".to_string(),
+        },
+    ]);
+}
+
+#[test]
+fn it_shows_the_examples_in_the_help_page() {
+    let commands = MyCli::registered_commands().unwrap();
+    let help = Formatter::<MyCli>::format_command_help(&common::info(), commands[0]);
+
+    assert!(help.ends_with(&[
+        "━━━ Examples ━━━",
+        "",
+        "  Add all files to the index.",
+        "    $ my-cli add .",
+        "",
+        "  Add a single file to the index.",
+        "    $ my-cli add src/main.rs",
+        "",
+    ].join("\n")));
+}
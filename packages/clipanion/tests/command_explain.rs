@@ -0,0 +1,141 @@
+use clipanion::{advanced::Environment, core::{BuiltinCommand, SelectionResult, TraceEvent}, details::{parse_args_with_trace, CommandProvider}, explain::render_explanation, prelude::*};
+
+mod common;
+
+#[cli::command]
+#[cli::path("config", "get")]
+struct ConfigGetCommand {
+    #[cli::option("--json", default = false)]
+    json: bool,
+
+    name: String,
+}
+
+impl ConfigGetCommand {
+    fn execute(&self) {
+    }
+}
+
+#[cli::command]
+#[cli::path("config", "get")]
+struct ConfigGetAllCommand {
+    #[cli::option("--all")]
+    all: bool,
+}
+
+impl ConfigGetAllCommand {
+    fn execute(&self) {
+    }
+}
+
+#[cli::command(default)]
+struct DefaultCommand {
+    args: Vec<String>,
+}
+
+impl DefaultCommand {
+    fn execute(&self) {
+    }
+}
+
+#[cli::program]
+enum MyCli {
+    ConfigGet(ConfigGetCommand),
+    ConfigGetAll(ConfigGetAllCommand),
+    Default(DefaultCommand),
+}
+
+fn explain(argv: &[&str]) -> String {
+    let cli = MyCli::build_cli().unwrap();
+    let env = Environment::default()
+        .with_argv(argv.iter().map(|arg| arg.to_string()).collect());
+
+    let mut trace: Vec<TraceEvent>
+        = vec![];
+
+    let result
+        = parse_args_with_trace(&cli, &env, Some(&mut trace), |state| MyCli::hydrate_command(state.context_id, &env, state));
+
+    render_explanation(&common::info(), cli.commands(), argv, &trace, &result)
+}
+
+#[test]
+fn it_selects_the_explain_builtin() {
+    let cli = MyCli::build_cli().unwrap();
+    let env = Environment::default()
+        .with_argv(vec!["--clipanion-explain".to_string(), "config".to_string(), "get".to_string(), "foo".to_string()]);
+
+    let Ok(SelectionResult::Builtin(BuiltinCommand::Explain(command_line, is_json))) = MyCli::parse_args(&cli, &env) else {
+        panic!("Expected an explain request");
+    };
+
+    assert_eq!(command_line, vec!["config", "get", "foo"]);
+    assert!(!is_json);
+}
+
+#[test]
+fn it_selects_the_json_explain_builtin() {
+    let cli = MyCli::build_cli().unwrap();
+    let env = Environment::default()
+        .with_argv(vec!["--clipanion-explain=json".to_string(), "config".to_string()]);
+
+    let Ok(SelectionResult::Builtin(BuiltinCommand::Explain(command_line, is_json))) = MyCli::parse_args(&cli, &env) else {
+        panic!("Expected an explain request");
+    };
+
+    assert_eq!(command_line, vec!["config"]);
+    assert!(is_json);
+}
+
+#[test]
+fn it_explains_why_the_other_commands_were_dropped() {
+    let explanation
+        = explain(&["config", "get", "foo"]);
+
+    assert!(explanation.contains("$ my-cli config get foo"));
+
+    // The default command takes the first argument as a positional, which
+    // makes it lose against the commands matching it as a keyword
+    assert!(explanation.contains("✗ #2 (default): trimmed, matched 0 keywords against 1"));
+
+    // The second command doesn't accept positional arguments
+    assert!(explanation.contains("(#1 config get): didn't match the command line"));
+
+    assert!(explanation.lines().any(|line| line.starts_with("  ✓ state") && line.ends_with("(#0 config get)")));
+    assert!(explanation.contains("Runs #0 config get"));
+}
+
+#[test]
+fn it_explains_errors() {
+    let explanation
+        = explain(&["config", "get", "--json"]);
+
+    assert!(explanation.contains("━━━ Result ━━━"));
+    assert!(explanation.contains("Error: "));
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn it_explains_as_json() {
+    use clipanion::explain::render_explanation_json;
+
+    let argv
+        = ["config", "get", "foo"];
+
+    let cli = MyCli::build_cli().unwrap();
+    let env = Environment::default()
+        .with_argv(argv.iter().map(|arg| arg.to_string()).collect());
+
+    let mut trace: Vec<TraceEvent>
+        = vec![];
+
+    let result
+        = parse_args_with_trace(&cli, &env, Some(&mut trace), |state| MyCli::hydrate_command(state.context_id, &env, state));
+
+    let explanation: serde_json::Value
+        = serde_json::from_str(&render_explanation_json(cli.commands(), &argv, &trace, &result).unwrap()).unwrap();
+
+    assert_eq!(explanation["argv"], serde_json::json!(["config", "get", "foo"]));
+    assert_eq!(explanation["result"], serde_json::json!({"type": "command", "commandId": 0}));
+    assert_eq!(explanation["events"][0], serde_json::json!({"type": "startOfInput"}));
+}
@@ -0,0 +1,100 @@
+use clipanion::{advanced::Environment, core::{BuiltinCommand, SelectionResult}, details::CommandProvider, format::Formatter, prelude::*};
+
+mod common;
+
+/// Retrieve the value of a configuration variable.
+///
+/// The value is printed on the standard output.
+///
+/// Variables that aren't set are reported as errors.
+#[cli::command]
+#[cli::path("config", "get")]
+#[cli::path("get")]
+struct ConfigGetCommand {
+    /// Print the value as JSON.
+    #[cli::option("--json", default = false)]
+    json: bool,
+
+    /// The working directory.
+    #[cli::option("-C,--cwd")]
+    cwd: String,
+
+    /// The name of the configuration variable.
+    name: String,
+}
+
+impl ConfigGetCommand {
+    fn execute(&self) {
+    }
+}
+
+#[cli::command(default)]
+struct DefaultCommand {
+    args: Vec<String>,
+}
+
+impl DefaultCommand {
+    fn execute(&self) {
+    }
+}
+
+#[cli::program]
+enum MyCli {
+    ConfigGet(ConfigGetCommand),
+    Default(DefaultCommand),
+}
+
+#[test]
+fn it_renders_the_full_help_page() {
+    let command_spec = ConfigGetCommand::command_spec().unwrap();
+    let help = Formatter::<MyCli>::format_command_help(&common::info(), command_spec);
+
+    assert_eq!(help, [
+        "━━━ Usage ━━━",
+        "",
+        "  › my-cli config get [options] <NAME>",
+        "  › my-cli get [options] <NAME>",
+        "",
+        "  Retrieve the value of a configuration variable.",
+        "",
+        "  The value is printed on the standard output.",
+        "",
+        "  Variables that aren't set are reported as errors.",
+        "",
+        "━━━ Arguments ━━━",
+        "",
+        "  <NAME>  The name of the configuration variable. (required)",
+        "",
+        "━━━ Options ━━━",
+        "",
+        "  --json          Print the value as JSON. [default: false]",
+        "  --cwd,-C <arg>  The working directory. (required)",
+        "",
+    ].join("\n"));
+}
+
+#[test]
+fn it_selects_the_help_page_of_the_requested_command() {
+    let cli = MyCli::build_cli().unwrap();
+    let env = Environment::default()
+        .with_argv(vec!["config".to_string(), "get".to_string(), "--help".to_string()]);
+
+    let Ok(SelectionResult::Builtin(BuiltinCommand::Help(commands))) = MyCli::parse_args(&cli, &env) else {
+        panic!("Expected a help request");
+    };
+
+    assert_eq!(commands, vec![ConfigGetCommand::command_spec().unwrap()]);
+}
+
+#[test]
+fn it_keeps_the_general_listing_for_the_default_command() {
+    let cli = MyCli::build_cli().unwrap();
+    let env = Environment::default()
+        .with_argv(vec!["--help".to_string()]);
+
+    let Ok(SelectionResult::Builtin(BuiltinCommand::Help(commands))) = MyCli::parse_args(&cli, &env) else {
+        panic!("Expected a help request");
+    };
+
+    assert_eq!(commands.len(), 2);
+}
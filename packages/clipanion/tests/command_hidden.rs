@@ -0,0 +1,125 @@
+use std::collections::HashMap;
+
+use clipanion::{advanced::Environment, completion::{generate_completion_script, Shell}, core::{CliBuilder, Completion, Error, SelectionResult}, details::{CliEnums, CommandProvider}, markdown::render_markdown, prelude::*, test_cli_failure, test_cli_success};
+
+mod common;
+
+/// Deploy the application.
+#[cli::command]
+#[cli::path("deploy")]
+struct DeployCommand {
+    #[cli::option("--force", default = false)]
+    force: bool,
+
+    #[cli::option("--debug-token", hidden, env = "MYTOOL_DEBUG_TOKEN")]
+    debug_token: Option<String>,
+}
+
+impl DeployCommand {
+    fn execute(&self) {
+    }
+}
+
+/// Dump the internal state.
+#[cli::command]
+#[cli::path("debug-dump")]
+#[cli::hidden]
+struct DebugDumpCommand {
+}
+
+impl DebugDumpCommand {
+    fn execute(&self) {
+    }
+}
+
+#[cli::program]
+enum MyCli {
+    Deploy(DeployCommand),
+    DebugDump(DebugDumpCommand),
+}
+
+fn completions(args: &[&str], cursor: usize) -> Vec<String> {
+    let commands
+        = MyCli::registered_commands().unwrap();
+
+    let mut builder
+        = CliBuilder::new();
+
+    for command_spec in &commands {
+        builder.add_command(command_spec);
+    }
+
+    builder.complete(args, cursor).into_iter()
+        .filter_map(|completion| match completion {
+            Completion::Keyword {value} | Completion::Option {value, ..} => Some(value),
+            _ => None,
+        })
+        .collect()
+}
+
+test_cli_success!(it_still_parses_hidden_commands, MyCli, DebugDumpCommand, &["debug-dump"], |_| {
+});
+
+test_cli_success!(it_still_parses_hidden_options, MyCli, DeployCommand, &["deploy", "--debug-token", "abc"], |command| {
+    assert_eq!(command.debug_token.as_deref(), Some("abc"));
+});
+
+test_cli_failure!(it_excludes_hidden_commands_from_the_not_found_errors, MyCli, &[], |error| {
+    assert_eq!(error, Error::NotFound(vec![DeployCommand::command_spec().unwrap()]));
+});
+
+#[test]
+fn it_reads_hidden_options_from_the_environment() {
+    let cli
+        = MyCli::build_cli().unwrap();
+
+    let env = Environment::default()
+        .with_argv(vec!["deploy".to_string()])
+        .with_env_vars(HashMap::from([("MYTOOL_DEBUG_TOKEN".to_string(), "abc".to_string())]));
+
+    let SelectionResult::Command(_, _, command) = MyCli::parse_args(&cli, &env).unwrap() else {
+        panic!("expected a command");
+    };
+
+    let MyCli::Deploy(command) = <MyCli as CliEnums>::Enum::try_from(command).unwrap() else {
+        panic!("expected the deploy command");
+    };
+
+    assert_eq!(command.debug_token.as_deref(), Some("abc"));
+}
+
+#[test]
+fn it_excludes_hidden_items_from_the_usage() {
+    assert_eq!(DeployCommand::command_spec().unwrap().usage().usage_line, "deploy [--force]");
+}
+
+#[test]
+fn it_excludes_hidden_items_from_completions() {
+    assert_eq!(completions(&["d"], 0), vec!["deploy".to_string()]);
+    assert_eq!(completions(&["deploy", "--"], 1), vec!["--force".to_string()]);
+
+    let script
+        = generate_completion_script(Shell::Bash, "my-cli", &MyCli::registered_commands().unwrap());
+
+    assert!(!script.contains("debug"));
+}
+
+#[test]
+fn it_excludes_hidden_items_from_the_docs() {
+    let markdown
+        = render_markdown(&common::info(), &MyCli::registered_commands().unwrap());
+
+    assert!(markdown.contains("my-cli deploy"));
+    assert!(!markdown.contains("debug"));
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn it_flags_hidden_items_in_the_description() {
+    let commands
+        = serde_json::to_value(MyCli::registered_commands().unwrap()).unwrap();
+
+    assert_eq!(commands[0]["isHidden"], false);
+    assert_eq!(commands[0]["components"][2]["isHidden"], true);
+    assert_eq!(commands[1]["isHidden"], true);
+}
@@ -0,0 +1,83 @@
+use clipanion::{prelude::*, test_cli_failure, test_cli_success};
+
+#[cli::command]
+#[cli::path("grep")]
+struct GrepCommand {
+    #[cli::option("-v,--verbose", default = false)]
+    verbose: bool,
+
+    #[cli::option("--pattern", allow_hyphen_values)]
+    pattern: String,
+
+    #[cli::positional(allow_hyphen_values)]
+    files: Vec<String>,
+}
+
+impl GrepCommand {
+    fn execute(&self) {
+    }
+}
+
+#[cli::command]
+#[cli::path("seek")]
+struct SeekCommand {
+    #[cli::option("-1,--once", default = false)]
+    once: bool,
+
+    #[cli::option("--by")]
+    by: Option<f64>,
+
+    offset: Option<i64>,
+}
+
+impl SeekCommand {
+    fn execute(&self) {
+    }
+}
+
+#[cli::program]
+enum MyCli {
+    Grep(GrepCommand),
+    Seek(SeekCommand),
+}
+
+#[cli::program(negative_numbers)]
+enum NumericCli {
+    Seek(SeekCommand),
+}
+
+test_cli_success!(it_accepts_hyphen_values_for_options, MyCli, GrepCommand, &["grep", "--pattern", "-foo"], |command| {
+    assert_eq!(command.pattern, "-foo");
+});
+
+test_cli_success!(it_accepts_option_names_as_option_values, MyCli, GrepCommand, &["grep", "--pattern", "--verbose"], |command| {
+    assert_eq!(command.pattern, "--verbose");
+    assert!(!command.verbose);
+});
+
+test_cli_success!(it_accepts_hyphen_values_for_positionals, MyCli, GrepCommand, &["grep", "--pattern", "foo", "-", "-bar", "--baz"], |command| {
+    assert_eq!(command.files, vec!["-", "-bar", "--baz"]);
+});
+
+test_cli_success!(it_still_parses_known_options_among_hyphen_positionals, MyCli, GrepCommand, &["grep", "--pattern", "foo", "-bar", "-v"], |command| {
+    assert_eq!(command.files, vec!["-bar"]);
+    assert!(command.verbose);
+});
+
+test_cli_failure!(it_rejects_negative_numbers_by_default, MyCli, &["seek", "-5"], |_| {});
+
+test_cli_success!(it_accepts_negative_numbers_when_enabled, NumericCli, SeekCommand, &["seek", "-5"], |command| {
+    assert_eq!(command.offset, Some(-5));
+});
+
+test_cli_success!(it_accepts_negative_numbers_as_option_values, NumericCli, SeekCommand, &["seek", "--by", "-2.5", "10"], |command| {
+    assert_eq!(command.by, Some(-2.5));
+    assert_eq!(command.offset, Some(10));
+});
+
+test_cli_success!(it_prefers_options_over_negative_numbers, NumericCli, SeekCommand, &["seek", "-1"], |command| {
+    assert!(command.once);
+    assert_eq!(command.offset, None);
+});
+
+test_cli_failure!(it_still_rejects_unknown_options_when_accepting_negative_numbers, NumericCli, &["seek", "-x"], |_| {});
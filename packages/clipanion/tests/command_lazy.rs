@@ -0,0 +1,84 @@
+use std::sync::Arc;
+
+use clipanion::{details::CommandProvider, prelude::*, test_cli_failure, test_cli_success};
+
+#[cli::command]
+#[cli::path("db", "migrate")]
+struct DbMigrateCommand {
+    #[cli::option("--dry-run", default = false)]
+    dry_run: bool,
+}
+
+impl DbMigrateCommand {
+    fn execute(&self) {
+    }
+}
+
+#[cli::command]
+#[cli::path("db")]
+struct DbCommand {
+    args: Vec<String>,
+}
+
+impl DbCommand {
+    fn execute(&self) {
+    }
+}
+
+#[cli::command]
+#[cli::path("serve")]
+struct ServeCommand {
+    #[cli::option("--port")]
+    port: Option<u16>,
+}
+
+impl ServeCommand {
+    fn execute(&self) {
+    }
+}
+
+#[cli::command(default)]
+struct BuildCommand {
+    script: String,
+}
+
+impl BuildCommand {
+    fn execute(&self) {
+    }
+}
+
+#[cli::program(lazy)]
+enum MyCli {
+    DbMigrate(DbMigrateCommand),
+    Db(DbCommand),
+    Serve(ServeCommand),
+    Build(BuildCommand),
+}
+
+#[test]
+fn it_doesnt_precompile_the_machine() {
+    let first_builder
+        = MyCli::build_cli().unwrap();
+    let second_builder
+        = MyCli::build_cli().unwrap();
+
+    assert!(!Arc::ptr_eq(&first_builder.machine(), &second_builder.machine()));
+}
+
+test_cli_success!(it_runs_the_deepest_command, MyCli, DbMigrateCommand, &["db", "migrate", "--dry-run"], |command| {
+    assert!(command.dry_run);
+});
+
+test_cli_success!(it_runs_the_parent_command, MyCli, DbCommand, &["db", "seed", "migrate"], |command| {
+    assert_eq!(command.args, vec!["seed", "migrate"]);
+});
+
+test_cli_success!(it_runs_a_sibling_command, MyCli, ServeCommand, &["serve", "--port", "8080"], |command| {
+    assert_eq!(command.port, Some(8080));
+});
+
+test_cli_success!(it_runs_the_default_command, MyCli, BuildCommand, &["build"], |command| {
+    assert_eq!(command.script, "build");
+});
+
+test_cli_failure!(it_reports_errors_within_the_namespace, MyCli, &["serve", "--port", "foo"], |_| {});
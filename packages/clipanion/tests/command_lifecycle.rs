@@ -0,0 +1,172 @@
+use std::collections::{BTreeMap, HashMap};
+
+use clipanion::{advanced::Environment, config::ConfigTable, core::{CommandError, Error, SelectionResult}, details::{deprecation_warnings, CommandProvider}, format::Formatter, prelude::*};
+
+mod common;
+
+/// Upload the build artifacts.
+#[cli::command(since = "1.2")]
+#[cli::path("upload")]
+struct UploadCommand {
+    /// Where to upload the artifacts.
+    #[cli::option("--dest", deprecated = "use --target instead")]
+    dest: Option<String>,
+
+    #[cli::option("--target", since = "1.4")]
+    target: Option<String>,
+
+    /// Upload the artifacts in parallel.
+    #[cli::option("--parallel", default = false, experimental)]
+    parallel: bool,
+}
+
+impl UploadCommand {
+    fn execute(&self) {
+    }
+}
+
+/// Upload the build artifacts.
+#[cli::command(deprecated = "use `my-cli upload` instead")]
+#[cli::path("push")]
+struct PushCommand {
+}
+
+impl PushCommand {
+    fn execute(&self) {
+    }
+}
+
+#[cli::command(experimental)]
+#[cli::path("sync")]
+struct SyncCommand {
+}
+
+impl SyncCommand {
+    fn execute(&self) {
+    }
+}
+
+#[cli::program]
+enum MyCli {
+    Upload(UploadCommand),
+    Push(PushCommand),
+    Sync(SyncCommand),
+}
+
+fn env(args: &[&str]) -> Environment {
+    let mut env = Environment::default()
+        .with_argv(args.iter().map(|arg| arg.to_string()).collect())
+        .with_env_vars(HashMap::new());
+
+    env.info = common::info();
+    env
+}
+
+fn warnings(env: &Environment) -> Result<Vec<String>, Error<'_>> {
+    let cli
+        = MyCli::build_cli().unwrap();
+
+    match MyCli::parse_args(&cli, env)? {
+        SelectionResult::Command(command_spec, state, _) => Ok(deprecation_warnings(&env.info, command_spec, &state)),
+        SelectionResult::Builtin(builtin) => panic!("expected command, got builtin: {:?}", builtin),
+    }
+}
+
+#[test]
+fn it_warns_about_deprecated_options() {
+    assert_eq!(warnings(&env(&["upload", "--dest", "s3"])).unwrap(), vec!["`--dest` is deprecated; use --target instead".to_string()]);
+    assert_eq!(warnings(&env(&["upload", "--target", "s3"])).unwrap(), Vec::<String>::new());
+}
+
+#[test]
+fn it_warns_once_about_repeated_options() {
+    assert_eq!(warnings(&env(&["upload", "--dest", "s3", "--dest", "gcs"])).unwrap(), vec!["`--dest` is deprecated; use --target instead".to_string()]);
+}
+
+#[test]
+fn it_doesnt_warn_about_the_options_set_from_the_environment() {
+    let env = env(&["upload"])
+        .with_env_prefix("MYTOOL".to_string())
+        .with_env_vars(HashMap::from([("MYTOOL_DEST".to_string(), "s3".to_string())]));
+
+    assert_eq!(warnings(&env).unwrap(), Vec::<String>::new());
+}
+
+#[test]
+fn it_doesnt_warn_about_the_options_set_from_the_configuration() {
+    let config = ConfigTable {
+        values: BTreeMap::from([
+            ("dest".to_string(), vec!["s3".to_string()]),
+        ]),
+        tables: BTreeMap::new(),
+    };
+
+    let env = env(&["upload"])
+        .with_config(config);
+
+    assert_eq!(warnings(&env).unwrap(), Vec::<String>::new());
+}
+
+#[test]
+fn it_warns_about_deprecated_commands() {
+    assert_eq!(warnings(&env(&["push"])).unwrap(), vec!["`my-cli push` is deprecated; use `my-cli upload` instead".to_string()]);
+}
+
+#[test]
+fn it_refuses_experimental_items_by_default() {
+    let env = env(&["upload", "--parallel"]);
+
+    assert_eq!(warnings(&env).err(), Some(Error::CommandError(UploadCommand::command_spec().unwrap(), CommandError::Experimental("`--parallel`".to_string(), "CLIPANION_EXPERIMENTAL".to_string()))));
+
+    let env = self::env(&["sync"]);
+
+    assert_eq!(warnings(&env).unwrap_err().to_string(), "`my-cli sync` is experimental; set CLIPANION_EXPERIMENTAL=1 to enable it");
+}
+
+#[test]
+fn it_refuses_experimental_options_set_from_the_environment() {
+    let env = env(&["upload"])
+        .with_env_prefix("MYTOOL".to_string())
+        .with_env_vars(HashMap::from([("MYTOOL_PARALLEL".to_string(), "1".to_string())]));
+
+    assert_eq!(warnings(&env).unwrap_err().to_string(), "`--parallel` is experimental; set MYTOOL_EXPERIMENTAL=1 to enable it");
+}
+
+#[test]
+fn it_refuses_experimental_options_set_from_the_configuration() {
+    let config = ConfigTable {
+        values: BTreeMap::from([
+            ("parallel".to_string(), vec!["true".to_string()]),
+        ]),
+        tables: BTreeMap::new(),
+    };
+
+    let env = env(&["upload"])
+        .with_config(config);
+
+    assert_eq!(warnings(&env).unwrap_err().to_string(), "`--parallel` is experimental; set CLIPANION_EXPERIMENTAL=1 to enable it");
+}
+
+#[test]
+fn it_accepts_experimental_items_once_enabled() {
+    let env = env(&["sync"])
+        .with_experimental(true);
+
+    assert!(warnings(&env).is_ok());
+
+    let env = self::env(&["upload", "--parallel"])
+        .with_env_prefix("MYTOOL".to_string())
+        .with_env_vars(HashMap::from([("MYTOOL_EXPERIMENTAL".to_string(), "1".to_string())]));
+
+    assert!(warnings(&env).is_ok());
+}
+
+#[test]
+fn it_marks_lifecycle_states_in_the_help() {
+    let help
+        = Formatter::<MyCli>::format_command_help(&common::info(), UploadCommand::command_spec().unwrap());
+
+    assert!(help.contains("  [since: 1.2]\n"), "{}", help);
+    assert!(help.contains("Where to upload the artifacts. [default: None] [deprecated: use --target instead]"), "{}", help);
+    assert!(help.contains("Upload the artifacts in parallel. [default: false] [experimental]"), "{}", help);
+}
@@ -0,0 +1,65 @@
+use clipanion::{details::CommandProvider, man::{command_page_name, render_man_pages}, prelude::*};
+
+mod common;
+
+/// Retrieve the value of a configuration variable.
+///
+/// The value is printed on the standard output.
+#[cli::command]
+#[cli::path("config", "get")]
+#[cli::category("Configuration commands")]
+struct ConfigGetCommand {
+    /// Print the value as JSON.
+    #[cli::option("--json", default = false)]
+    json: bool,
+
+    /// The name of the configuration variable.
+    name: String,
+}
+
+impl ConfigGetCommand {
+    fn execute(&self) {
+    }
+}
+
+#[cli::program]
+enum MyCli {
+    ConfigGet(ConfigGetCommand),
+}
+
+#[test]
+fn it_renders_one_page_per_command_plus_an_index() {
+    let commands = MyCli::registered_commands().unwrap();
+    let pages = render_man_pages(&common::info(), &commands);
+
+    assert_eq!(pages.iter().map(|page| page.file_name()).collect::<Vec<_>>(), vec![
+        "my-cli.1",
+        "my-cli-config-get.1",
+    ]);
+
+    assert_eq!(command_page_name(&common::info(), commands[0]), "my-cli-config-get");
+}
+
+#[test]
+fn it_renders_the_command_page() {
+    let commands = MyCli::registered_commands().unwrap();
+    let pages = render_man_pages(&common::info(), &commands);
+    let page = &pages[1].content;
+
+    assert!(page.starts_with(".TH \"MY\\-CLI\\-CONFIG\\-GET\" \"1\" \"\" \"My CLI 1.2.3\" \"Configuration commands\"\n"));
+    assert!(page.contains(".SH NAME\nmy\\-cli\\-config\\-get \\- Retrieve the value of a configuration variable.\n"));
+    assert!(page.contains(".SH DESCRIPTION\n.PP\nThe value is printed on the standard output.\n"));
+    assert!(page.contains(".SH ARGUMENTS\n.TP\n\\fINAME\\fR\nThe name of the configuration variable.\n"));
+    assert!(page.contains(".TP\n\\fB\\-\\-json\\fR\nPrint the value as JSON.\n.br\nDefault: false\n"));
+}
+
+#[test]
+fn it_renders_the_index_page() {
+    let commands = MyCli::registered_commands().unwrap();
+    let pages = render_man_pages(&common::info(), &commands);
+    let page = &pages[0].content;
+
+    assert!(page.contains(".SH NAME\nmy\\-cli \\- A tool that does things\n"));
+    assert!(page.contains(".SS Configuration commands\n"));
+    assert!(page.contains("See \\fBmy\\-cli\\-config\\-get\\fR(1).\n"));
+}
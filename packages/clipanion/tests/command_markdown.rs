@@ -0,0 +1,101 @@
+use clipanion::{details::CommandProvider, markdown::render_markdown, prelude::*};
+
+mod common;
+
+/// Retrieve the value of a configuration variable.
+///
+/// The value is printed on the standard output.
+#[cli::command]
+#[cli::path("config", "get")]
+#[cli::category("Configuration commands")]
+struct ConfigGetCommand {
+    /// Print the value as JSON.
+    #[cli::option("--json", default = false)]
+    json: bool,
+
+    /// The name of the configuration variable.
+    name: String,
+}
+
+impl ConfigGetCommand {
+    fn execute(&self) {
+    }
+}
+
+/// Record changes to the repository.
+#[cli::command]
+#[cli::path("ci")]
+#[cli::path("commit")]
+struct CommitCommand {
+    /// Use the given message as the commit message.
+    #[cli::option("-m,--message")]
+    message: String,
+}
+
+impl CommitCommand {
+    fn execute(&self) {
+    }
+}
+
+#[cli::program]
+enum MyCli {
+    ConfigGet(ConfigGetCommand),
+    Commit(CommitCommand),
+}
+
+const EXPECTED: &str = r#"# My CLI
+
+A tool that does things
+
+## General commands
+
+### `my-cli commit`
+
+```
+my-cli commit <--message,-m <arg>>
+```
+
+Record changes to the repository.
+
+Aliases: `my-cli ci`
+
+#### Options
+
+| Option | Description | Default |
+| --- | --- | --- |
+| `--message`, `-m` | Use the given message as the commit message. | *required* |
+
+## Configuration commands
+
+### `my-cli config get`
+
+```
+my-cli config get [--json] <NAME>
+```
+
+Retrieve the value of a configuration variable.
+
+The value is printed on the standard output.
+
+#### Arguments
+
+| Argument | Description |
+| --- | --- |
+| `NAME` | The name of the configuration variable. |
+
+#### Options
+
+| Option | Description | Default |
+| --- | --- | --- |
+| `--json` | Print the value as JSON. | `false` |
+"#;
+
+#[test]
+fn it_renders_a_deterministic_reference() {
+    let info
+        = common::info();
+
+    let commands = MyCli::registered_commands().unwrap();
+
+    assert_eq!(render_markdown(&info, &commands), EXPECTED);
+}
@@ -0,0 +1,167 @@
+use std::process::ExitCode;
+
+use clipanion::{advanced::{Cli, Environment}, core::SelectionResult, details::{CliEnums, CommandProvider}, prelude::*, test_cli_failure};
+
+#[cli::command]
+#[cli::path("read")]
+#[cli::path("get")]
+struct ConfigGetCommand {
+    #[cli::option("--json", default = false)]
+    json: bool,
+
+    name: String,
+}
+
+impl ConfigGetCommand {
+    fn execute(&self) -> ExitCode {
+        ExitCode::from(42)
+    }
+}
+
+#[cli::command]
+#[cli::path("set")]
+struct ConfigSetCommand {
+    name: String,
+    value: String,
+}
+
+impl ConfigSetCommand {
+    fn execute(&self) {
+    }
+}
+
+#[cli::command(default)]
+struct ConfigListCommand {
+}
+
+impl ConfigListCommand {
+    fn execute(&self) {
+    }
+}
+
+#[cli::program]
+enum ConfigCli {
+    Get(ConfigGetCommand),
+    Set(ConfigSetCommand),
+    List(ConfigListCommand),
+}
+
+#[cli::command]
+#[cli::path("ls")]
+struct CacheListCommand {
+}
+
+impl CacheListCommand {
+    fn execute(&self) {
+    }
+}
+
+#[cli::program]
+enum CacheCli {
+    List(CacheListCommand),
+}
+
+#[cli::program]
+enum PluginCli {
+    Cache(#[cli::mount("cache")] CacheCli),
+}
+
+#[cli::command]
+#[cli::path("build")]
+struct BuildCommand {
+}
+
+impl BuildCommand {
+    fn execute(&self) {
+    }
+}
+
+#[cli::program]
+enum MyCli {
+    Build(BuildCommand),
+    Config(#[cli::mount("config")] ConfigCli),
+    Plugin(#[cli::mount("plugin", "tools")] PluginCli),
+}
+
+fn parse(args: &[&str]) -> MyCli {
+    let cli
+        = MyCli::build_cli().unwrap();
+
+    let env = Environment::default()
+        .with_argv(args.iter().map(|arg| arg.to_string()).collect());
+
+    match MyCli::parse_args(&cli, &env).unwrap() {
+        SelectionResult::Command(_, _, command) => {
+            <MyCli as CliEnums>::Enum::try_from(command).unwrap()
+        },
+
+        SelectionResult::Builtin(builtin) => {
+            panic!("expected command, got builtin: {:?}", builtin);
+        },
+    }
+}
+
+#[test]
+fn it_registers_the_mounted_commands_under_their_prefix() {
+    let paths = MyCli::registered_commands().unwrap()
+        .into_iter()
+        .map(|command| command.primary_path.join(" "))
+        .collect::<Vec<_>>();
+
+    assert_eq!(paths, vec!["build", "config get", "config set", "config", "plugin tools cache ls"]);
+    assert_eq!(MyCli::COMMAND_COUNT, 5);
+
+    assert_eq!(MyCli::registered_commands().unwrap()[1].aliases, vec![vec!["config".to_string(), "read".to_string()]]);
+}
+
+#[test]
+fn it_hydrates_the_mounted_commands() {
+    let MyCli::Config(ConfigCli::Get(command)) = parse(&["config", "get", "--json", "user.name"]) else {
+        panic!("expected the config get command");
+    };
+
+    assert!(command.json);
+    assert_eq!(command.name, "user.name");
+
+    let MyCli::Config(ConfigCli::Get(command)) = parse(&["config", "read", "user.email"]) else {
+        panic!("expected the config get command");
+    };
+
+    assert_eq!(command.name, "user.email");
+
+    let MyCli::Config(ConfigCli::Set(command)) = parse(&["config", "set", "user.name", "John"]) else {
+        panic!("expected the config set command");
+    };
+
+    assert_eq!(command.name, "user.name");
+    assert_eq!(command.value, "John");
+}
+
+#[test]
+fn it_mounts_default_commands_on_the_prefix() {
+    assert!(matches!(parse(&["config"]), MyCli::Config(ConfigCli::List(_))));
+}
+
+#[test]
+fn it_mounts_programs_recursively() {
+    assert!(matches!(parse(&["plugin", "tools", "cache", "ls"]), MyCli::Plugin(PluginCli::Cache(CacheCli::List(_)))));
+    assert!(matches!(parse(&["build"]), MyCli::Build(_)));
+}
+
+#[test]
+fn it_executes_the_mounted_commands() {
+    let env = Environment::default()
+        .with_argv(vec!["config".to_string(), "get".to_string(), "user.name".to_string()]);
+
+    assert_eq!(<MyCli as Cli>::run(env), ExitCode::from(42));
+}
+
+#[test]
+fn it_uses_the_prefixed_path_in_usage() {
+    let usage
+        = MyCli::command_usage(2, Default::default()).unwrap();
+
+    assert!(usage.usage_line.contains("config set <NAME> <VALUE>"), "{}", usage.usage_line);
+}
+
+test_cli_failure!(it_requires_the_prefix, MyCli, &["get", "user.name"], |_| {});
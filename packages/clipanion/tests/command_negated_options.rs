@@ -26,19 +26,19 @@ enum MyCli {
 }
 
 test_cli_success!(it_supports_negated_options, MyCli, MyCommand, &["--no-my-option"], |command| {
-    assert_eq!(command.value_default_false, false);
+    assert!(!command.value_default_false);
 });
 
 test_cli_success!(it_supports_negated_options_override_false, MyCli, MyCommand, &["--my-option", "--no-my-option"], |command| {
-    assert_eq!(command.value_default_false, false);
+    assert!(!command.value_default_false);
 });
 
 test_cli_success!(it_supports_negated_options_override_true, MyCli, MyCommand, &["--my-option", "--no-my-option", "--my-option"], |command| {
-    assert_eq!(command.value_default_false, true);
+    assert!(command.value_default_false);
 });
 
 test_cli_success!(it_supports_negated_options_default_true, MyCli, MyCommand, &["--no-my-option-default-true"], |command| {
-    assert_eq!(command.value_default_true, false);
+    assert!(!command.value_default_true);
 });
 
 test_cli_success!(it_supports_negated_options_default_none, MyCli, MyCommand, &["--no-my-option-default-none"], |command| {
@@ -0,0 +1,122 @@
+use std::collections::HashMap;
+
+use clipanion::{advanced::Environment, core::{CommandError, Error, SelectionResult}, details::{CliEnums, CommandProvider}, prelude::*, test_cli_failure, test_cli_success};
+
+#[cli::command]
+#[cli::path("export")]
+#[cli::at_least_one("--output", "--stdout")]
+struct ExportCommand {
+    #[cli::option("--json", default = false, conflicts_with = "--yaml")]
+    json: bool,
+
+    #[cli::option("--yaml", default = false)]
+    yaml: bool,
+
+    #[cli::option("--key", requires = "--cert")]
+    key: Option<String>,
+
+    #[cli::option("--cert")]
+    cert: Option<String>,
+
+    #[cli::option("--tls", default = false)]
+    tls: bool,
+
+    #[cli::option("--ca", required_if = "--tls")]
+    ca: Option<String>,
+
+    #[cli::option("--token", required_unless = ["--key", "--anonymous"])]
+    token: Option<String>,
+
+    #[cli::option("--anonymous", default = false)]
+    anonymous: bool,
+
+    #[cli::option("--output")]
+    output: Option<String>,
+
+    #[cli::option("--stdout", default = false)]
+    stdout: bool,
+}
+
+impl ExportCommand {
+    fn execute(&self) {
+    }
+}
+
+#[cli::program]
+enum MyCli {
+    Export(ExportCommand),
+}
+
+fn command_error(err: CommandError) -> Error<'static> {
+    Error::CommandError(ExportCommand::command_spec().unwrap(), err)
+}
+
+fn parse_with_env_vars(args: &[&str], env_vars: &[(&str, &str)]) -> ExportCommand {
+    let env = Environment::default()
+        .with_argv(args.iter().map(|arg| arg.to_string()).collect())
+        .with_env_prefix("MYTOOL".to_string())
+        .with_env_vars(env_vars.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect::<HashMap<_, _>>());
+
+    let cli
+        = MyCli::build_cli().unwrap();
+
+    match MyCli::parse_args(&cli, &env).unwrap() {
+        SelectionResult::Command(_, _, command) => {
+            let MyCli::Export(command) = <MyCli as CliEnums>::Enum::try_from(command).unwrap();
+            command
+        },
+
+        SelectionResult::Builtin(builtin) => {
+            panic!("expected command, got builtin: {:?}", builtin);
+        },
+    }
+}
+
+test_cli_success!(it_accepts_valid_combinations, MyCli, ExportCommand, &["export", "--json", "--key", "a", "--cert", "b", "--stdout"], |command| {
+    assert!(command.json);
+    assert_eq!(command.key.as_deref(), Some("a"));
+    assert_eq!(command.cert.as_deref(), Some("b"));
+});
+
+test_cli_failure!(it_rejects_conflicting_options, MyCli, &["export", "--json", "--yaml", "--anonymous", "--stdout"], |err| {
+    assert_eq!(err, command_error(CommandError::ConflictingOptions(vec!["--json".to_string(), "--yaml".to_string()])));
+    assert_eq!(err.to_string(), "Options --json, --yaml can't be used together");
+});
+
+test_cli_failure!(it_enforces_requirements, MyCli, &["export", "--key", "a", "--stdout"], |err| {
+    assert_eq!(err, command_error(CommandError::MissingRequirements("--key".to_string(), vec!["--cert".to_string()])));
+    assert_eq!(err.to_string(), "Option --key requires --cert");
+});
+
+test_cli_failure!(it_enforces_required_if, MyCli, &["export", "--tls", "--anonymous", "--stdout"], |err| {
+    assert_eq!(err.to_string(), "Option --ca is required when --tls is set");
+});
+
+test_cli_failure!(it_enforces_required_unless, MyCli, &["export", "--stdout"], |err| {
+    assert_eq!(err, command_error(CommandError::MissingOptionUnless("--token".to_string(), vec!["--key".to_string(), "--anonymous".to_string()])));
+    assert_eq!(err.to_string(), "Option --token is required unless --key or --anonymous is set");
+});
+
+test_cli_failure!(it_enforces_at_least_one, MyCli, &["export", "--anonymous"], |err| {
+    assert_eq!(err.to_string(), "At least one of --output, --stdout must be set");
+});
+
+#[test]
+fn it_lets_the_command_line_override_exclusive_options_from_the_environment() {
+    let command = parse_with_env_vars(&["export", "--anonymous", "--stdout"], &[("MYTOOL_YAML", "1")]);
+
+    assert!(command.yaml);
+
+    let command = parse_with_env_vars(&["export", "--json", "--anonymous", "--stdout"], &[("MYTOOL_YAML", "1")]);
+
+    assert!(command.json);
+    assert!(!command.yaml);
+}
+
+#[test]
+fn it_renders_exclusive_options_together() {
+    let command_spec
+        = ExportCommand::command_spec().unwrap();
+
+    assert_eq!(command_spec.usage().usage_line, "export [--json | --yaml] [--key <arg>] [--cert <arg>] [--tls] [--ca <arg>] [--token <arg>] [--anonymous] [--output <arg>] [--stdout]");
+}
@@ -0,0 +1,75 @@
+use clipanion::{prelude::*, test_cli_failure, test_cli_success};
+
+#[cli::command]
+#[cli::path("exec-node")]
+struct ExecNodeCommand {
+    #[cli::option("-v,--verbose", default = false)]
+    verbose: bool,
+
+    #[cli::option("--cwd")]
+    cwd: Option<String>,
+
+    /// Options forwarded to Node.js.
+    #[cli::passthrough]
+    node_options: Vec<String>,
+
+    script: String,
+
+    args: Vec<String>,
+}
+
+impl ExecNodeCommand {
+    fn execute(&self) {
+    }
+}
+
+#[cli::command]
+#[cli::path("build")]
+struct BuildCommand {
+    #[cli::option("-v,--verbose", default = false)]
+    verbose: bool,
+}
+
+impl BuildCommand {
+    fn execute(&self) {
+    }
+}
+
+#[cli::program]
+enum MyCli {
+    ExecNode(ExecNodeCommand),
+    Build(BuildCommand),
+}
+
+test_cli_success!(it_collects_unknown_options, MyCli, ExecNodeCommand, &["exec-node", "--inspect", "-v", "--max-old-space-size=4096", "script.js"], |command| {
+    assert!(command.verbose);
+    assert_eq!(command.node_options, vec!["--inspect", "--max-old-space-size=4096"]);
+    assert_eq!(command.script, "script.js");
+});
+
+test_cli_success!(it_keeps_the_original_order, MyCli, ExecNodeCommand, &["exec-node", "-x", "--cwd", "/tmp", "--b", "-a", "script.js"], |command| {
+    assert_eq!(command.cwd, Some("/tmp".to_string()));
+    assert_eq!(command.node_options, vec!["-x", "--b", "-a"]);
+});
+
+test_cli_success!(it_collects_unknown_options_after_positionals, MyCli, ExecNodeCommand, &["exec-node", "script.js", "--trace-warnings", "foo"], |command| {
+    assert_eq!(command.node_options, vec!["--trace-warnings"]);
+    assert_eq!(command.args, vec!["foo"]);
+});
+
+test_cli_success!(it_stops_collecting_after_double_dash, MyCli, ExecNodeCommand, &["exec-node", "--inspect", "script.js", "--", "--foo"], |command| {
+    assert_eq!(command.node_options, vec!["--inspect"]);
+    assert_eq!(command.args, vec!["--foo"]);
+});
+
+test_cli_failure!(it_still_rejects_unknown_options_elsewhere, MyCli, &["build", "--inspect"], |_| {});
+
+#[test]
+fn it_shows_the_passthrough_in_the_usage() {
+    use clipanion::details::CommandController;
+
+    let spec
+        = ExecNodeCommand::command_spec().unwrap();
+
+    assert_eq!(spec.usage().usage_line, "exec-node [--verbose,-v] [--cwd <arg>] [--…NODE_OPTIONS] <SCRIPT> […ARGSN]");
+}
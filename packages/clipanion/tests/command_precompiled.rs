@@ -0,0 +1,34 @@
+use std::sync::Arc;
+
+use clipanion::{details::CommandProvider, prelude::*, test_cli_success};
+
+#[cli::command]
+#[cli::path("foo")]
+struct FooCommand {
+    #[cli::option("--bar", default = false)]
+    bar: bool,
+}
+
+impl FooCommand {
+    fn execute(&self) {
+    }
+}
+
+#[cli::program]
+enum MyCli {
+    Foo(FooCommand),
+}
+
+#[test]
+fn it_compiles_the_machine_once() {
+    let first_builder
+        = MyCli::build_cli().unwrap();
+    let second_builder
+        = MyCli::build_cli().unwrap();
+
+    assert!(Arc::ptr_eq(&first_builder.machine(), &second_builder.machine()));
+}
+
+test_cli_success!(it_runs_with_the_precompiled_machine, MyCli, FooCommand, &["foo", "--bar"], |command| {
+    assert!(command.bar);
+});
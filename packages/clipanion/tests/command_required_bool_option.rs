@@ -17,7 +17,7 @@ enum MyCli {
 }
 
 test_cli_success!(it_works, MyCli, MyCommand, &["--my-option"], |command| {
-    assert_eq!(command.value, true);
+    assert!(command.value);
 });
 
 test_cli_failure!(it_requires_the_option_to_be_present, MyCli, &[], |error| {
@@ -0,0 +1,292 @@
+use std::path::PathBuf;
+
+use clipanion::{advanced::Environment, core::{ArgSource, BuiltinCommand, CommandError, Constraint, SelectionResult}, details::CliEnums, prelude::*, response_files::{parse_response_file, ResponseFileError}, Error};
+
+#[cli::command]
+#[cli::path("build")]
+struct BuildCommand {
+    #[cli::option("--define", default = vec![])]
+    define: Vec<String>,
+
+    #[cli::option("--out-dir")]
+    out_dir: Option<String>,
+
+    #[cli::option("--jobs", min = 1)]
+    jobs: Option<usize>,
+
+    files: Vec<String>,
+}
+
+impl BuildCommand {
+    fn execute(&self) {
+    }
+}
+
+#[cli::program]
+enum MyCli {
+    Build(BuildCommand),
+}
+
+struct TempRoot(PathBuf);
+
+impl TempRoot {
+    fn new(name: &str) -> Self {
+        let path = std::env::temp_dir()
+            .join(format!("clipanion-response-files-{}-{}", name, std::process::id()));
+
+        let _ = std::fs::remove_dir_all(&path);
+        std::fs::create_dir_all(&path).unwrap();
+
+        Self(path)
+    }
+
+    fn write(&self, path: &str, content: &str) -> String {
+        let path
+            = self.0.join(path);
+
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, content).unwrap();
+
+        path.display().to_string()
+    }
+}
+
+impl Drop for TempRoot {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+fn expand(args: &[&str]) -> Result<Environment, ResponseFileError> {
+    Environment::default()
+        .with_argv(args.iter().map(|arg| arg.to_string()).collect())
+        .with_response_files(true)
+        .expand_response_files()
+}
+
+fn parse(env: &Environment) -> BuildCommand {
+    let cli
+        = MyCli::build_cli().unwrap();
+
+    let result: Result<_, Error> = MyCli::parse_args(&cli, env);
+
+    match result.unwrap() {
+        SelectionResult::Command(_, _, command) => {
+            let MyCli::Build(command) = <MyCli as CliEnums>::Enum::try_from(command).unwrap();
+            command
+        },
+
+        SelectionResult::Builtin(builtin) => {
+            panic!("expected command, got builtin: {:?}", builtin);
+        },
+    }
+}
+
+#[test]
+fn it_splits_arguments_following_the_quoting_rules() {
+    let args = parse_response_file(&PathBuf::from("args.rsp"), concat!(
+        "# comment\n",
+        "--define 'A=hello world'\n",
+        "  --define \"B=say \\\"hi\\\"\" C\\ D\n",
+        "'multi\nline' last\n",
+    )).unwrap();
+
+    assert_eq!(args, vec![
+        ("--define".to_string(), 2),
+        ("A=hello world".to_string(), 2),
+        ("--define".to_string(), 3),
+        ("B=say \"hi\"".to_string(), 3),
+        ("C D".to_string(), 3),
+        ("multi\nline".to_string(), 4),
+        ("last".to_string(), 5),
+    ]);
+}
+
+#[test]
+fn it_reports_unterminated_quotes() {
+    let result
+        = parse_response_file(&PathBuf::from("args.rsp"), "foo\n'bar\n");
+
+    assert!(matches!(result, Err(ResponseFileError::UnterminatedQuote(_, 2))));
+}
+
+#[test]
+fn it_expands_response_files() {
+    let root = TempRoot::new("expand");
+
+    let path = root.write("args.rsp", "--define A=1\n--out-dir dist\nsrc/main.rs\n");
+
+    let env
+        = expand(&["build", &format!("@{}", path), "src/lib.rs"]).unwrap();
+
+    assert_eq!(env.argv, vec!["build", "--define", "A=1", "--out-dir", "dist", "src/main.rs", "src/lib.rs"]);
+    assert_eq!(env.arg_source(0), None);
+    assert_eq!(env.arg_source(3), Some(&ArgSource {path: path.clone(), line: 2}));
+    assert_eq!(env.arg_source(6), None);
+
+    let command
+        = parse(&env);
+
+    assert_eq!(command.define, vec!["A=1"]);
+    assert_eq!(command.out_dir, Some("dist".to_string()));
+    assert_eq!(command.files, vec!["src/main.rs", "src/lib.rs"]);
+}
+
+fn parse_error(env: &Environment) -> CommandError {
+    let cli
+        = MyCli::build_cli().unwrap();
+
+    match MyCli::parse_args(&cli, env) {
+        Err(Error::CommandError(_, err)) => err,
+        Err(err) => panic!("expected a command error, got {:?}", err),
+        Ok(_) => panic!("expected an error"),
+    }
+}
+
+#[test]
+fn it_points_errors_at_the_argument_at_fault() {
+    let root = TempRoot::new("errors");
+
+    let path = root.write("args.rsp", "--define A=1\n--jobs 0\n");
+
+    // The same value is also used on the command line, but isn't the one
+    // that got rejected
+    let env
+        = expand(&["build", "--define", "0", &format!("@{}", path)]).unwrap();
+
+    assert_eq!(parse_error(&env), CommandError::FromSource(ArgSource {path: path.clone(), line: 2}, Box::new(
+        CommandError::ConstraintViolation("--jobs".to_string(), "0".to_string(), Constraint::Min {value: "1".to_string()}),
+    )));
+
+    let env
+        = expand(&["build", "--jobs", "0", &format!("@{}", path)]).unwrap();
+
+    assert_eq!(parse_error(&env), CommandError::ConstraintViolation("--jobs".to_string(), "0".to_string(), Constraint::Min {value: "1".to_string()}));
+}
+
+#[test]
+fn it_points_unknown_options_at_their_source() {
+    let root = TempRoot::new("unknown");
+
+    let path = root.write("args.rsp", "--define A=1\n--jbos 2\n");
+
+    let env
+        = expand(&["build", &format!("@{}", path)]).unwrap();
+
+    let err
+        = parse_error(&env);
+
+    assert!(matches!(&err, CommandError::FromSource(source, err) if source.line == 2 && matches!(**err, CommandError::UnknownOption(..))), "{:?}", err);
+    assert!(err.to_string().ends_with(&format!("(from {}:2)", path)), "{}", err);
+}
+
+#[test]
+fn it_resolves_nested_files_relative_to_their_parent() {
+    let root = TempRoot::new("nested");
+
+    root.write("nested/defines.rsp", "--define A=1\n");
+    let path = root.write("nested/args.rsp", "@defines.rsp\nsrc/main.rs\n");
+
+    let env
+        = expand(&["build", &format!("@{}", path)]).unwrap();
+
+    assert_eq!(env.argv, vec!["build", "--define", "A=1", "src/main.rs"]);
+}
+
+#[test]
+fn it_keeps_escaped_and_trailing_arguments_as_is() {
+    let root = TempRoot::new("escape");
+
+    let path = root.write("args.rsp", "@@scope/pkg\n");
+
+    let env
+        = expand(&["build", &format!("@{}", path), "@@other", "--", "@not-a-file"]).unwrap();
+
+    assert_eq!(env.argv, vec!["build", "@scope/pkg", "@other", "--", "@not-a-file"]);
+}
+
+#[test]
+fn it_leaves_arguments_alone_when_disabled() {
+    let env = Environment::default()
+        .with_argv(vec!["build".to_string(), "@missing.rsp".to_string()])
+        .expand_response_files()
+        .unwrap();
+
+    assert_eq!(env.argv, vec!["build", "@missing.rsp"]);
+}
+
+#[test]
+fn it_detects_cycles() {
+    let root = TempRoot::new("cycle");
+
+    root.write("a.rsp", "@b.rsp\n");
+    root.write("b.rsp", "@a.rsp\n");
+
+    let result
+        = expand(&["build", &format!("@{}", root.0.join("a.rsp").display())]);
+
+    assert!(matches!(result, Err(ResponseFileError::Cycle(_))));
+}
+
+#[test]
+fn it_limits_the_nesting_depth() {
+    let root = TempRoot::new("depth");
+
+    for i in 0..5 {
+        root.write(&format!("{}.rsp", i), &format!("@{}.rsp\n", i + 1));
+    }
+
+    root.write("5.rsp", "src/main.rs\n");
+
+    let args
+        = vec!["build".to_string(), format!("@{}", root.0.join("0.rsp").display())];
+
+    let env = Environment::default()
+        .with_argv(args.clone())
+        .with_response_files_max_depth(6)
+        .expand_response_files()
+        .unwrap();
+
+    assert_eq!(env.argv, vec!["build", "src/main.rs"]);
+
+    let result = Environment::default()
+        .with_argv(args)
+        .with_response_files_max_depth(3)
+        .expand_response_files();
+
+    assert!(matches!(result, Err(ResponseFileError::TooDeep(_, 3))));
+}
+
+#[test]
+fn it_reports_missing_files() {
+    let result
+        = expand(&["build", "@/nonexistent/args.rsp"]);
+
+    assert!(matches!(result, Err(ResponseFileError::Io(..))));
+}
+
+#[test]
+fn it_doesnt_expand_the_completion_requests() {
+    let root = TempRoot::new("complete");
+
+    let path = root.write("args.rsp", "--define A=1\n");
+
+    let env
+        = expand(&["--clipanion-complete=3", "build", &format!("@{}", path), "@ar", "--out"]).unwrap();
+
+    let cli
+        = MyCli::build_cli().unwrap();
+
+    let result: Result<_, Error> = MyCli::parse_args(&cli, &env);
+
+    let Ok(SelectionResult::Builtin(BuiltinCommand::Complete(command_line, cursor))) = result else {
+        panic!("expected a completion request");
+    };
+
+    assert_eq!(cursor, 3);
+
+    let completions
+        = cli.complete(&command_line, cursor);
+
+    assert!(completions.iter().any(|completion| completion.word() == Some("--out-dir")), "{:?}", completions);
+}
@@ -34,6 +34,7 @@ impl BarCommand {
 }
 
 #[cli::program]
+#[allow(clippy::enum_variant_names)]
 enum MyCli {
     FooCommand(FooCommand),
     BazCommand(BazCommand),
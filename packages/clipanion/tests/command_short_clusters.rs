@@ -0,0 +1,55 @@
+use clipanion::{prelude::*, test_cli_success};
+
+#[cli::command(default)]
+struct MyCommand {
+    #[cli::option("-x,--extract", default = false)]
+    extract: bool,
+
+    #[cli::option("-v,--verbose", default = 0, counter)]
+    verbose: u8,
+
+    #[cli::option("-f,--file")]
+    file: Option<String>,
+
+    #[cli::option("-n,--count")]
+    count: Option<usize>,
+}
+
+impl MyCommand {
+    fn execute(&self) {
+    }
+}
+
+#[cli::program]
+enum MyCli {
+    MyCommand(MyCommand),
+}
+
+test_cli_success!(it_takes_the_value_from_the_next_argument, MyCli, MyCommand, &["-xvf", "archive.tar"], |command| {
+    assert!(command.extract);
+    assert_eq!(command.verbose, 1);
+    assert_eq!(command.file, Some("archive.tar".to_string()));
+});
+
+test_cli_success!(it_takes_the_value_from_the_rest_of_the_batch, MyCli, MyCommand, &["-xvvfarchive.tar"], |command| {
+    assert!(command.extract);
+    assert_eq!(command.verbose, 2);
+    assert_eq!(command.file, Some("archive.tar".to_string()));
+});
+
+test_cli_success!(it_supports_attached_values, MyCli, MyCommand, &["-farchive.tar"], |command| {
+    assert_eq!(command.file, Some("archive.tar".to_string()));
+});
+
+test_cli_success!(it_supports_attached_values_with_equals, MyCli, MyCommand, &["-f=archive.tar"], |command| {
+    assert_eq!(command.file, Some("archive.tar".to_string()));
+});
+
+test_cli_success!(it_supports_attached_numbers, MyCli, MyCommand, &["-n5"], |command| {
+    assert_eq!(command.count, Some(5));
+});
+
+test_cli_success!(it_still_supports_long_bindings, MyCli, MyCommand, &["--file=archive.tar", "-vv"], |command| {
+    assert_eq!(command.verbose, 2);
+    assert_eq!(command.file, Some("archive.tar".to_string()));
+});
@@ -0,0 +1,93 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use clipanion::{advanced::{Autocorrect, Cli, Environment}, core::CommandError, prelude::*, test_cli_failure, Error};
+
+static HAS_COMMITTED: AtomicBool
+    = AtomicBool::new(false);
+
+#[cli::command]
+#[cli::path("commit")]
+struct CommitCommand {
+    #[cli::option("--amend", default = false)]
+    amend: bool,
+}
+
+impl CommitCommand {
+    fn execute(&self) {
+        HAS_COMMITTED.store(true, Ordering::SeqCst);
+    }
+}
+
+#[cli::command]
+#[cli::path("config", "get")]
+struct ConfigGetCommand {
+    name: String,
+}
+
+impl ConfigGetCommand {
+    fn execute(&self) {
+    }
+}
+
+#[cli::command]
+#[cli::path("remote")]
+struct RemoteCommand {
+    #[cli::option("--url")]
+    url: String,
+
+    name: Option<String>,
+
+    rest: Vec<String>,
+}
+
+impl RemoteCommand {
+    fn execute(&self) {
+    }
+}
+
+#[cli::command]
+#[cli::path("remove")]
+struct RemoveCommand {
+}
+
+impl RemoveCommand {
+    fn execute(&self) {
+    }
+}
+
+#[cli::program]
+enum MyCli {
+    Commit(CommitCommand),
+    ConfigGet(ConfigGetCommand),
+    Remote(RemoteCommand),
+    Remove(RemoveCommand),
+}
+
+test_cli_failure!(it_only_lists_the_closest_commands, MyCli, &["comit"], |error| {
+    assert_eq!(error, Error::NotFound(vec![CommitCommand::command_spec().unwrap()]));
+});
+
+test_cli_failure!(it_suggests_close_option_names, MyCli, &["commit", "--ammend"], |error| {
+    assert_eq!(error, Error::CommandError(CommitCommand::command_spec().unwrap(), CommandError::UnknownOption("--ammend".to_string(), vec!["--amend".to_string()])));
+});
+
+test_cli_failure!(it_reports_unknown_options_without_close_names, MyCli, &["commit", "--zzz"], |error| {
+    assert_eq!(error, Error::CommandError(CommitCommand::command_spec().unwrap(), CommandError::UnknownOption("--zzz".to_string(), vec![])));
+});
+
+test_cli_failure!(it_favors_missing_options_over_close_command_names, MyCli, &["remote", "origin"], |error| {
+    assert_eq!(error, Error::CommandError(RemoteCommand::command_spec().unwrap(), CommandError::MissingOptionArguments(vec!["--url".to_string()])));
+});
+
+#[test]
+fn it_autocorrects_unambiguous_typos() {
+    let env = Environment::default()
+        .with_argv(vec!["comit".to_string(), "--amend".to_string()])
+        .with_autocorrect(Autocorrect::Delay(std::time::Duration::ZERO));
+
+    let exit_code
+        = <MyCli as Cli>::run(env);
+
+    assert_eq!(exit_code, std::process::ExitCode::SUCCESS);
+    assert!(HAS_COMMITTED.load(Ordering::SeqCst));
+}
@@ -1,4 +1,4 @@
-use clipanion::{prelude::*, test_cli_failure, test_cli_success, CommandError, Error};
+use clipanion::{prelude::*, test_cli_failure, CommandError, Error};
 
 #[cli::command(default)]
 struct MyProxyCommand {
@@ -0,0 +1,47 @@
+use clipanion::{prelude::*, test_cli_failure, Error};
+
+#[cli::command]
+#[cli::path("bar")]
+struct MyBarCommand {
+}
+
+impl MyBarCommand {
+    fn execute(&self) {
+    }
+}
+
+#[cli::command]
+#[cli::path("foo")]
+struct MyFooCommand {
+    some_value: usize,
+}
+
+impl MyFooCommand {
+    fn execute(&self) {
+    }
+}
+
+#[cli::command]
+#[cli::path("foo")]
+struct MyVerboseFooCommand {
+    #[cli::option("--verbose", default = false)]
+    verbose: bool,
+
+    some_value: usize,
+}
+
+impl MyVerboseFooCommand {
+    fn execute(&self) {
+    }
+}
+
+#[cli::program]
+enum MyCli {
+    Bar(MyBarCommand),
+    Foo(MyFooCommand),
+    VerboseFoo(MyVerboseFooCommand),
+}
+
+test_cli_failure!(it_reports_the_commands_that_failed_to_hydrate, MyCli, &["foo", "not-a-number"], |error| {
+    assert_eq!(error, Error::NotFound(vec![MyFooCommand::command_spec().unwrap(), MyVerboseFooCommand::command_spec().unwrap()]));
+});
@@ -0,0 +1,124 @@
+use clipanion::{completion::{generate_completion_script, Shell}, core::{CliBuilder, CommandError, Completion, Error}, details::CommandProvider, prelude::*, test_cli_failure, test_cli_success, ValueEnum};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+#[value(case_insensitive = true)]
+enum Format {
+    Json,
+    #[value(alias = "yml")]
+    Yaml,
+    #[value(name = "txt", alias = ["text", "plain"])]
+    PlainText,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Level {
+    Low,
+    High,
+}
+
+#[cli::command]
+#[cli::path("export")]
+struct ExportCommand {
+    #[cli::option("--format", default = Format::Json)]
+    format: Format,
+
+    level: Option<Level>,
+}
+
+impl ExportCommand {
+    fn execute(&self) {
+    }
+}
+
+#[cli::program]
+enum MyCli {
+    Export(ExportCommand),
+}
+
+test_cli_success!(it_parses_variant_names, MyCli, ExportCommand, &["export", "--format", "yaml", "high"], |command| {
+    assert_eq!(command.format, Format::Yaml);
+    assert_eq!(command.level, Some(Level::High));
+});
+
+test_cli_success!(it_parses_case_insensitive_names, MyCli, ExportCommand, &["export", "--format", "JSON"], |command| {
+    assert_eq!(command.format, Format::Json);
+    assert_eq!(command.level, None);
+});
+
+test_cli_success!(it_parses_aliases, MyCli, ExportCommand, &["export", "--format", "yml"], |command| {
+    assert_eq!(command.format, Format::Yaml);
+});
+
+test_cli_success!(it_parses_renamed_variants, MyCli, ExportCommand, &["export", "--format=plain"], |command| {
+    assert_eq!(command.format, Format::PlainText);
+});
+
+test_cli_failure!(it_rejects_unknown_values, MyCli, &["export", "--format", "xml"], |err| {
+    assert_eq!(err, Error::CommandError(ExportCommand::command_spec().unwrap(), CommandError::InvalidChoice("xml".to_string(), vec!["json".to_string(), "yaml".to_string(), "txt".to_string()])));
+    assert_eq!(err.to_string(), "Invalid value `xml`; expected one of json, yaml, txt");
+});
+
+test_cli_failure!(it_keeps_case_sensitive_enums_strict, MyCli, &["export", "HIGH"], |err| {
+    assert_eq!(err, Error::CommandError(ExportCommand::command_spec().unwrap(), CommandError::InvalidChoice("HIGH".to_string(), vec!["low".to_string(), "high".to_string()])));
+});
+
+#[test]
+fn it_records_the_choices_in_the_spec() {
+    let command_spec
+        = ExportCommand::command_spec().unwrap();
+
+    assert_eq!(command_spec.components[0].choices(), &["json".to_string(), "yaml".to_string(), "txt".to_string()]);
+    assert_eq!(command_spec.usage().usage_line, "export [--format <json|yaml|txt>] […low|high 0-1]");
+}
+
+#[test]
+fn it_completes_the_choices() {
+    let commands
+        = MyCli::registered_commands().unwrap();
+
+    let mut builder
+        = CliBuilder::new();
+
+    for command_spec in &commands {
+        builder.add_command(command_spec);
+    }
+
+    let values = builder.complete(&["export", "--format", "y"], 2).into_iter()
+        .filter_map(|completion| match completion {
+            Completion::Choice {value, ..} => Some(value),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
+    assert_eq!(values, vec!["yaml".to_string()]);
+
+    let script
+        = generate_completion_script(Shell::Fish, "my-cli", &commands);
+
+    assert!(script.contains("-l 'format' -x -a 'json yaml txt'"));
+    assert!(script.contains("-n '__my_cli_path_is \\'export\\'' -a 'high low'"));
+}
+
+#[test]
+fn it_emits_the_choices_in_every_script() {
+    let commands
+        = MyCli::registered_commands().unwrap();
+
+    let bash_script
+        = generate_completion_script(Shell::Bash, "my-cli", &commands);
+
+    assert!(bash_script.contains("positionals='high low'"));
+    assert!(bash_script.contains("'--format') value_choices='json yaml txt' ;;"));
+
+    let zsh_script
+        = generate_completion_script(Shell::Zsh, "my-cli", &commands);
+
+    assert!(zsh_script.contains("positionals=('high' 'low')"));
+    assert!(zsh_script.contains("('--format') value_choices=('json' 'yaml' 'txt') ;;"));
+
+    let powershell_script
+        = generate_completion_script(Shell::PowerShell, "my-cli", &commands);
+
+    assert!(powershell_script.contains("$positionals = @('high', 'low')"));
+    assert!(powershell_script.contains("'--format' { $valueChoices = @('json', 'yaml', 'txt') }"));
+}
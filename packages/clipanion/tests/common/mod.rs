@@ -0,0 +1,16 @@
+use clipanion::core::Info;
+
+/**
+ * The program information used by the tests rendering help pages, manuals,
+ * and references; colors are disabled so that the output can be compared as
+ * plain text.
+ */
+pub fn info() -> Info {
+    Info {
+        program_name: "My CLI".to_string(),
+        binary_name: "my-cli".to_string(),
+        version: "1.2.3".to_string(),
+        about: "A tool that does things".to_string(),
+        colorized: false,
+    }
+}